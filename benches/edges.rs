@@ -0,0 +1,77 @@
+// Construction and traversal of a graph whose nodes mostly have 0-3 edges.
+// Run with `cargo bench --bench edges`; prints heap allocations and timings.
+//
+// Baseline: the same bench on the tree just before `Node::edges` became an
+// inline `EdgeList`, against the commit that made it one (release build,
+// three runs each, one machine, so only the allocation counts are exact):
+//
+//                Vec<Edge>                   EdgeList = SmallVec<[Edge; 3]>
+//     build      2141650 allocs, ~300ms      2066651 allocs, ~285ms
+//     order      ~530ms                      ~460ms
+//     reachable  ~530-665ms                  ~430ms
+//
+// `order` and `reachable` allocate the same in both; the saving is the one
+// edge buffer per node with edges. Later bookkeeping (sequences, provenance)
+// makes today's `build` slower than either, so rerun both sides to compare.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use dag::Dag;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const NODES: usize = 100_000;
+
+fn key(i: usize) -> String {
+    format!("n{:06}", i)
+}
+
+// Node i links forward to between zero and three of the next few nodes.
+fn build() -> Dag {
+    let mut dag = Dag::new();
+    for i in 0..NODES {
+        dag.add(&key(i), i);
+    }
+    for i in 0..NODES {
+        for step in [1, 7, 13].into_iter().take(i % 4) {
+            if i + step < NODES {
+                dag.add_edge_directed(&key(i), &key(i + step));
+            }
+        }
+    }
+    dag
+}
+
+fn measure<T>(label: &str, run: impl FnOnce() -> T) -> T {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    let value = black_box(run());
+    let elapsed = started.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{:<12} {:>10.2?} {:>10} allocations", label, elapsed, allocations);
+    value
+}
+
+fn main() {
+    let dag = measure("build", build);
+    measure("order", || dag.topological_order().unwrap().len());
+    measure("reachable", || (0..NODES).step_by(9_973).filter(|i| dag.is_reachable(&key(1), &key(*i))).count());
+}
@@ -0,0 +1,67 @@
+use crate::{borrow, Dag, DagError};
+
+// For graphs where repeating an edge means more of it, e.g. co-occurrence
+// counts: the weights combine instead of a second edge being stored.
+impl Dag {
+    // Adds `from -> to` with `weight`, or if the edge is already there sets
+    // its weight to `merge(old, weight)`, keeping whether it is soft.
+    // Returns the edge's weight afterwards. Panics if either node is missing.
+    pub fn add_edge_merge<F>(&mut self, from_key: &str, to_key: &str, weight: i32, merge: F) -> i32
+        where F: FnOnce(i32, i32) -> i32 {
+        self.try_add_edge_merge(from_key, to_key, weight, merge).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Only a new edge counts against the edge quotas. A merged weight is
+    // written to an attached store like any other edge change.
+    pub fn try_add_edge_merge<F>(&mut self, from_key: &str, to_key: &str, weight: i32, merge: F) -> Result<i32, DagError>
+        where F: FnOnce(i32, i32) -> i32 {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        for key in [from_key, to_key] {
+            if self.get(key).is_none() {
+                return Err(DagError::NodeNotFound(key.to_string()));
+            }
+        }
+        let from_node = self.get(from_key).expect("Checked above");
+        let merged = {
+            let mut borrowed_node = borrow::write(&from_node, from_key, "add_edge_merge")?;
+            let existing = borrowed_node.edges.iter()
+                .position(|edge| edge.to_key == to_key && self.live_target(edge).is_some());
+            existing.map(|position| {
+                let edge = &mut borrowed_node.edges[position];
+                edge.weight = merge(edge.weight, weight);
+                edge.weight
+            })
+        };
+        if let Some(merged) = merged {
+            self.mark_stored_dirty(&[from_key]);
+            return Ok(merged);
+        }
+        self.check_edge_quota(&[from_key])?;
+        self.try_link(from_key, to_key, weight)?;
+        Ok(weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStore;
+
+    #[test]
+    fn repeated_edges_accumulate() {
+        let mut dag = Dag::new();
+        for word in ["rust", "graph", "cargo"] {
+            dag.add(word, ());
+        }
+        for (a, b) in [("rust", "graph"), ("rust", "cargo"), ("rust", "graph"), ("rust", "graph")] {
+            dag.add_edge_merge(a, b, 1, |old, new| old + new);
+        }
+        assert_eq!(dag.edge_weight("rust", "graph"), Some(3));
+        assert_eq!(dag.successors("rust").len(), 2);
+        dag.attach_store(Box::new(MemoryStore::default()));
+        dag.flush().unwrap();
+        assert_eq!(dag.add_edge_merge("rust", "cargo", 5, i32::max), 5);
+        assert!(dag.has_unflushed_changes());
+        assert_eq!(dag.try_add_edge_merge("rust", "missing", 1, i32::max), Err(DagError::NodeNotFound("missing".to_string())));
+    }
+}
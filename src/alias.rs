@@ -0,0 +1,89 @@
+use std::borrow::Cow;
+
+use crate::{Dag, DagError};
+
+impl Dag {
+    // Every lookup and mutation given `alias` acts on `target` instead, so
+    // edge definitions written against an old key keep working after a
+    // rename. Aliasing an alias points straight at its final target.
+    pub fn alias(&mut self, alias: &str, target: &str) -> Result<(), DagError> {
+        let target = self.resolve_key(target).into_owned();
+        if !self.nodes.borrow().contains_key(&target) {
+            return Err(DagError::NodeNotFound(target));
+        }
+        let alias = self.normalize_key(alias).into_owned();
+        if alias == target || self.nodes.borrow().contains_key(&alias) {
+            return Err(DagError::DuplicateNode(alias));
+        }
+        for existing in self.aliases.values_mut().filter(|existing| **existing == alias) {
+            *existing = target.clone();
+        }
+        self.aliases.insert(alias, target);
+        Ok(())
+    }
+
+    pub fn unalias(&mut self, alias: &str) -> bool {
+        let alias = self.normalize_key(alias).into_owned();
+        self.aliases.remove(&alias).is_some()
+    }
+
+    pub fn aliases_of(&self, key: &str) -> Vec<String> {
+        let key = self.resolve_key(key);
+        let mut aliases: Vec<String> = self.aliases.iter()
+            .filter(|(_, target)| **target == key)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    // The key the graph stores `key` under, after normalization and aliases.
+    pub fn resolve_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let key = self.normalize_key(key);
+        match self.aliases.get(key.as_ref()) {
+            Some(target) => Cow::Owned(target.clone()),
+            None => key,
+        }
+    }
+
+    pub(crate) fn drop_aliases_to(&mut self, key: &str) {
+        self.aliases.retain(|_, target| target != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_follow_aliases() {
+        let mut dag = Dag::new();
+        dag.add("orders-v2", 1);
+        dag.add("report", 2);
+        dag.alias("orders", "orders-v2").unwrap();
+        dag.alias("legacy-orders", "orders").unwrap();
+        assert_eq!(dag.aliases_of("orders-v2"), vec!["legacy-orders", "orders"]);
+
+        dag.add_edge_directed("legacy-orders", "report");
+        assert_eq!(dag.successors("orders-v2"), vec!["report".to_string()]);
+        dag.update("orders", 3);
+        assert_eq!(format!("{:?}", dag.get("orders-v2").unwrap().borrow().data), "3");
+        assert_eq!(dag.alias("report", "orders"), Err(DagError::DuplicateNode("report".to_string())));
+
+        assert!(dag.remove("orders"));
+        assert!(dag.get("legacy-orders").is_none());
+        assert!(dag.aliases_of("legacy-orders").is_empty());
+    }
+
+    #[test]
+    fn fingerprints_follow_aliases() {
+        let mut dag = Dag::new();
+        dag.add("orders", 0);
+        dag.alias("orders-v1", "orders").unwrap();
+        assert!(dag.update_fingerprinted("orders", 1));
+        assert!(dag.fingerprint("orders-v1").is_some());
+        dag.invalidated.clear();
+        assert!(!dag.update_fingerprinted("orders-v1", 1));
+        assert!(dag.invalidated.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Dag, DagError};
+
+// `start` and everything reachable from it along `next`.
+fn closure(start: Vec<String>, next: &BTreeMap<String, Vec<(String, i32)>>) -> BTreeSet<String> {
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut stack = start;
+    while let Some(key) = stack.pop() {
+        if seen.insert(key.clone()) {
+            stack.extend(next.get(&key).into_iter().flatten().map(|(to_key, _)| to_key.clone()));
+        }
+    }
+    seen
+}
+
+impl Dag {
+    // Nodes no entry point leads to, in key order.
+    pub fn unreachable_from(&self, roots: &[&str]) -> Result<Vec<String>, DagError> {
+        let topology = self.topology();
+        let successors = topology.keys()
+            .map(|key| (key.clone(), topology.successors(key).to_vec()))
+            .collect();
+        let reached = closure(self.declared(roots)?, &successors);
+        Ok(topology.keys().filter(|key| !reached.contains(*key)).cloned().collect())
+    }
+
+    // Nodes that lead to none of the declared outputs, in key order.
+    pub fn dead_ends(&self, sinks: &[&str]) -> Result<Vec<String>, DagError> {
+        let topology = self.topology();
+        let reached = closure(self.declared(sinks)?, &topology.predecessors());
+        Ok(topology.keys().filter(|key| !reached.contains(*key)).cloned().collect())
+    }
+
+    fn declared(&self, keys: &[&str]) -> Result<Vec<String>, DagError> {
+        keys.iter()
+            .map(|key| {
+                let key = self.resolve_key(key).into_owned();
+                match self.get(&key) {
+                    Some(_) => Ok(key),
+                    None => Err(DagError::NodeNotFound(key)),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_configuration_is_reported() {
+        let mut dag = Dag::new();
+        for key in ["source", "clean", "publish", "legacy_feed", "scratch"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("source", "clean");
+        dag.add_edge_directed("clean", "publish");
+        dag.add_edge_directed("legacy_feed", "clean");
+        dag.add_edge_directed("clean", "scratch");
+
+        assert_eq!(dag.unreachable_from(&["source"]).unwrap(), vec!["legacy_feed".to_string()]);
+        assert_eq!(dag.dead_ends(&["publish"]).unwrap(), vec!["scratch".to_string()]);
+        assert_eq!(dag.dead_ends(&["missing"]), Err(DagError::NodeNotFound("missing".to_string())));
+    }
+}
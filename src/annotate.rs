@@ -0,0 +1,64 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use crate::{borrow, Dag, DagError, Node};
+
+// One value per node from a single sweep in dependency order. The values
+// are a snapshot: edits afterwards don't update them, and the next
+// `annotate` replaces them all.
+impl Dag {
+    // `compute` gets each node with its direct predecessors' values and the
+    // weight of the edge from each, sorted by key. Nothing is stored if the
+    // graph has a cycle.
+    pub fn annotate<T, F>(&mut self, mut compute: F) -> Result<(), DagError>
+        where T: 'static, F: FnMut(&Node, &[(&str, i32, &T)]) -> T {
+        let topology = self.try_topology("annotate")?;
+        let order = topology.topological_order().map_err(|err| err.during("annotate"))?;
+        let predecessors = topology.predecessors();
+        let mut values: HashMap<String, T> = HashMap::new();
+        for key in order {
+            let node = self.get(&key).expect("Topology node missing");
+            let mut inputs: Vec<(&str, i32, &T)> = predecessors[&key].iter()
+                .map(|(input, weight)| (input.as_str(), *weight, &values[input]))
+                .collect();
+            inputs.sort_by(|a, b| a.0.cmp(b.0));
+            let value = compute(&*borrow::read(&node, &key, "annotate")?, &inputs);
+            values.insert(key, value);
+        }
+        self.annotations = values.into_iter().map(|(key, value)| (key, Box::new(value) as Box<dyn Any>)).collect();
+        Ok(())
+    }
+
+    // `None` if `key` wasn't annotated or was annotated with another type.
+    pub fn annotation<T: 'static>(&self, key: &str) -> Option<&T> {
+        self.annotations.get(self.resolve_key(key).as_ref()).and_then(|value| value.downcast_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earliest_finish_in_one_sweep() {
+        let mut dag = Dag::new();
+        for (key, duration) in [("fetch", 3), ("compile", 5), ("test", 2), ("docs", 1)] {
+            dag.add(key, duration);
+        }
+        dag.add_edge_directed("fetch", "compile");
+        dag.add_edge_directed("compile", "test");
+        dag.add_edge_directed("fetch", "docs");
+        dag.link("docs", "test", 4);
+
+        dag.annotate(|node, inputs: &[(&str, i32, &i32)]| {
+            let duration: i32 = format!("{:?}", node.data).parse().unwrap();
+            inputs.iter().map(|(_, weight, finish)| **finish + weight - 1).max().unwrap_or(0) + duration
+        }).unwrap();
+        assert_eq!(dag.annotation::<i32>("compile"), Some(&8));
+        assert_eq!(dag.annotation::<i32>("test"), Some(&10));
+        assert_eq!(dag.annotation::<String>("test"), None);
+
+        dag.remove("test");
+        assert_eq!(dag.annotation::<i32>("test"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use crate::{borrow, Dag, DagError, NodeData};
+
+impl Dag {
+    // A copy with every key passed through `rename` and every payload
+    // through `scrub`, both called once per node in key order. Edges keep
+    // their weights, softness and probabilities, and invalidated nodes stay
+    // invalidated; everything else keyed by name (aliases, views, groups
+    // and so on) is left behind. Two keys renamed alike are an error.
+    pub fn anonymize<K, S, T>(&self, mut rename: K, mut scrub: S) -> Result<Dag, DagError>
+        where K: FnMut(&str) -> String, S: FnMut(&NodeData) -> T, T: Debug + 'static {
+        let topology = self.try_topology("anonymize")?;
+        let mut renamed: BTreeMap<&str, String> = BTreeMap::new();
+        let mut copy = Dag::new();
+        for key in topology.keys() {
+            let new_key = rename(key);
+            if copy.get(&new_key).is_some() {
+                return Err(DagError::DuplicateNode(new_key).during("anonymize"));
+            }
+            let node = self.get(key).expect("Topology node missing");
+            let data = scrub(&*borrow::read(&node, key, "anonymize")?.data);
+            copy.insert_boxed(&new_key, Box::new(data));
+            renamed.insert(key, new_key);
+        }
+        for (key, new_key) in renamed.iter() {
+            for (next, weight) in topology.successors(key) {
+                let new_next = &renamed[next.as_str()];
+                copy.link(new_key, new_next, *weight);
+                if topology.is_soft(key, next) {
+                    copy.set_edge_soft(new_key, new_next, true);
+                }
+                if let Some(probability) = self.edge_probability(key, next).filter(|probability| *probability != 1.0) {
+                    copy.set_edge_probability(new_key, new_next, probability);
+                }
+            }
+        }
+        copy.invalidated = self.invalidated.iter()
+            .filter_map(|key| renamed.get(key.as_str()).cloned())
+            .collect();
+        Ok(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_payloads_are_replaced() {
+        let mut dag = Dag::new();
+        dag.add("customers.db", "postgres://admin:hunter2@db");
+        dag.add("billing-report", "acme corp");
+        dag.add("audit", ());
+        dag.link("customers.db", "billing-report", 5);
+        dag.add_soft_edge("billing-report", "audit");
+        dag.update("customers.db", "postgres://admin:hunter3@db");
+
+        let mut count = 0;
+        let anonymized = dag.anonymize(|_| {
+            count += 1;
+            format!("node-{}", count)
+        }, |data| format!("{:?}", data).len()).unwrap();
+        let mut expected = Dag::new();
+        expected.add("node-1", 2);
+        expected.add("node-2", 11);
+        expected.add("node-3", 29);
+        expected.link("node-3", "node-2", 5);
+        expected.add_soft_edge("node-2", "node-1");
+        expected.update("node-3", 29);
+        crate::assert_dag_eq!(anonymized, expected);
+
+        let Err(err) = dag.anonymize(|_| "same".to_string(), |_| ()) else { panic!("Colliding keys accepted") };
+        assert_eq!(err.root(), &DagError::DuplicateNode("same".to_string()));
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::{Dag, DagError};
+
+// Per-node Bloom labels over descendant sets. A negative answer from
+// `may_reach` is exact; a positive one can be confirmed with `reaches`, which
+// only walks into successors whose labels still admit the target.
+#[derive(Debug, Clone)]
+pub struct ReachabilityFilter {
+    bits: usize,
+    hashes: usize,
+    labels: HashMap<String, Vec<u64>>,
+}
+
+impl ReachabilityFilter {
+    fn sized(false_positive_rate: f64, expected_descendants: usize) -> ReachabilityFilter {
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let items = expected_descendants.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(items * rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hashes = ((bits as f64 / items) * ln2).round().max(1.0) as usize;
+        ReachabilityFilter { bits, hashes, labels: HashMap::new() }
+    }
+
+    pub fn bits_per_node(&self) -> usize {
+        self.bits
+    }
+
+    pub fn may_reach(&self, from_key: &str, to_key: &str) -> bool {
+        match self.labels.get(from_key) {
+            Some(label) => self.positions(to_key).all(|bit| label[bit / 64] & (1 << (bit % 64)) != 0),
+            None => false,
+        }
+    }
+
+    pub fn reaches(&self, dag: &Dag, from_key: &str, to_key: &str) -> bool {
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut stack = vec![from_key.to_string()];
+        while let Some(key) = stack.pop() {
+            if key == to_key {
+                return true;
+            }
+            if self.may_reach(&key, to_key) && visited.insert(key.clone()) {
+                stack.extend(dag.successors(&key));
+            }
+        }
+        false
+    }
+
+    // Double hashing: bit i is h1 + i * h2.
+    fn positions(&self, key: &str) -> impl Iterator<Item = usize> + '_ {
+        let mut first = DefaultHasher::new();
+        key.hash(&mut first);
+        let h1 = first.finish();
+        let mut second = DefaultHasher::new();
+        (key, 0x9e37_79b9_u32).hash(&mut second);
+        let h2 = second.finish() | 1;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % self.bits as u64) as usize)
+    }
+
+    fn label_for(&self, key: &str) -> Vec<u64> {
+        let mut label = vec![0u64; self.bits.div_ceil(64)];
+        for bit in self.positions(key) {
+            label[bit / 64] |= 1 << (bit % 64);
+        }
+        label
+    }
+}
+
+impl Dag {
+    pub fn reachability_filter(&self, false_positive_rate: f64, expected_descendants: usize) -> Result<ReachabilityFilter, DagError> {
+        let topology = self.topology();
+        let mut filter = ReachabilityFilter::sized(false_positive_rate, expected_descendants);
+        for key in topology.topological_order()?.into_iter().rev() {
+            let mut label = filter.label_for(&key);
+            for (next, _) in topology.successors(&key) {
+                for (word, other) in label.iter_mut().zip(filter.labels[next].iter()) {
+                    *word |= *other;
+                }
+            }
+            filter.labels.insert(key, label);
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_never_misses_reachable_nodes() {
+        let mut dag = Dag::new();
+        for i in 0..50 {
+            dag.add(&format!("n{}", i), i);
+        }
+        for i in 0..49 {
+            if i % 10 != 9 {
+                dag.add_edge_directed(&format!("n{}", i), &format!("n{}", i + 1));
+            }
+        }
+        let filter = dag.reachability_filter(0.01, 10).unwrap();
+        for i in 0..50 {
+            for j in 0..50 {
+                let (from, to) = (format!("n{}", i), format!("n{}", j));
+                let exact = dag.reaches(&from, &to);
+                if exact {
+                    assert!(filter.may_reach(&from, &to));
+                }
+                assert_eq!(filter.reaches(&dag, &from, &to), exact);
+            }
+        }
+    }
+
+    #[test]
+    fn filter_size_follows_false_positive_rate() {
+        let dag = Dag::new();
+        let loose = dag.reachability_filter(0.1, 100).unwrap();
+        let tight = dag.reachability_filter(0.001, 100).unwrap();
+        assert!(tight.bits_per_node() > loose.bits_per_node());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Dag;
+
+// `edges!["a" => "b", "b" => "c"]`, for `assert_subgraph!`.
+#[macro_export]
+macro_rules! edges {
+    ($($from:expr => $to:expr),* $(,)?) => {
+        [$(($from.to_string(), $to.to_string())),*]
+    };
+}
+
+// Compares everything a snapshot holds and lists each difference on a line
+// of its own when the graphs don't match.
+#[macro_export]
+macro_rules! assert_dag_eq {
+    ($actual:expr, $expected:expr $(,)?) => {{
+        let differences = $actual.snapshot_diff(&$expected);
+        if !differences.is_empty() {
+            panic!("graphs differ (- expected, + actual):\n{}", differences.join("\n"));
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! assert_subgraph {
+    ($dag:expr, contains: $edges:expr $(,)?) => {{
+        let missing = $dag.missing_edges(&$edges);
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.iter().map(|(from, to)| format!("  {} -> {}", from, to)).collect();
+            panic!("graph lacks {} expected edge(s):\n{}", missing.len(), missing.join("\n"));
+        }
+    }};
+}
+
+impl Dag {
+    // What it takes to turn `expected` into this graph, one line each: `-`
+    // for what only `expected` has, `+` for what only this graph has, and
+    // both for a payload, weight or mark that changed. Empty when they
+    // match.
+    pub fn snapshot_diff(&self, expected: &Dag) -> Vec<String> {
+        let (actual, expected) = (self.to_stored_graph(), expected.to_stored_graph());
+        let mut lines = vec![];
+        let nodes = |nodes: &[(String, String)]| -> BTreeMap<String, String> { nodes.iter().cloned().collect() };
+        let (now, before) = (nodes(&actual.nodes), nodes(&expected.nodes));
+        for key in before.keys().chain(now.keys()).collect::<BTreeSet<_>>() {
+            match (before.get(key), now.get(key)) {
+                (Some(old), Some(new)) if old != new => {
+                    lines.push(format!("- node {} = {}", key, old));
+                    lines.push(format!("+ node {} = {}", key, new));
+                },
+                (Some(old), None) => lines.push(format!("- node {} = {}", key, old)),
+                (None, Some(new)) => lines.push(format!("+ node {} = {}", key, new)),
+                _ => {},
+            }
+        }
+        let edges = |edges: &[(String, String, i32)]| -> BTreeMap<(String, String), i32> {
+            edges.iter().map(|(from, to, weight)| ((from.clone(), to.clone()), *weight)).collect()
+        };
+        let (now, before) = (edges(&actual.edges), edges(&expected.edges));
+        for edge in before.keys().chain(now.keys()).collect::<BTreeSet<_>>() {
+            let line = |sign: &str, weight: &i32| format!("{} edge {} -> {} (weight {})", sign, edge.0, edge.1, weight);
+            match (before.get(edge), now.get(edge)) {
+                (Some(old), Some(new)) if old != new => {
+                    lines.push(line("-", old));
+                    lines.push(line("+", new));
+                },
+                (Some(old), None) => lines.push(line("-", old)),
+                (None, Some(new)) => lines.push(line("+", new)),
+                _ => {},
+            }
+        }
+        let marks = |stored: &crate::StoredGraph| -> BTreeSet<String> { stored.invalidated.iter().cloned().collect() };
+        let (now, before) = (marks(&actual), marks(&expected));
+        lines.extend(before.difference(&now).map(|key| format!("- invalidated {}", key)));
+        lines.extend(now.difference(&before).map(|key| format!("+ invalidated {}", key)));
+        lines
+    }
+
+    // The `(from, to)` pairs among `edges` that this graph has no live edge
+    // for, in the order given.
+    pub fn missing_edges(&self, edges: &[(String, String)]) -> Vec<(String, String)> {
+        edges.iter().filter(|(from, to)| self.edge_weight(from, to).is_none()).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("extract", 1);
+        dag.add("load", 2).depends_on("extract");
+        dag
+    }
+
+    #[test]
+    fn differences_are_listed_line_by_line() {
+        let expected = pipeline();
+        let mut actual = pipeline();
+        assert_dag_eq!(actual, expected);
+        assert_subgraph!(actual, contains: edges!["extract" => "load"]);
+
+        actual.add("load", 3);
+        actual.add("audit", ()).depends_on("load");
+        assert_eq!(actual.snapshot_diff(&expected), vec![
+            "+ node audit = ()",
+            "- node load = 2",
+            "+ node load = 3",
+            "+ edge load -> audit (weight 1)",
+            "+ invalidated load",
+        ]);
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            assert_subgraph!(expected, contains: edges!["load" => "audit"]);
+        }));
+        let message = panic.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(*message, "graph lacks 1 expected edge(s):\n  load -> audit");
+    }
+}
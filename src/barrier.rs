@@ -0,0 +1,77 @@
+use crate::{borrow, Dag};
+
+// A barrier runs when invalidated but holds back everything below it: its
+// dependents are only dirtied once the barrier is released. It holds again
+// the next time it runs.
+impl Dag {
+    // Returns false if `key` isn't in the graph. Clearing a barrier that is
+    // holding releases it first.
+    pub fn set_barrier(&mut self, key: &str, barrier: bool) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        if barrier {
+            self.barriers.insert(key);
+        } else {
+            self.release_barrier(&key);
+            self.barriers.remove(&key);
+        }
+        true
+    }
+
+    pub fn is_barrier(&self, key: &str) -> bool {
+        self.barriers.contains(self.resolve_key(key).as_ref())
+    }
+
+    // Barriers that have run since they were last released, by key.
+    pub fn held_barriers(&self) -> Vec<String> {
+        self.held_barriers.iter().cloned().collect()
+    }
+
+    // Invalidates what the barrier held back, for the next dispatch to run.
+    // Returns false if it wasn't holding anything.
+    pub fn release_barrier(&mut self, key: &str) -> bool {
+        let key: &str = &self.resolve_key(key);
+        if !self.held_barriers.remove(key) {
+            return false;
+        }
+        let node = self.get(key).expect("Held barrier missing");
+        let held: Vec<String> = borrow::or_panic(borrow::read(&node, key, "release_barrier")).edges.iter()
+            .filter(|edge| !edge.soft && self.live_target(edge).is_some())
+            .map(|edge| edge.to_key.clone())
+            .collect();
+        self.invalidated.extend(held);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_changes_wait_for_approval() {
+        let mut dag = Dag::new();
+        for key in ["build", "approve", "deploy"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("build", "approve");
+        dag.add_edge_directed("approve", "deploy");
+        assert!(dag.set_barrier("approve", true));
+        assert!(!dag.set_barrier("missing", true));
+
+        dag.update("build", ());
+        let mut seen = vec![];
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.key().to_string())).unwrap();
+        assert_eq!(seen, vec!["build", "approve"]);
+        assert_eq!(dag.held_barriers(), vec!["approve".to_string()]);
+
+        assert!(dag.release_barrier("approve"));
+        assert!(!dag.release_barrier("approve"));
+        seen.clear();
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.key().to_string())).unwrap();
+        assert_eq!(seen, vec!["deploy"]);
+        assert!(dag.held_barriers().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::btree_set;
+use std::collections::VecDeque;
+
+use crate::{borrow, Dag};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeRecord {
+    pub from: String,
+    pub to: String,
+    pub weight: i32,
+    pub soft: bool,
+}
+
+// Live edges in source key order, `chunk_size` at a time. Each node is only
+// borrowed while its own edges are copied out, so callbacks may hold a batch
+// for as long as they like.
+pub struct EdgeChunks<'a> {
+    dag: &'a Dag,
+    keys: btree_set::Iter<'a, String>,
+    pending: VecDeque<EdgeRecord>,
+    chunk_size: usize,
+}
+
+impl Iterator for EdgeChunks<'_> {
+    type Item = Vec<EdgeRecord>;
+
+    fn next(&mut self) -> Option<Vec<EdgeRecord>> {
+        while self.pending.len() < self.chunk_size {
+            let Some(key) = self.keys.next() else { break };
+            let node = self.dag.get(key).expect("Indexed node missing");
+            let borrowed_node = borrow::or_panic(borrow::read(&node, key, "edges_chunked"));
+            self.pending.extend(borrowed_node.edges.iter()
+                .filter(|edge| self.dag.live_target(edge).is_some())
+                .map(|edge| EdgeRecord {
+                    from: key.clone(),
+                    to: edge.to_key.clone(),
+                    weight: edge.weight,
+                    soft: edge.soft,
+                }));
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        let take = self.chunk_size.min(self.pending.len());
+        Some(self.pending.drain(..take).collect())
+    }
+}
+
+impl Dag {
+    // Every batch but the last holds exactly `chunk_size` records.
+    pub fn edges_chunked(&self, chunk_size: usize) -> EdgeChunks<'_> {
+        assert!(chunk_size > 0, "Edge batches need room for at least one edge");
+        EdgeChunks { dag: self, keys: self.index.iter(), pending: VecDeque::new(), chunk_size }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_are_full_except_the_last() {
+        let mut dag = Dag::new();
+        for key in ["a", "b", "c", "d"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("a", "b");
+        dag.add_edge_directed("a", "c");
+        dag.add_soft_edge("b", "d");
+        dag.link("c", "d", 3);
+        dag.add_edge_directed("a", "d");
+        dag.remove("d");
+        dag.add("d", ());
+
+        let batches: Vec<Vec<EdgeRecord>> = dag.edges_chunked(2).collect();
+        assert_eq!(batches.iter().map(|batch| batch.len()).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(batches[0][1], EdgeRecord { from: "a".to_string(), to: "c".to_string(), weight: 1, soft: false });
+
+        dag.add_soft_edge("b", "d");
+        dag.link("c", "d", 3);
+        let records: Vec<EdgeRecord> = dag.edges_chunked(3).flatten().collect();
+        assert_eq!(dag.edges_chunked(3).map(|batch| batch.len()).collect::<Vec<_>>(), vec![3, 1]);
+        assert_eq!(records[2], EdgeRecord { from: "b".to_string(), to: "d".to_string(), weight: 1, soft: true });
+        assert_eq!(records[3].weight, 3);
+    }
+}
@@ -0,0 +1,60 @@
+use std::cell::{Ref, RefMut};
+
+use crate::{DagError, Node, NodeStrongRef};
+
+// Nodes are handed out as shared RefCells, so a caller still holding a
+// borrow from `get` can collide with the graph borrowing the same node. With
+// the `no_panic` feature these collisions become a `BorrowConflict` naming
+// the node and the operation: methods returning `Result` return it, and the
+// rest panic with it as their message. Without the feature these are plain
+// RefCell borrows.
+pub(crate) fn read<'a>(node: &'a NodeStrongRef, key: &str, operation: &'static str) -> Result<Ref<'a, Node>, DagError> {
+    #[cfg(feature = "no_panic")]
+    return node.try_borrow().map_err(|_| conflict(key, operation));
+    #[cfg(not(feature = "no_panic"))]
+    {
+        let _ = (key, operation);
+        Ok(node.borrow())
+    }
+}
+
+pub(crate) fn write<'a>(node: &'a NodeStrongRef, key: &str, operation: &'static str) -> Result<RefMut<'a, Node>, DagError> {
+    #[cfg(feature = "no_panic")]
+    return node.try_borrow_mut().map_err(|_| conflict(key, operation));
+    #[cfg(not(feature = "no_panic"))]
+    {
+        let _ = (key, operation);
+        Ok(node.borrow_mut())
+    }
+}
+
+pub(crate) fn or_panic<T>(result: Result<T, DagError>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[cfg(feature = "no_panic")]
+fn conflict(key: &str, operation: &'static str) -> DagError {
+    DagError::BorrowConflict { key: key.to_string(), operation }
+}
+
+#[cfg(all(test, feature = "no_panic"))]
+mod tests {
+    use crate::{Dag, DagError};
+
+    #[test]
+    fn held_borrows_become_errors() {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        let node = dag.get("A").unwrap();
+        let guard = node.borrow_mut();
+        assert_eq!(
+            dag.try_add_edge("A", "B"),
+            Err(DagError::BorrowConflict { key: "A".to_string(), operation: "add_edge" })
+        );
+        assert_eq!(dag.critical_path().err().unwrap().keys(), vec!["A"]);
+        drop(guard);
+        dag.try_add_edge("A", "B").unwrap();
+        assert_eq!(dag.critical_path().unwrap(), vec!["A", "B"]);
+    }
+}
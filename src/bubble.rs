@@ -0,0 +1,70 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{borrow, Dag, DagError, Node};
+
+impl Dag {
+    // The mirror image of `annotate`: values start at `start_keys` and flow
+    // against the edges, each ancestor computed once from the values of its
+    // successors that have one, sorted by key. Only the start keys and
+    // their ancestors get a value.
+    pub fn bubble_up<T, F>(&self, start_keys: &[&str], mut compute: F) -> Result<BTreeMap<String, T>, DagError>
+        where F: FnMut(&Node, &[(&str, i32, &T)]) -> T {
+        let topology = self.try_topology("bubble_up")?;
+        let predecessors = topology.predecessors();
+        let mut region: HashSet<String> = HashSet::new();
+        let mut stack = vec![];
+        for key in start_keys {
+            let key = self.resolve_key(key).into_owned();
+            if !topology.contains(&key) {
+                return Err(DagError::NodeNotFound(key).during("bubble_up"));
+            }
+            stack.push(key);
+        }
+        while let Some(key) = stack.pop() {
+            if region.insert(key.clone()) {
+                stack.extend(predecessors[&key].iter().map(|(input, _)| input.clone()));
+            }
+        }
+        let order = topology.topological_order().map_err(|err| err.during("bubble_up"))?;
+        let mut values: BTreeMap<String, T> = BTreeMap::new();
+        for key in order.into_iter().rev().filter(|key| region.contains(key)) {
+            let node = self.get(&key).expect("Topology node missing");
+            let mut children: Vec<(&str, i32, &T)> = topology.successors(&key).iter()
+                .filter_map(|(child, weight)| values.get(child).map(|value| (child.as_str(), *weight, value)))
+                .collect();
+            children.sort_by(|a, b| a.0.cmp(b.0));
+            let value = compute(&*borrow::read(&node, &key, "bubble_up")?, &children);
+            values.insert(key, value);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Failing test counts rolled up an ownership tree.
+    #[test]
+    fn failures_roll_up_to_owners() {
+        let mut dag = Dag::new();
+        for (key, failures) in [("org", 0), ("storage", 0), ("web", 0), ("disk-tests", 2), ("cache-tests", 1), ("ui-tests", 4)] {
+            dag.add(key, failures);
+        }
+        dag.add_edge_directed("org", "storage");
+        dag.add_edge_directed("org", "web");
+        dag.add_edge_directed("storage", "disk-tests");
+        dag.add_edge_directed("storage", "cache-tests");
+        dag.add_edge_directed("web", "ui-tests");
+
+        let totals = dag.bubble_up(&["disk-tests", "cache-tests"], |node, children: &[(&str, i32, &i32)]| {
+            let own: i32 = format!("{:?}", node.data).parse().unwrap();
+            own + children.iter().map(|(_, _, total)| **total).sum::<i32>()
+        }).unwrap();
+        assert_eq!(totals.keys().collect::<Vec<_>>(), vec!["cache-tests", "disk-tests", "org", "storage"]);
+        assert_eq!((totals["storage"], totals["org"]), (3, 3));
+
+        let err = dag.bubble_up(&["gone"], |_, _: &[(&str, i32, &i32)]| 0).unwrap_err();
+        assert_eq!(err.root(), &DagError::NodeNotFound("gone".to_string()));
+    }
+}
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::BuildHasher;
+use std::rc::Rc;
+use std::thread;
+
+use crate::{Dag, DagError, Node, NodeStrongRef};
+
+type Shard<'a> = HashMap<&'a str, usize, RandomState>;
+
+// Key positions split across one map per worker by key hash, so the maps
+// fill in parallel and are only read afterwards.
+struct ShardedIndex<'a> {
+    hasher: RandomState,
+    shards: Vec<Shard<'a>>,
+}
+
+impl ShardedIndex<'_> {
+    fn position(&self, key: &str) -> Option<usize> {
+        let shard = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.shards[shard].get(key).copied()
+    }
+}
+
+impl Dag {
+    // Builds the same graph as adding every node and then every edge in
+    // order, with weight 1. Nodes hold `Rc`s and can't be made off-thread,
+    // so the workers do the hashing, duplicate detection and edge
+    // resolution; the final pass only links nodes by position. Fails on a
+    // repeated key or an edge naming a missing node.
+    pub fn build_parallel<T, N, E>(nodes: N, edges: E, threads: usize) -> Result<Dag, DagError>
+        where T: Debug + 'static, N: IntoIterator<Item = (String, T)>, E: IntoIterator<Item = (String, String)> {
+        assert!(threads > 0, "A parallel build needs at least one thread");
+        let (keys, payloads): (Vec<String>, Vec<T>) = nodes.into_iter().unzip();
+        let edges: Vec<(String, String)> = edges.into_iter().collect();
+        let index = shard_keys(&keys, threads).map_err(|err| err.during("build_parallel"))?;
+        let resolved = resolve_edges(&index, &edges, threads).map_err(|err| err.during("build_parallel"))?;
+
+        let mut out_degrees = vec![0; keys.len()];
+        for (from, _) in resolved.iter() {
+            out_degrees[*from] += 1;
+        }
+        let node_refs: Vec<NodeStrongRef> = keys.iter().zip(payloads).zip(out_degrees)
+            .map(|((key, data), out_degree)| {
+                let mut node = Node::new(key.clone(), Box::new(data));
+                node.edges.reserve_exact(out_degree);
+                Rc::new(RefCell::new(node))
+            })
+            .collect();
+        let mut dag = Dag::new();
+        dag.index = keys.iter().cloned().collect();
+        for key in keys.iter() {
+            dag.ids.assign(key);
+            let sequence = dag.next_sequence();
+            dag.sequences.get_mut().insert(key.clone(), sequence);
+        }
+        for (from, to) in resolved {
+            let mut from_node = node_refs[from].borrow_mut();
+            from_node.push_edge(Rc::clone(&node_refs[to]), &keys[to], 1);
+            from_node.edges.last_mut().expect("Edge just pushed").sequence = dag.next_sequence();
+        }
+        *dag.nodes.get_mut() = keys.into_iter().zip(node_refs).collect();
+        Ok(dag)
+    }
+}
+
+fn shard_keys(keys: &[String], threads: usize) -> Result<ShardedIndex<'_>, DagError> {
+    let hasher = RandomState::new();
+    let chunk_size = keys.len().div_ceil(threads).max(1);
+    // Each worker sorts its chunk's positions by shard, then each shard's
+    // worker merges what every chunk sorted for it, in input order.
+    let buckets: Vec<Vec<Vec<usize>>> = thread::scope(|scope| {
+        let workers: Vec<_> = keys.chunks(chunk_size).enumerate()
+            .map(|(chunk, slice)| {
+                let hasher = &hasher;
+                scope.spawn(move || {
+                    let mut buckets = vec![vec![]; threads];
+                    for (offset, key) in slice.iter().enumerate() {
+                        buckets[hasher.hash_one(key.as_str()) as usize % threads].push(chunk * chunk_size + offset);
+                    }
+                    buckets
+                })
+            })
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("Key sharding worker panicked")).collect()
+    });
+    let shards: Vec<Result<Shard<'_>, DagError>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|shard| {
+                let (buckets, hasher) = (&buckets, &hasher);
+                scope.spawn(move || {
+                    let mut positions = Shard::with_capacity_and_hasher(keys.len() / threads, hasher.clone());
+                    for position in buckets.iter().flat_map(|chunk| chunk[shard].iter()) {
+                        let key = keys[*position].as_str();
+                        if positions.insert(key, *position).is_some() {
+                            return Err(DagError::DuplicateNode(key.to_string()));
+                        }
+                    }
+                    Ok(positions)
+                })
+            })
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("Key sharding worker panicked")).collect()
+    });
+    Ok(ShardedIndex { hasher, shards: shards.into_iter().collect::<Result<_, _>>()? })
+}
+
+// Reports the first missing endpoint in edge order.
+fn resolve_edges(index: &ShardedIndex<'_>, edges: &[(String, String)], threads: usize) -> Result<Vec<(usize, usize)>, DagError> {
+    let chunk_size = edges.len().div_ceil(threads).max(1);
+    let chunks: Vec<Result<Vec<(usize, usize)>, DagError>> = thread::scope(|scope| {
+        let workers: Vec<_> = edges.chunks(chunk_size)
+            .map(|slice| scope.spawn(move || {
+                slice.iter()
+                    .map(|(from, to)| {
+                        let position = |key: &String| index.position(key).ok_or_else(|| DagError::NodeNotFound(key.clone()));
+                        Ok((position(from)?, position(to)?))
+                    })
+                    .collect()
+            }))
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("Edge resolution worker panicked")).collect()
+    });
+    let mut resolved = Vec::with_capacity(edges.len());
+    for chunk in chunks {
+        resolved.extend(chunk?);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sequential_construction() {
+        let nodes: Vec<(String, usize)> = (0..200).map(|i| (format!("n{}", i), i)).collect();
+        let edges: Vec<(String, String)> = (0..200)
+            .flat_map(|i| [(i, (i * 7 + 3) % 200), (i, (i + 1) % 200)])
+            .filter(|(from, to)| from < to)
+            .map(|(from, to)| (format!("n{}", from), format!("n{}", to)))
+            .collect();
+        let mut expected = Dag::new();
+        for (key, data) in nodes.iter() {
+            expected.add(key, *data);
+        }
+        for (from, to) in edges.iter() {
+            expected.add_edge_directed(from, to);
+        }
+
+        let built = Dag::build_parallel(nodes.clone(), edges.clone(), 4).unwrap();
+        crate::assert_dag_eq!(built, expected);
+        assert_eq!(built.successors("n10"), expected.successors("n10"));
+        assert_eq!(built.id_of("n57"), expected.id_of("n57"));
+        assert_eq!(built.edge_sequence("n10", "n11"), expected.edge_sequence("n10", "n11"));
+
+        let mut repeated = nodes.clone();
+        repeated.push(("n3".to_string(), 0));
+        let Err(err) = Dag::build_parallel(repeated, edges.clone(), 3) else { panic!("Duplicate key accepted") };
+        assert_eq!(err.root(), &DagError::DuplicateNode("n3".to_string()));
+        let mut dangling = edges;
+        dangling.push(("n1".to_string(), "gone".to_string()));
+        let Err(err) = Dag::build_parallel(nodes, dangling, 1) else { panic!("Dangling edge accepted") };
+        assert_eq!(err.root(), &DagError::NodeNotFound("gone".to_string()));
+    }
+}
@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{Dag, DagError, NodeStrongRef};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Eviction {
+    #[default]
+    LeastRecentlyUsed,
+    LeastFrequentlyUsed,
+}
+
+// With `write_through` every mutation is flushed as it happens instead of
+// waiting for an explicit `flush`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub capacity: usize,
+    pub eviction: Eviction,
+    pub write_through: bool,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy {
+            capacity: 1024,
+            eviction: Eviction::default(),
+            write_through: false,
+        }
+    }
+}
+
+// Stands in for a payload that currently only lives in the store.
+struct Evicted;
+
+impl fmt::Debug for Evicted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<evicted>")
+    }
+}
+
+// Keys and edges always stay in memory; only payloads are paged out, so
+// edges into a cold node never go stale.
+pub(crate) struct PayloadCache {
+    policy: CachePolicy,
+    clock: u64,
+    usage: HashMap<String, (u64, u64)>,
+    cold: HashSet<String>,
+}
+
+impl PayloadCache {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        let usage = self.usage.entry(key.to_string()).or_insert((0, 0));
+        usage.0 = self.clock;
+        usage.1 += 1;
+    }
+}
+
+impl Dag {
+    pub fn set_cache_policy(&mut self, policy: Option<CachePolicy>) -> Result<(), DagError> {
+        if self.store.is_none() {
+            return Err(DagError::Store("no store attached".to_string()));
+        }
+        if policy.is_none() {
+            self.page_in_all();
+        }
+        let attached = self.store.as_mut().expect("Store checked above");
+        attached.cache = match (attached.cache.take(), policy) {
+            (_, None) => None,
+            (Some(cache), Some(policy)) => {
+                let mut cache = cache.into_inner();
+                cache.policy = policy;
+                Some(RefCell::new(cache))
+            },
+            (None, Some(policy)) => Some(RefCell::new(PayloadCache {
+                policy,
+                clock: 0,
+                usage: HashMap::new(),
+                cold: HashSet::new(),
+            })),
+        };
+        Ok(())
+    }
+
+    pub fn cache_policy(&self) -> Option<CachePolicy> {
+        self.payload_cache().map(|cache| cache.borrow().policy)
+    }
+
+    pub fn is_resident(&self, key: &str) -> bool {
+        self.nodes.borrow().contains_key(key) && !self.is_cold(key)
+    }
+
+    pub fn resident_count(&self) -> usize {
+        match self.payload_cache() {
+            Some(cache) => self.nodes.borrow().len() - cache.borrow().cold.len(),
+            None => self.nodes.borrow().len(),
+        }
+    }
+
+    pub(crate) fn is_cold(&self, key: &str) -> bool {
+        self.payload_cache().is_some_and(|cache| cache.borrow().cold.contains(key))
+    }
+
+    pub(crate) fn writes_through(&self) -> bool {
+        self.payload_cache().is_some_and(|cache| cache.borrow().policy.write_through)
+    }
+
+    // Counts as a use of the node, and reads its payload back from the store
+    // if it was evicted. A node that is borrowed elsewhere is left as is.
+    pub(crate) fn page_in(&self, node: &NodeStrongRef) {
+        let (Some(attached), Some(cache)) = (self.store.as_ref(), self.payload_cache()) else {
+            return;
+        };
+        let Ok(mut borrowed) = node.try_borrow_mut() else {
+            return;
+        };
+        let mut cache = cache.borrow_mut();
+        cache.touch(&borrowed.key);
+        if cache.cold.contains(&borrowed.key) {
+            if let Ok(Some(payload)) = attached.backend.load_payload(&borrowed.key) {
+                borrowed.data = (attached.decode)(&payload);
+                cache.cold.remove(&borrowed.key);
+            }
+        }
+    }
+
+    pub(crate) fn touch_cached(&self, keys: &[&str]) {
+        if let Some(cache) = self.payload_cache() {
+            let mut cache = cache.borrow_mut();
+            for key in keys {
+                cache.touch(key);
+            }
+        }
+    }
+
+    // Only called right after a successful flush, when every payload in
+    // memory also exists in the store.
+    pub(crate) fn evict_to_capacity(&self) {
+        let Some(cache) = self.payload_cache() else {
+            return;
+        };
+        let mut cache = cache.borrow_mut();
+        cache.usage.retain(|key, _| self.nodes.borrow().contains_key(key));
+        cache.cold.retain(|key| self.nodes.borrow().contains_key(key));
+        let resident = self.nodes.borrow().len() - cache.cold.len();
+        if resident <= cache.policy.capacity {
+            return;
+        }
+        let nodes = self.nodes.borrow();
+        let mut candidates: Vec<(u64, u64, &String)> = nodes.keys()
+            .filter(|key| !cache.cold.contains(*key))
+            .map(|key| {
+                let (last_used, uses) = cache.usage.get(key).copied().unwrap_or((0, 0));
+                match cache.policy.eviction {
+                    Eviction::LeastRecentlyUsed => (last_used, 0, key),
+                    Eviction::LeastFrequentlyUsed => (uses, last_used, key),
+                }
+            })
+            .collect();
+        candidates.sort();
+        let mut excess = resident - cache.policy.capacity;
+        for (_, _, key) in candidates {
+            if excess == 0 {
+                break;
+            }
+            if let Ok(mut node) = nodes[key].try_borrow_mut() {
+                node.data = Box::new(Evicted);
+                cache.cold.insert(key.clone());
+                cache.usage.remove(key);
+                excess -= 1;
+            }
+        }
+    }
+
+    pub(crate) fn page_in_all(&self) {
+        let cold: Vec<String> = match self.payload_cache() {
+            Some(cache) => cache.borrow().cold.iter().cloned().collect(),
+            None => return,
+        };
+        for key in cold {
+            let node = self.nodes.borrow().get(&key).cloned();
+            if let Some(node) = node {
+                self.page_in(&node);
+            }
+        }
+    }
+
+    fn payload_cache(&self) -> Option<&RefCell<PayloadCache>> {
+        self.store.as_ref().and_then(|attached| attached.cache.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStore;
+
+    fn cached(capacity: usize, eviction: Eviction) -> Dag {
+        let mut dag = Dag::new();
+        dag.attach_store(Box::new(MemoryStore::default()));
+        dag.set_cache_policy(Some(CachePolicy { capacity, eviction, write_through: true })).unwrap();
+        for (key, data) in [("A", 1), ("B", 2), ("C", 3), ("D", 4)] {
+            dag.add(key, data);
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.add_edge_directed("C", "D");
+        dag
+    }
+
+    #[test]
+    fn cold_payloads_reload_through_get() {
+        let mut dag = cached(2, Eviction::LeastRecentlyUsed);
+        dag.get("A");
+        dag.flush().unwrap();
+        assert_eq!(dag.resident_count(), 2);
+        assert!(dag.is_resident("A"));
+        assert!(!dag.has_unflushed_changes());
+
+        let cold = ["B", "C", "D"].into_iter().find(|key| !dag.is_resident(key)).unwrap();
+        let node = dag.get(cold).unwrap();
+        assert!(dag.is_resident(cold));
+        assert_ne!(format!("{:?}", node.borrow().data), "<evicted>");
+        assert_eq!(dag.successors("A"), vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn frequency_policy_keeps_hot_nodes() {
+        let mut dag = cached(1, Eviction::LeastFrequentlyUsed);
+        for _ in 0..50 {
+            dag.get("D");
+        }
+        dag.flush().unwrap();
+        assert!(dag.is_resident("D"));
+        assert_eq!(dag.resident_count(), 1);
+
+        dag.set_cache_policy(None).unwrap();
+        assert_eq!(dag.resident_count(), 4);
+        assert_eq!(format!("{:?}", dag.get("A").unwrap().borrow().data), "1");
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Centrality {
+    // How many root-to-leaf chains run through the node.
+    Betweenness,
+    // How many nodes depend on it, directly or not.
+    Reach,
+    // Summed edge weight of every chain leaving it, so a heavy edge counts
+    // once for each chain behind it.
+    Influence,
+}
+
+impl Dag {
+    // The `top_k` most central nodes, highest first and by key among equal
+    // scores. Fails if the graph has a cycle, since none of the measures is
+    // defined on one.
+    pub fn centrality(&self, kind: Centrality, top_k: usize) -> Result<Vec<(String, f64)>, DagError> {
+        let topology = self.try_topology("centrality")?;
+        let order = topology.topological_order().map_err(|err| err.during("centrality"))?;
+        let mut scores: BTreeMap<&String, f64> = BTreeMap::new();
+        match kind {
+            Centrality::Betweenness => {
+                let mut from_roots: BTreeMap<&String, f64> = BTreeMap::new();
+                for key in order.iter() {
+                    let paths = *from_roots.entry(key).or_insert(1.0);
+                    for (next, _) in topology.successors(key) {
+                        let entry = from_roots.entry(next).or_insert(0.0);
+                        *entry += paths;
+                    }
+                }
+                for key in order.iter().rev() {
+                    let successors = topology.successors(key);
+                    let to_leaves = if successors.is_empty() {
+                        1.0
+                    } else {
+                        successors.iter().map(|(next, _)| scores[next]).sum()
+                    };
+                    scores.insert(key, to_leaves);
+                }
+                for (key, score) in scores.iter_mut() {
+                    *score *= from_roots[key];
+                }
+            },
+            Centrality::Reach => {
+                let mut below: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+                for key in order.iter().rev() {
+                    let mut reached = BTreeSet::new();
+                    for (next, _) in topology.successors(key) {
+                        reached.insert(next);
+                        reached.extend(below[next].iter().copied());
+                    }
+                    scores.insert(key, reached.len() as f64);
+                    below.insert(key, reached);
+                }
+            },
+            Centrality::Influence => {
+                let mut chains: BTreeMap<&String, f64> = BTreeMap::new();
+                for key in order.iter().rev() {
+                    let (mut influence, mut count) = (0.0, 1.0);
+                    for (next, weight) in topology.successors(key) {
+                        influence += *weight as f64 * chains[next] + scores[next];
+                        count += chains[next];
+                    }
+                    chains.insert(key, count);
+                    scores.insert(key, influence);
+                }
+            },
+        }
+        let mut ranked: Vec<(String, f64)> = scores.into_iter().map(|(key, score)| (key.clone(), score)).collect();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked.truncate(top_k);
+        Ok(ranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two services share `auth`, which in turn sits on `db`.
+    fn services() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["db", "cache", "auth", "billing", "search"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("db", "auth");
+        dag.add_edge_directed("cache", "auth");
+        dag.link("auth", "billing", 3);
+        dag.add_edge_directed("auth", "search");
+        dag.add_edge_directed("cache", "search");
+        dag
+    }
+
+    #[test]
+    fn shared_dependencies_rank_first() {
+        let dag = services();
+        assert_eq!(dag.centrality(Centrality::Betweenness, 2).unwrap(), vec![
+            ("auth".to_string(), 4.0),
+            ("cache".to_string(), 3.0),
+        ]);
+        assert_eq!(dag.centrality(Centrality::Reach, 1).unwrap(), vec![("cache".to_string(), 3.0)]);
+        assert_eq!(dag.centrality(Centrality::Influence, 3).unwrap(), vec![
+            ("cache".to_string(), 8.0),
+            ("db".to_string(), 7.0),
+            ("auth".to_string(), 4.0),
+        ]);
+    }
+}
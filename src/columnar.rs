@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, BooleanArray, Int32Array, StringArray, UInt32Array, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+
+use crate::{Dag, DagError};
+
+fn export_error(err: impl std::fmt::Display) -> DagError {
+    DagError::Export(err.to_string())
+}
+
+impl Dag {
+    // One row per node, ordered by key. Payloads are exported in their Debug
+    // rendering since that is all the graph knows about them.
+    pub fn nodes_record_batch(&self) -> Result<RecordBatch, DagError> {
+        let keys: Vec<&String> = self.index.iter().collect();
+        let ids: Vec<u64> = keys.iter().map(|key| self.id_of(key).expect("Node without an id").index() as u64).collect();
+        let data: Vec<String> = keys.iter().map(|key| format!("{:?}", self.get(key).expect("Indexed node missing").borrow().data)).collect();
+        let out_degrees: Vec<u32> = keys.iter().map(|key| self.successors(key).len() as u32).collect();
+        let invalidated: Vec<bool> = keys.iter().map(|key| self.invalidated.contains(*key)).collect();
+        let placeholders: Vec<bool> = keys.iter().map(|key| self.placeholders.contains(*key)).collect();
+
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::UInt64, false),
+            Field::new("key", DataType::Utf8, false),
+            Field::new("data", DataType::Utf8, false),
+            Field::new("out_degree", DataType::UInt32, false),
+            Field::new("invalidated", DataType::Boolean, false),
+            Field::new("placeholder", DataType::Boolean, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(ids)),
+            Arc::new(StringArray::from_iter_values(keys)),
+            Arc::new(StringArray::from(data)),
+            Arc::new(UInt32Array::from(out_degrees)),
+            Arc::new(BooleanArray::from(invalidated)),
+            Arc::new(BooleanArray::from(placeholders)),
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns).map_err(export_error)
+    }
+
+    pub fn edges_record_batch(&self) -> Result<RecordBatch, DagError> {
+        let topology = self.topology();
+        let edges: Vec<(&String, &String, i32)> = topology.keys()
+            .flat_map(|from_key| topology.successors(from_key).iter().map(move |(to_key, weight)| (from_key, to_key, *weight)))
+            .collect();
+        let schema = Schema::new(vec![
+            Field::new("from", DataType::Utf8, false),
+            Field::new("to", DataType::Utf8, false),
+            Field::new("weight", DataType::Int32, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(edges.iter().map(|(from_key, _, _)| from_key))),
+            Arc::new(StringArray::from_iter_values(edges.iter().map(|(_, to_key, _)| to_key))),
+            Arc::new(Int32Array::from_iter_values(edges.iter().map(|(_, _, weight)| *weight))),
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns).map_err(export_error)
+    }
+
+    #[cfg(feature = "parquet")]
+    pub fn write_nodes_parquet<W>(&self, writer: W) -> Result<(), DagError> where W: std::io::Write + Send {
+        write_parquet(writer, self.nodes_record_batch()?)
+    }
+
+    #[cfg(feature = "parquet")]
+    pub fn write_edges_parquet<W>(&self, writer: W) -> Result<(), DagError> where W: std::io::Write + Send {
+        write_parquet(writer, self.edges_record_batch()?)
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet<W>(writer: W, batch: RecordBatch) -> Result<(), DagError> where W: std::io::Write + Send {
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, batch.schema(), None).map_err(export_error)?;
+    writer.write(&batch).map_err(export_error)?;
+    writer.close().map_err(export_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Array;
+
+    fn graph() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.add_edge_directed("A", "B");
+        dag.update("A", 3);
+        dag
+    }
+
+    #[test]
+    fn tables_describe_nodes_and_edges() {
+        let dag = graph();
+        let nodes = dag.nodes_record_batch().unwrap();
+        assert_eq!(nodes.num_rows(), 2);
+        let data = nodes.column_by_name("data").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(data.value(0), "3");
+        let invalidated = nodes.column_by_name("invalidated").unwrap().as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert!(invalidated.value(0) && !invalidated.value(1));
+
+        let edges = dag.edges_record_batch().unwrap();
+        assert_eq!(edges.num_rows(), 1);
+        let to = edges.column_by_name("to").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(to.value(0), "B");
+        assert_eq!(to.len(), 1);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn tables_written_as_parquet() {
+        let mut buffer = vec![];
+        graph().write_edges_parquet(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], b"PAR1");
+    }
+}
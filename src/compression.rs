@@ -0,0 +1,130 @@
+use std::io::{Read, Write};
+
+use crate::interchange::MAGIC;
+use crate::{Dag, DagError};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Binary,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+fn export_error(err: impl std::fmt::Display) -> DagError {
+    DagError::Export(err.to_string())
+}
+
+fn import_error(err: impl std::fmt::Display) -> DagError {
+    DagError::Import(err.to_string())
+}
+
+fn decompress(raw: &[u8]) -> Result<Vec<u8>, DagError> {
+    let mut bytes = vec![];
+    #[cfg(feature = "gzip")]
+    if raw.starts_with(GZIP_MAGIC) {
+        flate2::read::GzDecoder::new(raw).read_to_end(&mut bytes).map_err(import_error)?;
+        return Ok(bytes);
+    }
+    #[cfg(feature = "zstd")]
+    if raw.starts_with(ZSTD_MAGIC) {
+        zstd::stream::copy_decode(raw, &mut bytes).map_err(import_error)?;
+        return Ok(bytes);
+    }
+    let detail = match raw {
+        _ if raw.starts_with(GZIP_MAGIC) => "gzip support is not enabled",
+        _ if raw.starts_with(ZSTD_MAGIC) => "zstd support is not enabled",
+        _ => "not a compressed graph",
+    };
+    Err(import_error(detail))
+}
+
+impl Dag {
+    pub fn write_compressed<W>(&self, writer: W, format: SnapshotFormat, compression: Compression)
+        -> Result<(), DagError> where W: Write {
+        let bytes = match format {
+            SnapshotFormat::Binary => self.to_bytes(),
+            #[cfg(feature = "json")]
+            SnapshotFormat::Json => self.to_json().into_bytes(),
+        };
+        let written = match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                encoder.write_all(&bytes).and_then(|_| encoder.finish().map(|_| ()))
+            },
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::stream::copy_encode(&bytes[..], writer, 0),
+        };
+        written.map_err(export_error).map_err(|err| err.during("write_compressed"))
+    }
+
+    // The compression and the format inside it are both recognised from
+    // their leading bytes.
+    pub fn read_compressed<R>(mut reader: R) -> Result<Dag, DagError> where R: Read {
+        let mut raw = vec![];
+        reader.read_to_end(&mut raw).map_err(import_error).map_err(|err| err.during("read_compressed"))?;
+        let bytes = decompress(&raw).map_err(|err| err.during("read_compressed"))?;
+        if bytes.starts_with(MAGIC) {
+            return Dag::from_bytes(&bytes);
+        }
+        #[cfg(feature = "json")]
+        if let Ok(text) = std::str::from_utf8(&bytes) {
+            return Dag::from_json(text);
+        }
+        Err(import_error("not a binary graph").during("read_compressed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repetitive() -> Dag {
+        let mut dag = Dag::new();
+        for i in 0..200 {
+            dag.add(&format!("warehouse/analytics/daily/partition-{:03}", i), i);
+            if i > 0 {
+                dag.add_edge_directed(&format!("warehouse/analytics/daily/partition-{:03}", i - 1),
+                    &format!("warehouse/analytics/daily/partition-{:03}", i));
+            }
+        }
+        dag
+    }
+
+    #[test]
+    fn compressed_snapshots_round_trip() {
+        let dag = repetitive();
+        let expected = dag.to_stored_graph();
+        let compressions = [
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ];
+        for compression in compressions {
+            let mut written = vec![];
+            dag.write_compressed(&mut written, SnapshotFormat::Binary, compression).unwrap();
+            assert!(written.len() * 4 < dag.to_bytes().len());
+            assert_eq!(Dag::read_compressed(&written[..]).unwrap().to_stored_graph(), expected);
+            #[cfg(feature = "json")]
+            {
+                written.clear();
+                dag.write_compressed(&mut written, SnapshotFormat::Json, compression).unwrap();
+                assert_eq!(Dag::read_compressed(&written[..]).unwrap().to_stored_graph(), expected);
+            }
+        }
+        let err = Dag::read_compressed(&dag.to_bytes()[..]).err().unwrap();
+        assert_eq!(err.root(), &DagError::Import("not a compressed graph".to_string()));
+    }
+}
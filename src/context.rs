@@ -0,0 +1,108 @@
+use std::cell::Ref;
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+
+use crate::{borrow, Dag, DagError, DispatchId, Node, NodeStrongRef};
+
+// What a `dispatch_with_ctx` callback sees: the node being dispatched and
+// read access to the nodes feeding it, all of which are clean by then.
+pub struct Ctx<'a> {
+    dag: &'a Dag,
+    key: String,
+    node: NodeStrongRef,
+    id: DispatchId,
+    inputs: Vec<(String, i32, NodeStrongRef)>,
+}
+
+impl Ctx<'_> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn node(&self) -> NodeStrongRef {
+        self.node.clone()
+    }
+
+    pub fn id(&self) -> DispatchId {
+        self.id
+    }
+
+    // Direct predecessors with the weight of their edge, sorted by key.
+    pub fn inputs(&self) -> Vec<(&str, i32)> {
+        self.inputs.iter().map(|(key, weight, _)| (key.as_str(), *weight)).collect()
+    }
+
+    // `None` unless `key` feeds this node directly.
+    pub fn input(&self, key: &str) -> Option<Ref<'_, Node>> {
+        let key: &str = &self.dag.resolve_key(key);
+        self.inputs.iter()
+            .find(|(input, _, _)| input == key)
+            .map(|(input, _, node)| borrow::or_panic(borrow::read(node, input, "input")))
+    }
+
+    // As `Dag::data` for the node being dispatched.
+    pub fn data<V: 'static>(&self) -> Option<Rc<V>> {
+        self.dag.data(&self.key)
+    }
+}
+
+impl Dag {
+    // Visits the dirty region in dependency order, so every input a callback
+    // reads through its `Ctx` has already been dispatched in this run or was
+    // never dirty. Nothing runs if the region has a cycle.
+    pub fn dispatch_with_ctx<F>(&mut self, mut callback: F) -> Result<DispatchId, DagError> where F: FnMut(&Ctx) {
+        let order = self.dirty_order()?;
+        self.resolve_references(&order.iter().map(|key| key.as_str()).collect::<Vec<_>>());
+        let predecessors = self.topology().predecessors();
+        let id = self.next_dispatch_id();
+        let mut dispatched: HashSet<String> = HashSet::new();
+        for key in order {
+            let Some(ctx) = self.context(&key, id, &predecessors) else { continue };
+            callback(&ctx);
+            dispatched.insert(key);
+        }
+        self.mark_dispatched(dispatched, id);
+        self.clear_invalidated();
+        Ok(id)
+    }
+
+    pub(crate) fn context(&self, key: &str, id: DispatchId, predecessors: &BTreeMap<String, Vec<(String, i32)>>)
+        -> Option<Ctx<'_>> {
+        let node = self.get(key)?;
+        let mut inputs: Vec<(String, i32, NodeStrongRef)> = predecessors.get(key).into_iter().flatten()
+            .filter_map(|(input, weight)| self.get(input).map(|node| (input.clone(), *weight, node)))
+            .collect();
+        inputs.sort_by(|a, b| a.0.cmp(&b.0));
+        Some(Ctx { dag: self, key: key.to_string(), node, id, inputs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callbacks_read_their_inputs_through_the_context() {
+        let mut dag = Dag::new();
+        dag.add("price", 4);
+        dag.add("quantity", 3);
+        dag.add("total", 0);
+        dag.add_edge_directed("price", "total");
+        dag.add_edge_directed("quantity", "total");
+        dag.update("price", 5);
+
+        let mut seen = vec![];
+        let id = dag.dispatch_with_ctx(|ctx| {
+            let inputs: Vec<String> = ctx.inputs().iter()
+                .map(|(key, _)| format!("{}={:?}", key, ctx.input(key).unwrap().data))
+                .collect();
+            seen.push((ctx.key().to_string(), inputs));
+            assert!(ctx.input("total").is_none());
+        }).unwrap();
+        assert_eq!(seen, vec![
+            ("price".to_string(), vec![]),
+            ("total".to_string(), vec!["price=5".to_string(), "quantity=3".to_string()]),
+        ]);
+        assert_eq!(dag.last_dispatched("total"), Some(id));
+    }
+}
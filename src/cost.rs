@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    TopologicalSort,
+    CriticalPath,
+    LayerAssignment,
+    Traverse { from: String },
+    Reachability { from: String, to: String },
+    TransitiveClosure,
+    AllPaths { from: String, to: String },
+    Layout,
+    Dispatch,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+impl CostEstimate {
+    pub fn total(&self) -> usize {
+        self.nodes.saturating_add(self.edges)
+    }
+}
+
+impl Dag {
+    // Cheap approximations of the work an operation will do. Anything that
+    // would itself be expensive to estimate is bounded from above instead.
+    pub fn estimate_cost(&self, operation: &Operation) -> Result<CostEstimate, DagError> {
+        let node_count = self.nodes.borrow().len();
+        let edge_count = self.edge_count();
+        let linear = CostEstimate { nodes: node_count, edges: edge_count };
+        let estimate = match operation {
+            Operation::TopologicalSort | Operation::CriticalPath | Operation::LayerAssignment => linear,
+            Operation::Traverse { from } | Operation::Reachability { from, .. } => {
+                self.require(from)?;
+                self.probe_region(&[from.as_str()])
+            },
+            Operation::Dispatch => {
+                let roots: Vec<&str> = self.invalidated.iter().map(|key| key.as_str()).collect();
+                self.probe_region(&roots)
+            },
+            Operation::TransitiveClosure => {
+                // Every edge merges a descendant set; assume those average
+                // half the graph.
+                let reach = node_count.div_ceil(2).max(1);
+                CostEstimate { nodes: node_count, edges: edge_count.saturating_mul(reach) }
+            },
+            Operation::AllPaths { from, to } => {
+                self.require(from)?;
+                self.require(to)?;
+                let paths = self.saturating_path_count(from, to)?;
+                let depth = self.layer_assignment(None)?.len();
+                CostEstimate { nodes: paths.saturating_mul(depth), edges: paths.saturating_mul(depth) }
+            },
+            Operation::Layout => {
+                const SWEEPS: usize = 24;
+                CostEstimate { nodes: node_count.saturating_mul(SWEEPS), edges: edge_count.saturating_mul(SWEEPS) }
+            },
+        };
+        Ok(estimate)
+    }
+
+    fn require(&self, key: &str) -> Result<(), DagError> {
+        match self.get(key) {
+            Some(_) => Ok(()),
+            None => Err(DagError::NodeNotFound(key.to_string())),
+        }
+    }
+
+    // Walks at most PROBE_LIMIT nodes; a region larger than that is assumed
+    // to cover the whole graph.
+    fn probe_region(&self, roots: &[&str]) -> CostEstimate {
+        const PROBE_LIMIT: usize = 256;
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = roots.iter().map(|key| key.to_string()).collect();
+        let mut edges = 0;
+        while let Some(key) = stack.pop() {
+            if visited.len() >= PROBE_LIMIT {
+                return CostEstimate { nodes: self.nodes.borrow().len(), edges: self.edge_count() };
+            }
+            if visited.insert(key.clone()) {
+                let successors = self.successors(&key);
+                edges += successors.len();
+                stack.extend(successors);
+            }
+        }
+        CostEstimate { nodes: visited.len(), edges }
+    }
+
+    fn saturating_path_count(&self, from_key: &str, to_key: &str) -> Result<usize, DagError> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let topology = self.topology();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        counts.insert(from_key.to_string(), 1);
+        for key in topology.topological_order()? {
+            let count = counts.get(&key).copied().unwrap_or(0);
+            if count == 0 {
+                continue;
+            }
+            for (next, _) in topology.successors(&key) {
+                let entry = counts.entry(next.clone()).or_insert(0);
+                *entry = entry.saturating_add(count);
+            }
+        }
+        Ok(counts.get(to_key).copied().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fan(width: usize) -> Dag {
+        let mut dag = Dag::new();
+        dag.add("root", 0);
+        dag.add("sink", 0);
+        for i in 0..width {
+            let key = format!("mid-{}", i);
+            dag.add(&key, i);
+            dag.add_edge_directed("root", &key);
+            dag.add_edge_directed(&key, "sink");
+        }
+        dag
+    }
+
+    #[test]
+    fn linear_operations_touch_everything_once() {
+        let dag = fan(3);
+        let estimate = dag.estimate_cost(&Operation::TopologicalSort).unwrap();
+        assert_eq!(estimate, CostEstimate { nodes: 5, edges: 6 });
+    }
+
+    #[test]
+    fn traversal_estimate_is_regional() {
+        let dag = fan(3);
+        let estimate = dag.estimate_cost(&Operation::Traverse { from: "mid-0".to_string() }).unwrap();
+        assert_eq!(estimate, CostEstimate { nodes: 2, edges: 1 });
+        assert!(dag.estimate_cost(&Operation::Traverse { from: "missing".to_string() }).is_err());
+    }
+
+    #[test]
+    fn all_paths_scales_with_path_count() {
+        let small = fan(2).estimate_cost(&Operation::AllPaths { from: "root".to_string(), to: "sink".to_string() }).unwrap();
+        let large = fan(8).estimate_cost(&Operation::AllPaths { from: "root".to_string(), to: "sink".to_string() }).unwrap();
+        assert_eq!(large.total(), small.total() * 4);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::Dag;
+
+#[derive(Default)]
+pub(crate) struct CoverageLog {
+    nodes: BTreeSet<String>,
+    edges: BTreeSet<(String, String)>,
+}
+
+// What the recorded dispatches and walks (`traverse`, `ancestors_iter`,
+// `Traverser::walk` and what is built on them) exercised, measured against
+// the graph as it is now. An edge counts once both its ends ran in the same
+// dispatch, or once a walk followed it. Answers served from the query cache
+// walk nothing, so they record nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub visited_nodes: Vec<String>,
+    pub unvisited_nodes: Vec<String>,
+    pub visited_edges: Vec<(String, String)>,
+    pub unvisited_edges: Vec<(String, String)>,
+}
+
+impl Coverage {
+    pub fn is_complete(&self) -> bool {
+        self.unvisited_nodes.is_empty() && self.unvisited_edges.is_empty()
+    }
+}
+
+impl Dag {
+    // Starts recording with an empty log; turning it off discards the log.
+    pub fn set_coverage_tracking(&mut self, enabled: bool) {
+        *self.coverage.get_mut() = enabled.then(CoverageLog::default);
+    }
+
+    // `None` unless tracking is on.
+    pub fn coverage(&self) -> Option<Coverage> {
+        let log = self.coverage.borrow();
+        let log = log.as_ref()?;
+        let (visited_nodes, unvisited_nodes) = self.index.iter().cloned().partition(|key| log.nodes.contains(key));
+        let edges: Vec<(String, String)> = self.index.iter()
+            .flat_map(|from| self.successors(from).into_iter().map(move |to| (from.clone(), to)))
+            .collect();
+        let (visited_edges, unvisited_edges) = edges.into_iter().partition(|edge| log.edges.contains(edge));
+        Some(Coverage { visited_nodes, unvisited_nodes, visited_edges, unvisited_edges })
+    }
+
+    pub(crate) fn record_coverage(&mut self, keys: &HashSet<String>) {
+        let Some(mut log) = self.coverage.get_mut().take() else { return };
+        for key in keys {
+            log.edges.extend(self.successors(key).into_iter()
+                .filter(|to| keys.contains(to))
+                .map(|to| (key.clone(), to)));
+        }
+        log.nodes.extend(keys.iter().cloned());
+        *self.coverage.get_mut() = Some(log);
+    }
+
+    pub(crate) fn cover_node(&self, key: &str) {
+        if let Some(log) = self.coverage.borrow_mut().as_mut() {
+            log.nodes.insert(key.to_string());
+        }
+    }
+
+    pub(crate) fn cover_edge(&self, from_key: &str, to_key: &str) {
+        if let Some(log) = self.coverage.borrow_mut().as_mut() {
+            log.edges.insert((from_key.to_string(), to_key.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untested_branches_show_up() {
+        let mut dag = Dag::new();
+        for key in ["input", "fast", "slow", "output"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("input", "fast");
+        dag.add_edge_directed("input", "slow");
+        dag.add_edge_directed("fast", "output");
+        dag.add_edge_directed("slow", "output");
+        assert_eq!(dag.coverage(), None);
+        dag.set_coverage_tracking(true);
+
+        dag.update("fast", ());
+        dag.dispatch_with_ctx(|_| ()).unwrap();
+        dag.update("slow", ());
+        dag.dispatch(|_| ());
+        let coverage = dag.coverage().unwrap();
+        assert_eq!(coverage.unvisited_nodes, vec!["input".to_string()]);
+        assert_eq!(coverage.unvisited_edges, vec![
+            ("input".to_string(), "fast".to_string()),
+            ("input".to_string(), "slow".to_string()),
+        ]);
+
+        dag.update("input", ());
+        dag.try_dispatch(|_| Ok::<(), ()>(())).unwrap();
+        assert!(dag.coverage().unwrap().is_complete());
+    }
+
+    #[test]
+    fn walks_count_too() {
+        let mut dag = Dag::new();
+        for key in ["input", "fast", "slow", "output"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("input", "fast");
+        dag.add_edge_directed("input", "slow");
+        dag.add_edge_directed("fast", "output");
+        dag.set_coverage_tracking(true);
+
+        assert_eq!(dag.descendants("fast"), vec!["output".to_string()]);
+        assert_eq!(dag.ancestors_iter("slow").count(), 1);
+        let coverage = dag.coverage().unwrap();
+        assert_eq!(coverage.visited_nodes, vec!["fast", "input", "output"]);
+        assert_eq!(coverage.unvisited_edges, vec![("input".to_string(), "fast".to_string())]);
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use crate::{Ctx, Dag, DagError, DispatchId};
+
+// The values a `dispatch_dataflow` run produced, one per dispatched node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataflowRun<T> {
+    pub id: DispatchId,
+    pub results: BTreeMap<String, T>,
+}
+
+impl Dag {
+    // As `dispatch_with_ctx`, with each callback returning its node's value.
+    // A callback also gets the values its direct inputs returned earlier in
+    // the same run, sorted by key; inputs that were clean have none.
+    pub fn dispatch_dataflow<T, F>(&mut self, mut callback: F) -> Result<DataflowRun<T>, DagError>
+        where F: FnMut(&Ctx, &[(&str, &T)]) -> T {
+        let mut results: BTreeMap<String, T> = BTreeMap::new();
+        let id = self.dispatch_with_ctx(|ctx| {
+            let upstream: Vec<(&str, &T)> = ctx.inputs().into_iter()
+                .filter_map(|(key, _)| results.get(key).map(|value| (key, value)))
+                .collect();
+            let value = callback(ctx, &upstream);
+            results.insert(ctx.key().to_string(), value);
+        })?;
+        Ok(DataflowRun { id, results })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_flow_to_dependents() {
+        let mut dag = Dag::new();
+        for (key, cost) in [("a", 1), ("b", 2), ("sum", 0), ("report", 0)] {
+            dag.add(key, cost);
+        }
+        dag.add_edge_directed("a", "sum");
+        dag.add_edge_directed("b", "sum");
+        dag.add_edge_directed("sum", "report");
+        dag.update("a", 1);
+        dag.update("b", 2);
+
+        let run = dag.dispatch_dataflow(|ctx, upstream: &[(&str, &String)]| match ctx.key() {
+            "report" => format!("total {}", upstream[0].1),
+            "sum" => upstream.iter().map(|(_, value)| value.parse::<i32>().unwrap()).sum::<i32>().to_string(),
+            _ => format!("{:?}", ctx.node().borrow().data),
+        }).unwrap();
+        assert_eq!(run.results["sum"], "3");
+        assert_eq!(run.results["report"], "total 3");
+        assert_eq!(dag.last_dispatched("report"), Some(run.id));
+    }
+}
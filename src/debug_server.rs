@@ -0,0 +1,198 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::{Dag, DagError};
+
+// A request gets this long, start to finish, and this many bytes before it is
+// turned away, so one slow or noisy client can't stall the owning loop.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(1);
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+fn remote_error(err: impl std::fmt::Display) -> DagError {
+    DagError::Remote(err.to_string())
+}
+
+// A read-only HTTP window onto a graph in the same process. The graph can't
+// leave its thread, so the server answers only when `poll` is handed it:
+// call that from the loop that owns the graph.
+//
+//     GET /nodes        keys, rendered payloads, marks and last dispatch ids
+//     GET /edges        every live edge with its weight and softness
+//     GET /invalidated  the invalidated keys
+//     GET /dispatch     the last dispatch id and the nodes it ran
+pub struct DebugServer {
+    listener: TcpListener,
+}
+
+impl DebugServer {
+    pub fn local_addr(&self) -> Result<SocketAddr, DagError> {
+        self.listener.local_addr().map_err(remote_error)
+    }
+
+    // Answers every request already waiting, without blocking for new ones,
+    // and returns how many were answered. A client that hangs up or
+    // misbehaves only loses its own answer; only a failing listener is an
+    // error.
+    pub fn poll(&mut self, dag: &Dag) -> Result<usize, DagError> {
+        let mut answered = 0;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if answer(stream, dag).is_ok() {
+                        answered += 1;
+                    }
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(answered),
+                Err(err) if matches!(err.kind(), ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::Interrupted) => (),
+                Err(err) => return Err(remote_error(err)),
+            }
+        }
+    }
+}
+
+fn answer(mut stream: TcpStream, dag: &Dag) -> Result<(), DagError> {
+    stream.set_nonblocking(false).map_err(remote_error)?;
+    let deadline = Instant::now() + REQUEST_DEADLINE;
+    let mut request = vec![];
+    let mut chunk = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() <= MAX_REQUEST_BYTES {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        stream.set_read_timeout(Some(remaining)).map_err(remote_error)?;
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => request.extend_from_slice(&chunk[..read]),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(err) => return Err(remote_error(err)),
+        }
+    }
+    let too_large = request.len() > MAX_REQUEST_BYTES;
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next().and_then(|path| dag.debug_view(path))) {
+        _ if too_large => ("431 Request Header Fields Too Large", json!({ "error": "request too large" })),
+        (Some("GET"), Some(body)) => ("200 OK", body),
+        (Some("GET"), None) => ("404 Not Found", json!({ "error": "unknown view" })),
+        _ => ("405 Method Not Allowed", json!({ "error": "only GET is supported" })),
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).map_err(remote_error)
+}
+
+impl Dag {
+    pub fn serve_debug<A: ToSocketAddrs>(&self, addr: A) -> Result<DebugServer, DagError> {
+        let listener = TcpListener::bind(addr).map_err(remote_error)?;
+        listener.set_nonblocking(true).map_err(remote_error)?;
+        Ok(DebugServer { listener })
+    }
+
+    fn debug_view(&self, path: &str) -> Option<Value> {
+        let stored = self.to_stored_graph();
+        let mut invalidated: Vec<&String> = self.invalidated.iter().collect();
+        invalidated.sort();
+        let view = match path {
+            "/nodes" => json!(stored.nodes.iter().map(|(key, payload)| json!({
+                "key": key,
+                "payload": payload,
+                "invalidated": self.invalidated.contains(key),
+                "last_dispatched": self.last_dispatched(key).map(|id| id.0),
+            })).collect::<Vec<_>>()),
+            "/edges" => json!(stored.edges.iter().map(|(from, to, weight)| json!({
+                "from": from,
+                "to": to,
+                "weight": weight,
+                "soft": stored.soft_edges.contains(&(from.clone(), to.clone())),
+            })).collect::<Vec<_>>()),
+            "/invalidated" => json!(invalidated),
+            "/dispatch" => {
+                let id = self.last_dispatch_id();
+                let ran: Vec<&String> = self.index.iter().filter(|key| id.is_some() && self.last_dispatched(key) == id).collect();
+                json!({ "id": id.map(|id| id.0), "nodes": ran })
+            },
+            _ => return None,
+        };
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn get(addr: SocketAddr, path: &str) -> thread::JoinHandle<String> {
+        let path = path.to_string();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+    }
+
+    #[test]
+    fn views_reflect_the_live_graph() {
+        let mut dag = Dag::new();
+        dag.add("extract", 12);
+        dag.add("load", ()).depends_on("extract");
+        dag.update("extract", 13);
+        dag.dispatch_with_ctx(|_| ()).unwrap();
+        dag.update("load", ());
+        let mut server = dag.serve_debug("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let mut responses = vec![];
+        for path in ["/dispatch", "/invalidated", "/edges", "/missing"] {
+            let client = get(addr, path);
+            while !client.is_finished() {
+                server.poll(&dag).unwrap();
+                thread::sleep(Duration::from_millis(1));
+            }
+            responses.push(client.join().unwrap());
+        }
+        assert!(responses[0].starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(responses[0].ends_with(r#"{"id":1,"nodes":["extract","load"]}"#));
+        assert!(responses[1].ends_with(r#"["load"]"#));
+        assert!(responses[2].ends_with(r#"[{"from":"extract","soft":false,"to":"load","weight":1}]"#));
+        assert!(responses[3].starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn oversized_and_dropped_requests_do_not_stop_the_server() {
+        let dag = Dag::new();
+        let mut server = dag.serve_debug("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        drop(TcpStream::connect(addr).unwrap());
+        let flood = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let _ = stream.write_all(&vec![b'a'; MAX_REQUEST_BYTES + 1]);
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        });
+        while !flood.is_finished() {
+            server.poll(&dag).unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(flood.join().unwrap().starts_with("HTTP/1.1 431"));
+
+        let client = get(addr, "/invalidated");
+        while !client.is_finished() {
+            server.poll(&dag).unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(client.join().unwrap().ends_with("[]"));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{Dag, Direction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+impl Dag {
+    // Every node with its degree in `direction`, `Both` counting incoming
+    // and outgoing edges together. Equal degrees keep key order either way.
+    pub fn nodes_by_degree(&self, direction: Direction, order: Order) -> Vec<(String, usize)> {
+        let topology = self.topology();
+        let in_degrees = topology.in_degrees();
+        let mut nodes: Vec<(String, usize)> = in_degrees.into_iter()
+            .map(|(key, incoming)| {
+                let outgoing = topology.successors(&key).len();
+                let degree = match direction {
+                    Direction::Outgoing => outgoing,
+                    Direction::Incoming => incoming,
+                    Direction::Both => incoming + outgoing,
+                };
+                (key, degree)
+            })
+            .collect();
+        match order {
+            Order::Ascending => nodes.sort_by_key(|(_, degree)| *degree),
+            Order::Descending => nodes.sort_by_key(|(_, degree)| std::cmp::Reverse(*degree)),
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_fanout_first() {
+        let mut dag = Dag::new();
+        for key in ["hub", "a", "b", "c", "sink"] {
+            dag.add(key, ());
+        }
+        for key in ["a", "b", "c"] {
+            dag.add_edge_directed("hub", key);
+        }
+        dag.add_edge_directed("a", "sink");
+        dag.add_edge_directed("b", "sink");
+
+        let keys = |nodes: Vec<(String, usize)>| nodes.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(dag.nodes_by_degree(Direction::Outgoing, Order::Descending)[0], ("hub".to_string(), 3));
+        assert_eq!(keys(dag.nodes_by_degree(Direction::Incoming, Order::Descending)), vec!["sink", "a", "b", "c", "hub"]);
+        assert_eq!(keys(dag.nodes_by_degree(Direction::Both, Order::Ascending)), vec!["c", "a", "b", "sink", "hub"]);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{Dag, DagError, Direction};
+
+// Edges always point from the node that runs first to the node that runs
+// after it. These spell that out at the call site, unlike `add_edge`, whose
+// parameter names read backwards.
+impl Dag {
+    pub fn add_edge_directed(&mut self, from_key: &str, to_key: &str) {
+        self.get(from_key).expect("Cannot find node to add edge from");
+        self.get(to_key).expect("Cannot find node to add edge to");
+        self.try_add_edge_allowing_cycles(from_key, to_key).unwrap_or_else(|err| panic!("{}", err));
+    }
+
+    // `dependent` runs after `dependency`: adds `dependency -> dependent`.
+    pub fn add_dependency(&mut self, dependent: &str, dependency: &str) {
+        self.add_edge_directed(dependency, dependent);
+    }
+
+    // Checked like `try_add_edge`: quotas and cycles.
+    pub fn try_add_dependency(&mut self, dependent: &str, dependency: &str) -> Result<(), DagError> {
+        self.try_add_edge(dependency, dependent)
+    }
+
+    // Direct neighbours by key: `Outgoing` gives the nodes `key` feeds,
+    // `Incoming` the ones it depends on.
+    pub fn neighbors(&self, key: &str, direction: Direction) -> Vec<String> {
+        let key = self.resolve_key(key);
+        let mut neighbors = self.neighborhood(&key, 1, direction);
+        neighbors.retain(|neighbor| *neighbor != key);
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_spellings_point_the_same_way() {
+        let mut dag = Dag::new();
+        for key in ["extract", "clean", "report"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("extract", "clean");
+        dag.add_dependency("report", "clean");
+
+        assert_eq!(dag.successors("extract"), vec!["clean".to_string()]);
+        assert_eq!(dag.neighbors("clean", Direction::Outgoing), vec!["report".to_string()]);
+        assert_eq!(dag.neighbors("clean", Direction::Incoming), vec!["extract".to_string()]);
+        assert_eq!(dag.neighbors("clean", Direction::Both), vec!["extract".to_string(), "report".to_string()]);
+        assert_eq!(dag.try_add_dependency("report", "load"), Err(DagError::NodeNotFound("load".to_string())));
+        assert!(matches!(dag.try_add_dependency("extract", "report"), Err(DagError::WouldCreateCycle { .. })));
+        assert!(dag.neighbors("extract", Direction::Incoming).is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::topology::Topology;
+use crate::{Dag, DagError, NodeStrongRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DispatchId(pub u64);
+
+impl Dag {
+    // Same visiting order as `dispatch`, with the run's id handed to the
+    // callback so side effects can be tagged with it.
+    pub fn dispatch_with_id<F>(&mut self, mut callback: F) -> DispatchId where F: FnMut(NodeStrongRef, DispatchId) {
+        let id = self.next_dispatch_id();
+        let mut dispatched: HashSet<String> = HashSet::new();
+        let roots: Vec<String> = self.invalidated.iter().cloned().collect();
+        for root in roots {
+            let mut validated: HashSet<String> = HashSet::new();
+            let mut stack = vec![root];
+            while let Some(key) = stack.pop() {
+                let node = match self.get(&key) {
+                    Some(node) if validated.insert(key.clone()) => node,
+                    _ => continue,
+                };
+                self.check_walk(&key, validated.len(), stack.len()).unwrap_or_else(|err| panic!("{}", err));
+                callback(node, id);
+                stack.extend(self.hard_successors(&key).into_iter().rev());
+            }
+            dispatched.extend(validated);
+        }
+        self.mark_dispatched(dispatched, id);
+        self.clear_invalidated();
+        id
+    }
+
+    pub fn last_dispatched(&self, key: &str) -> Option<DispatchId> {
+        self.last_dispatched.get(self.resolve_key(key).as_ref()).copied()
+    }
+
+    pub fn last_dispatch_id(&self) -> Option<DispatchId> {
+        match self.last_dispatch_id {
+            0 => None,
+            id => Some(DispatchId(id)),
+        }
+    }
+
+    // For graphs rebuilt after a restart: ids handed out afterwards are
+    // greater than `id`, so they never collide with ones issued before.
+    pub fn resume_dispatch_ids(&mut self, id: DispatchId) {
+        self.last_dispatch_id = self.last_dispatch_id.max(id.0);
+    }
+
+    // Everything reachable from an invalidated node, in dependency order.
+    pub(crate) fn dirty_order(&self) -> Result<Vec<String>, DagError> {
+        self.ordered(&self.dirty_region()?)
+    }
+
+    pub(crate) fn dirty_region(&self) -> Result<Topology, DagError> {
+        self.region_below(self.invalidated.iter())
+    }
+
+    // `roots` and everything reachable from them over hard edges, stopping
+    // at barriers. Soft edges between nodes of the region are kept so they
+    // still order it. Fails as soon as the walk outgrows the graph's
+    // visit or frontier limit.
+    pub(crate) fn region_below<'a>(&self, roots: impl Iterator<Item = &'a String>) -> Result<Topology, DagError> {
+        let topology = self.topology();
+        let mut dirty: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = roots.filter(|key| topology.contains(key)).cloned().collect();
+        while let Some(key) = stack.pop() {
+            if dirty.insert(key.clone()) && !self.barriers.contains(&key) {
+                stack.extend(topology.hard_successors(&key).map(|next| next.to_string()));
+                self.check_walk(&key, dirty.len(), stack.len())?;
+            }
+        }
+        let mut region = Topology::default();
+        for key in dirty.iter() {
+            region.insert_node(key);
+            for (next, weight) in topology.successors(key).iter().filter(|(next, _)| dirty.contains(next)) {
+                region.insert_edge(key, next, *weight);
+                if topology.is_soft(key, next) {
+                    region.mark_soft(key, next);
+                }
+            }
+        }
+        Ok(region)
+    }
+
+    // After a run over the whole dirty region.
+    pub(crate) fn clear_invalidated(&mut self) {
+        self.invalidated.clear();
+        self.invalidated_at.clear();
+        self.priorities.clear();
+        self.quarantine.clear();
+    }
+
+    pub(crate) fn next_dispatch_id(&mut self) -> DispatchId {
+        self.last_dispatch_id += 1;
+        DispatchId(self.last_dispatch_id)
+    }
+
+    // Call before unmarking what ran: the nodes still invalidated among
+    // `keys` are where the run's explanations start.
+    pub(crate) fn mark_dispatched(&mut self, keys: HashSet<String>, id: DispatchId) {
+        let roots: BTreeSet<String> = match self.explanations {
+            Some(_) => self.invalidated.iter().filter(|key| keys.contains(*key)).cloned().collect(),
+            None => BTreeSet::new(),
+        };
+        self.mark_dispatched_from(keys, &roots, id);
+    }
+
+    // For runs that unmark nodes as they go; `roots` are the nodes that were
+    // invalidated when the run was planned.
+    pub(crate) fn mark_dispatched_from(&mut self, keys: HashSet<String>, roots: &BTreeSet<String>, id: DispatchId) {
+        self.record_coverage(&keys);
+        self.record_explanations(&keys, roots, id);
+        for key in keys {
+            if self.barriers.contains(&key) {
+                self.held_barriers.insert(key.clone());
+            }
+            self.invalidated_at.remove(&key);
+            self.last_dispatched.insert(key, id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch_runs_get_distinct_ids() {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.add_edge_directed("A", "B");
+
+        dag.update("A", 10);
+        let mut seen = vec![];
+        let first = dag.dispatch_with_id(|node, id| seen.push((node.borrow().key.clone(), id)));
+        assert_eq!(seen, vec![("A".to_string(), first), ("B".to_string(), first)]);
+
+        dag.update("B", 20);
+        dag.dispatch(|_| ());
+        let second = dag.last_dispatch_id().unwrap();
+        assert!(second > first);
+        assert_eq!(dag.last_dispatched("A"), Some(first));
+        assert_eq!(dag.last_dispatched("B"), Some(second));
+    }
+
+    #[test]
+    fn resumed_ids_continue_past_previous_runs() {
+        let mut dag = Dag::new();
+        dag.resume_dispatch_ids(DispatchId(41));
+        assert_eq!(dag.dispatch_with_id(|_, _| ()), DispatchId(42));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Dag, Direction};
+
+// A sorted sample of edge weights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightDistribution {
+    weights: Vec<i32>,
+}
+
+impl WeightDistribution {
+    fn new(mut weights: Vec<i32>) -> WeightDistribution {
+        weights.sort_unstable();
+        WeightDistribution { weights }
+    }
+
+    pub fn count(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn min(&self) -> Option<i32> {
+        self.weights.first().copied()
+    }
+
+    pub fn max(&self) -> Option<i32> {
+        self.weights.last().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.weights.is_empty())
+            .then(|| self.weights.iter().map(|weight| *weight as f64).sum::<f64>() / self.weights.len() as f64)
+    }
+
+    // Nearest rank: the smallest weight with at least `percent` of the
+    // sample at or below it, so every answer is a weight that occurs.
+    pub fn percentile(&self, percent: f64) -> Option<i32> {
+        assert!((0.0..=100.0).contains(&percent), "Percentiles run from 0 to 100");
+        let rank = (percent / 100.0 * self.weights.len() as f64).ceil() as usize;
+        self.weights.get(rank.saturating_sub(1)).copied()
+    }
+
+    // Counts per bucket of `width` consecutive weights, by each bucket's
+    // lowest weight; empty buckets are left out.
+    pub fn histogram(&self, width: u32) -> Vec<(i32, usize)> {
+        assert!(width > 0, "Histogram buckets need a width");
+        let mut buckets: Vec<(i32, usize)> = vec![];
+        for weight in self.weights.iter() {
+            let low = (*weight as i64).div_euclid(width as i64) * width as i64;
+            match buckets.last_mut() {
+                Some((last, count)) if *last as i64 == low => *count += 1,
+                _ => buckets.push((low as i32, 1)),
+            }
+        }
+        buckets
+    }
+}
+
+impl Dag {
+    // Weights of every live edge.
+    pub fn weight_distribution(&self) -> WeightDistribution {
+        let topology = self.topology();
+        WeightDistribution::new(topology.keys()
+            .flat_map(|key| topology.successors(key).iter().map(|(_, weight)| *weight))
+            .collect())
+    }
+
+    // Weights of the edges at one node, `Both` taking its incoming and
+    // outgoing edges together; `None` if `key` isn't in the graph.
+    pub fn node_weight_distribution(&self, key: &str, direction: Direction) -> Option<WeightDistribution> {
+        let key = self.resolve_key(key);
+        self.get(&key)?;
+        let topology = self.topology();
+        let mut weights = vec![];
+        if direction != Direction::Incoming {
+            weights.extend(topology.successors(&key).iter().map(|(_, weight)| *weight));
+        }
+        if direction != Direction::Outgoing {
+            weights.extend(topology.keys()
+                .flat_map(|from| topology.successors(from).iter())
+                .filter(|(to, _)| *to == key)
+                .map(|(_, weight)| *weight));
+        }
+        Some(WeightDistribution::new(weights))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_and_buckets() {
+        let mut dag = Dag::new();
+        dag.add("hub", ());
+        for (i, weight) in [1, 2, 2, 3, 5, 8, 13, 21, 34, 55].into_iter().enumerate() {
+            let key = format!("leaf-{}", i);
+            dag.add(&key, ());
+            dag.link("hub", &key, weight);
+        }
+        dag.add("root", ());
+        dag.link("root", "hub", -4);
+
+        let all = dag.weight_distribution();
+        assert_eq!((all.count(), all.min(), all.max()), (11, Some(-4), Some(55)));
+        assert_eq!(all.percentile(50.0), Some(5));
+        assert_eq!(all.percentile(90.0), Some(34));
+        assert_eq!(all.percentile(100.0), Some(55));
+        assert_eq!(all.histogram(10), vec![(-10, 1), (0, 6), (10, 1), (20, 1), (30, 1), (50, 1)]);
+
+        let outgoing = dag.node_weight_distribution("hub", Direction::Outgoing).unwrap();
+        assert_eq!(outgoing.mean(), Some(14.4));
+        let incoming = dag.node_weight_distribution("hub", Direction::Incoming).unwrap();
+        assert_eq!(incoming.percentile(1.0), Some(-4));
+        assert_eq!(dag.node_weight_distribution("leaf-0", Direction::Outgoing).unwrap().percentile(50.0), None);
+        assert_eq!(dag.node_weight_distribution("missing", Direction::Both), None);
+    }
+}
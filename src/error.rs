@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::Limit;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DagError {
+    NodeNotFound(String),
+    DuplicateNode(String),
+    // `path` is the existing route from `to` back to `from`.
+    WouldCreateCycle { from: String, to: String, path: Vec<String> },
+    CycleDetected { path: Vec<String> },
+    IntegrityMismatch(String),
+    QuotaExceeded { limit: Limit, maximum: usize, key: String },
+    Journal(String),
+    MissingParameter { pattern: String, parameter: String },
+    NotBipartite { from: String, to: String },
+    Export(String),
+    Import(String),
+    // A definition file that doesn't describe a valid graph; positions are
+    // 1-based.
+    Definition { line: usize, column: usize, detail: String },
+    Store(String),
+    Remote(String),
+    NotReady(String),
+    ReplayDiverged(String),
+    ViewNotFound(String),
+    // The public operation that was running when `source` happened.
+    During { operation: &'static str, source: Box<DagError> },
+    // Only raised with the `no_panic` feature; see `borrow.rs`.
+    BorrowConflict { key: String, operation: &'static str },
+}
+
+impl DagError {
+    pub fn during(self, operation: &'static str) -> DagError {
+        DagError::During { operation, source: Box::new(self) }
+    }
+
+    // The underlying failure, with any operation context peeled off.
+    pub fn root(&self) -> &DagError {
+        match self {
+            DagError::During { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    pub fn operation(&self) -> Option<&'static str> {
+        match self {
+            DagError::During { operation, .. } => Some(operation),
+            _ => None,
+        }
+    }
+
+    // Every node key the failure names, in the order they appear in it.
+    pub fn keys(&self) -> Vec<&str> {
+        match self.root() {
+            DagError::NodeNotFound(key)
+            | DagError::DuplicateNode(key)
+            | DagError::QuotaExceeded { key, .. }
+            | DagError::BorrowConflict { key, .. }
+            | DagError::NotReady(key)
+            | DagError::ReplayDiverged(key) => vec![key],
+            DagError::WouldCreateCycle { from, to, path } => {
+                let mut keys = vec![from.as_str(), to.as_str()];
+                keys.extend(path.iter().map(|key| key.as_str()).filter(|key| *key != from && *key != to));
+                keys
+            },
+            DagError::CycleDetected { path } => path.iter().map(|key| key.as_str()).collect(),
+            DagError::NotBipartite { from, to } => vec![from, to],
+            _ => vec![],
+        }
+    }
+}
+
+fn cycle(path: &[String]) -> String {
+    let mut rendered = path.join(" -> ");
+    if let Some(first) = path.first() {
+        rendered.push_str(" -> ");
+        rendered.push_str(first);
+    }
+    rendered
+}
+
+impl fmt::Display for DagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DagError::NodeNotFound(key) => write!(f, "Cannot find node {}", key),
+            DagError::DuplicateNode(key) => write!(f, "Node {} already exists", key),
+            DagError::WouldCreateCycle { from, to, path } => {
+                write!(f, "Edge from {} to {} would create a cycle: {}", from, to, cycle(path))
+            },
+            DagError::CycleDetected { path } => write!(f, "Cycle detected: {}", cycle(path)),
+            DagError::IntegrityMismatch(detail) => write!(f, "Integrity check failed: {}", detail),
+            DagError::QuotaExceeded { limit, maximum, key } => {
+                write!(f, "Quota exceeded at node {}: at most {} {}", key, maximum, match limit {
+                    Limit::Nodes => "nodes",
+                    Limit::Edges => "edges",
+                    Limit::OutDegree => "outgoing edges per node",
+                    Limit::Visited => "nodes visited in one walk",
+                    Limit::Frontier => "nodes waiting in one walk",
+                })
+            },
+            DagError::Journal(detail) => write!(f, "Dispatch journal failed: {}", detail),
+            DagError::MissingParameter { pattern, parameter } => {
+                write!(f, "Template {} needs parameter {}", pattern, parameter)
+            },
+            DagError::NotBipartite { from, to } => {
+                write!(f, "Edge from {} to {} does not run from a source to a sink", from, to)
+            },
+            DagError::Export(detail) => write!(f, "Export failed: {}", detail),
+            DagError::Import(detail) => write!(f, "Import failed: {}", detail),
+            DagError::Definition { line, column, detail } => {
+                write!(f, "Invalid definition at line {}, column {}: {}", line, column, detail)
+            },
+            DagError::Store(detail) => write!(f, "Graph store failed: {}", detail),
+            DagError::Remote(detail) => write!(f, "Remote graph call failed: {}", detail),
+            DagError::NotReady(key) => write!(f, "Node {} is not ready to be reported on", key),
+            DagError::ReplayDiverged(key) => write!(f, "Replay diverged from the trace at node {}", key),
+            DagError::ViewNotFound(name) => write!(f, "Cannot find view {}", name),
+            DagError::During { operation, source } => write!(f, "{} failed: {}", operation, source),
+            DagError::BorrowConflict { key, operation } => {
+                write!(f, "{} needs node {}, which is already borrowed elsewhere", operation, key)
+            },
+        }
+    }
+}
+
+impl Error for DagError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DagError::During { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::{Rc, Weak};
+
+use crate::{Dag, Node, NodeStrongRef};
+
+// A reference to a node that never keeps it alive and goes dead for good
+// once the node is removed, even while something else still holds the
+// node itself. Re-adding the key makes a new node; old handles stay dead.
+#[derive(Debug, Clone)]
+pub struct WeakNode {
+    key: String,
+    node: Weak<RefCell<Node>>,
+    alive: Rc<Cell<bool>>,
+}
+
+impl WeakNode {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.get()
+    }
+
+    pub fn upgrade(&self) -> Option<NodeStrongRef> {
+        self.node.upgrade().filter(|_| self.is_alive())
+    }
+}
+
+type ExpiryCallback = Box<dyn FnOnce(&str)>;
+
+#[derive(Default)]
+struct Watch {
+    alive: Rc<Cell<bool>>,
+    callbacks: Vec<ExpiryCallback>,
+}
+
+#[derive(Default)]
+pub(crate) struct Watchers {
+    watched: RefCell<HashMap<String, Watch>>,
+}
+
+impl Watchers {
+    fn watch(&self, key: &str) -> Rc<Cell<bool>> {
+        let mut watched = self.watched.borrow_mut();
+        let watch = watched.entry(key.to_string()).or_insert_with(|| Watch { alive: Rc::new(Cell::new(true)), callbacks: vec![] });
+        Rc::clone(&watch.alive)
+    }
+
+    pub(crate) fn expire(&self, key: &str) {
+        let Some(watch) = self.watched.borrow_mut().remove(key) else { return };
+        watch.alive.set(false);
+        for callback in watch.callbacks {
+            callback(key);
+        }
+    }
+}
+
+// Dropping the graph removes every node, so it expires them all, in key
+// order.
+impl Drop for Watchers {
+    fn drop(&mut self) {
+        let watched: BTreeMap<String, Watch> = self.watched.get_mut().drain().collect();
+        for (key, watch) in watched {
+            watch.alive.set(false);
+            for callback in watch.callbacks {
+                callback(&key);
+            }
+        }
+    }
+}
+
+impl Dag {
+    // `None` if `key` isn't in the graph.
+    pub fn weak_handle(&self, key: &str) -> Option<WeakNode> {
+        let key = self.resolve_key(key).into_owned();
+        let node = self.get(&key)?;
+        let alive = self.watchers.watch(&key);
+        Some(WeakNode { key, node: Rc::downgrade(&node), alive })
+    }
+
+    // Runs `callback` with the key once the node is removed. Returns false
+    // if `key` isn't in the graph.
+    pub fn on_expire<F>(&mut self, key: &str, callback: F) -> bool where F: FnOnce(&str) + 'static {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        self.watchers.watch(&key);
+        let mut watched = self.watchers.watched.borrow_mut();
+        watched.get_mut(&key).expect("Watch just added").callbacks.push(Box::new(callback));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_die_with_their_node() {
+        let expired: Rc<RefCell<Vec<String>>> = Rc::default();
+        let mut dag = Dag::new();
+        dag.add("thumbnail", ());
+        dag.add("page", ()).depends_on("thumbnail");
+        let handle = dag.weak_handle("thumbnail").unwrap();
+        let log = Rc::clone(&expired);
+        assert!(dag.on_expire("thumbnail", move |key| log.borrow_mut().push(key.to_string())));
+        assert!(!dag.on_expire("missing", |_| ()));
+
+        let held = dag.get("thumbnail").unwrap();
+        dag.update("thumbnail", 2);
+        assert!(handle.upgrade().is_some_and(|node| Rc::ptr_eq(&node, &held)));
+        dag.remove("thumbnail");
+        assert!(!handle.is_alive() && handle.upgrade().is_none());
+        assert_eq!(*expired.borrow(), vec!["thumbnail"]);
+
+        dag.add("thumbnail", ());
+        assert!(!handle.is_alive());
+        let log = Rc::clone(&expired);
+        dag.on_expire("page", move |key| log.borrow_mut().push(key.to_string()));
+        drop(dag);
+        assert_eq!(*expired.borrow(), vec!["thumbnail", "page"]);
+    }
+}
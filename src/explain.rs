@@ -0,0 +1,95 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::{Dag, DispatchId};
+
+// Why a node last ran: `chain` starts at the invalidated node that set it
+// off and follows hard edges down to the node itself, so a node that was
+// invalidated directly explains itself with a chain of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub id: DispatchId,
+    pub chain: Vec<String>,
+}
+
+impl Dag {
+    // Off by default: every dispatch then walks its run once more. Turning
+    // it off forgets what was recorded.
+    pub fn set_explanations(&mut self, enabled: bool) {
+        self.explanations = enabled.then(HashMap::new);
+    }
+
+    pub fn explain(&self, key: &str) -> Option<&Explanation> {
+        self.explanations.as_ref()?.get(self.resolve_key(key).as_ref())
+    }
+
+    // Shortest chains from `roots`, the smallest root on ties.
+    pub(crate) fn record_explanations(&mut self, keys: &HashSet<String>, roots: &BTreeSet<String>, id: DispatchId) {
+        let Some(mut explanations) = self.explanations.take() else { return };
+        let roots: BTreeSet<&String> = roots.iter().filter(|key| keys.contains(*key)).collect();
+        let mut parents: HashMap<String, Option<String>> = roots.iter().map(|root| (root.to_string(), None)).collect();
+        let mut queue: VecDeque<String> = roots.into_iter().cloned().collect();
+        while let Some(key) = queue.pop_front() {
+            for next in self.hard_successors(&key) {
+                if keys.contains(&next) && !parents.contains_key(&next) {
+                    parents.insert(next.clone(), Some(key.clone()));
+                    queue.push_back(next);
+                }
+            }
+        }
+        for key in keys {
+            let mut chain = vec![key.clone()];
+            while let Some(Some(parent)) = parents.get(chain.last().expect("Chain starts non-empty")) {
+                chain.push(parent.clone());
+            }
+            chain.reverse();
+            explanations.insert(key.clone(), Explanation { id, chain });
+        }
+        self.explanations = Some(explanations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chains_lead_back_to_the_update() {
+        let mut dag = Dag::new();
+        for key in ["config", "schema", "codegen", "binary", "docs"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("config", "codegen");
+        dag.add_edge_directed("schema", "codegen");
+        dag.add_edge_directed("codegen", "binary");
+        dag.add_edge_directed("schema", "docs");
+        dag.set_explanations(true);
+
+        dag.update("schema", ());
+        let id = dag.dispatch_with_id(|_, _| ());
+        let chain = |dag: &Dag, key: &str| dag.explain(key).map(|explanation| explanation.chain.clone());
+        assert_eq!(dag.explain("binary").unwrap().id, id);
+        assert_eq!(chain(&dag, "binary").unwrap(), vec!["schema", "codegen", "binary"]);
+        assert_eq!(chain(&dag, "schema").unwrap(), vec!["schema"]);
+        assert_eq!(chain(&dag, "config"), None);
+
+        dag.update("config", ());
+        dag.dispatch_with_ctx(|_| ()).unwrap();
+        assert_eq!(chain(&dag, "binary").unwrap(), vec!["config", "codegen", "binary"]);
+        assert_eq!(chain(&dag, "docs").unwrap(), vec!["schema", "docs"]);
+
+        dag.update("schema", ());
+        dag.try_dispatch(|_| Ok::<(), ()>(())).unwrap();
+        assert_eq!(chain(&dag, "binary").unwrap(), vec!["schema", "codegen", "binary"]);
+        dag.update("config", ());
+        dag.dispatch_budgeted(10, |_, _| ()).unwrap();
+        assert_eq!(chain(&dag, "binary").unwrap(), vec!["config", "codegen", "binary"]);
+        dag.update("schema", ());
+        dag.ready_nodes().unwrap();
+        for key in ["schema", "codegen", "docs", "binary"] {
+            dag.mark_complete(key).unwrap();
+        }
+        assert_eq!(chain(&dag, "binary").unwrap(), vec!["schema", "codegen", "binary"]);
+        dag.remove("binary");
+        assert_eq!(chain(&dag, "binary"), None);
+    }
+}
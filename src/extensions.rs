@@ -0,0 +1,101 @@
+use std::any::{Any, TypeId};
+use std::fmt;
+
+use crate::Node;
+
+// Data that other crates attach to a node, at most one value per type. A
+// crate keying by a type of its own can't collide with anyone else's.
+// Values stay with the node through `update` and go with it on `remove`.
+#[derive(Default)]
+pub struct Extensions {
+    // A node rarely carries more than a few, so a scan beats hashing, and
+    // an empty list doesn't allocate.
+    values: Vec<(TypeId, Box<dyn Any>)>,
+}
+
+impl Extensions {
+    // Returns the value of the same type that this replaced.
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        let old = self.remove::<T>();
+        self.values.push((TypeId::of::<T>(), Box::new(value)));
+        old
+    }
+
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values.iter()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_ref())
+    }
+
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.values.iter_mut()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_mut())
+    }
+
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        let position = self.values.iter().position(|(id, _)| *id == TypeId::of::<T>())?;
+        let (_, value) = self.values.swap_remove(position);
+        Some(*value.downcast().expect("Extension stored under its own type"))
+    }
+
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+// Values are type-erased, so only how many there are can be shown.
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.len()).finish()
+    }
+}
+
+impl Node {
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dag;
+
+    #[derive(Debug, PartialEq)]
+    struct Owner(&'static str);
+
+    #[derive(Debug, PartialEq)]
+    struct Retries(u32);
+
+    #[test]
+    fn extensions_are_keyed_by_type() {
+        let mut dag = Dag::new();
+        dag.add("build", ());
+        let node = dag.get("build").unwrap();
+        assert!(node.borrow().extensions().is_empty());
+        node.borrow_mut().extensions_mut().insert(Owner("infra"));
+        node.borrow_mut().extensions_mut().insert(Retries(1));
+        *node.borrow_mut().extensions_mut().get_mut::<Retries>().unwrap() = Retries(2);
+        assert_eq!(node.borrow_mut().extensions_mut().insert(Owner("web")), Some(Owner("infra")));
+
+        dag.update("build", "rebuilt");
+        let node = dag.get("build").unwrap();
+        let borrowed = node.borrow();
+        assert_eq!(borrowed.extensions().get::<Owner>(), Some(&Owner("web")));
+        assert_eq!(borrowed.extensions().get::<Retries>(), Some(&Retries(2)));
+        assert!(!borrowed.extensions().contains::<String>());
+        assert_eq!(borrowed.extensions().len(), 2);
+    }
+}
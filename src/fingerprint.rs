@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use sha2::{Digest, Sha256};
+
+use crate::Dag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub u64);
+
+pub trait DataFingerprint {
+    fn fingerprint(&self) -> Fingerprint;
+}
+
+// SHA-256 rather than `DefaultHasher`, whose algorithm may change between
+// Rust releases, so a fingerprint can be kept and compared across runs. The
+// bytes fed in still come from `Hash` impls, which write integers in native
+// byte order: don't compare fingerprints across platforms.
+struct FingerprintHasher(Sha256);
+
+impl Hasher for FingerprintHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+    }
+}
+
+impl<T> DataFingerprint for T where T: Hash + ?Sized {
+    fn fingerprint(&self) -> Fingerprint {
+        let mut hasher = FingerprintHasher(Sha256::new());
+        self.hash(&mut hasher);
+        Fingerprint(hasher.finish())
+    }
+}
+
+impl Dag {
+    // Like `update`, but skips the write and the invalidation when the new
+    // data fingerprints the same as what the node already holds.
+    pub fn update_fingerprinted<T>(&mut self, key: &str, data: T) -> bool where T: Debug + DataFingerprint + 'static {
+        let key: &str = &self.resolve_key(key);
+        if self.get(key).is_none() {
+            return false;
+        }
+        let fingerprint = data.fingerprint();
+        if self.fingerprints.get(key) == Some(&fingerprint) {
+            return false;
+        }
+        self.update(key, data);
+        self.fingerprints.insert(key.to_string(), fingerprint);
+        true
+    }
+
+    // Only data written through `update_fingerprinted` has a fingerprint;
+    // plain `add`/`update` clear it.
+    pub fn fingerprint(&self, key: &str) -> Option<Fingerprint> {
+        self.fingerprints.get(self.resolve_key(key).as_ref()).copied()
+    }
+
+    pub fn changed_since(&self, key: &str, fingerprint: Fingerprint) -> bool {
+        self.fingerprint(key) != Some(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyNormalizer;
+
+    #[test]
+    fn unchanged_data_is_not_invalidated() {
+        let mut dag = Dag::new();
+        dag.add("A", 0);
+        assert!(dag.update_fingerprinted("A", 1));
+        let seen = dag.fingerprint("A").unwrap();
+        dag.invalidated.clear();
+
+        assert!(!dag.update_fingerprinted("A", 1));
+        assert!(dag.invalidated.is_empty());
+        assert!(!dag.changed_since("A", seen));
+
+        assert!(dag.update_fingerprinted("A", 2));
+        assert!(dag.changed_since("A", seen));
+        // Pinned, so a change of hasher shows up here.
+        assert_eq!(0xabu8.fingerprint(), Fingerprint(0x44dd_82f1_f780_7d08));
+    }
+
+    #[test]
+    fn plain_updates_clear_fingerprints() {
+        let mut dag = Dag::new();
+        dag.add("A", "a");
+        dag.update_fingerprinted("A", "b");
+        let seen = dag.fingerprint("A").unwrap();
+        dag.update("A", "b");
+        assert!(dag.fingerprint("A").is_none());
+        assert!(dag.changed_since("A", seen));
+    }
+
+    #[test]
+    fn fingerprints_follow_normalized_keys() {
+        let mut dag = Dag::with_key_normalizer(KeyNormalizer::case_insensitive());
+        dag.add("orders", 0);
+        assert!(dag.update_fingerprinted("Orders", 1));
+        assert!(dag.fingerprint("orders").is_some());
+        dag.invalidated.clear();
+        assert!(!dag.update_fingerprinted("ORDERS", 1));
+        assert!(dag.invalidated.is_empty());
+    }
+}
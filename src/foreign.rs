@@ -0,0 +1,154 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::{Dag, NodeHandle};
+
+// Looks payloads up in whatever store really holds them. The graph always
+// asks for everything it is missing in one batch.
+pub trait Resolver: 'static {
+    type Id: Clone + Eq + Hash + 'static;
+    type Value: 'static;
+
+    // One answer per id, in order; `None` for ids the store doesn't know.
+    fn resolve_batch(&self, ids: &[Self::Id]) -> Vec<Option<Self::Value>>;
+}
+
+// The payload of a node added with `add_reference`: it renders as the id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForeignKey<I>(pub I);
+
+pub(crate) trait AnyResolver {
+    fn lookup(&self, ids: &[&dyn Any]) -> Vec<Option<Rc<dyn Any>>>;
+    fn clear(&self);
+}
+
+struct Cached<R: Resolver> {
+    resolver: R,
+    values: RefCell<HashMap<R::Id, Rc<R::Value>>>,
+}
+
+impl<R: Resolver> AnyResolver for Cached<R> {
+    fn lookup(&self, ids: &[&dyn Any]) -> Vec<Option<Rc<dyn Any>>> {
+        let ids: Vec<Option<&R::Id>> = ids.iter().map(|id| id.downcast_ref()).collect();
+        let mut asked: HashSet<&R::Id> = HashSet::new();
+        let missing: Vec<R::Id> = ids.iter().flatten()
+            .filter(|id| !self.values.borrow().contains_key(*id) && asked.insert(*id))
+            .map(|id| (*id).clone())
+            .collect();
+        if !missing.is_empty() {
+            let found = self.resolver.resolve_batch(&missing);
+            let mut values = self.values.borrow_mut();
+            for (id, value) in missing.into_iter().zip(found) {
+                if let Some(value) = value {
+                    values.insert(id, Rc::new(value));
+                }
+            }
+        }
+        let values = self.values.borrow();
+        ids.into_iter()
+            .map(|id| id.and_then(|id| values.get(id)).map(|value| Rc::clone(value) as Rc<dyn Any>))
+            .collect()
+    }
+
+    fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+}
+
+impl Dag {
+    // Replaces any earlier resolver, dropping what it had cached.
+    pub fn set_resolver<R: Resolver>(&mut self, resolver: R) {
+        self.resolver = Some(Box::new(Cached { resolver, values: RefCell::new(HashMap::new()) }));
+    }
+
+    // Forgets every resolved value, e.g. after the external store changed.
+    pub fn clear_resolved(&self) {
+        if let Some(resolver) = self.resolver.as_ref() {
+            resolver.clear();
+        }
+    }
+
+    // A node whose payload is `id`, resolved through the graph's resolver.
+    pub fn add_reference<I>(&mut self, key: &str, id: I) -> NodeHandle<'_> where I: Clone + Debug + 'static {
+        let key = self.resolve_key(key).into_owned();
+        self.add(&key, ForeignKey(id.clone()));
+        self.references.insert(key.clone(), Box::new(id));
+        self.node(&key)
+    }
+
+    pub fn reference<I: 'static>(&self, key: &str) -> Option<&I> {
+        self.references.get(self.resolve_key(key).as_ref()).and_then(|id| id.downcast_ref())
+    }
+
+    // The resolved value behind a reference node. `None` if `key` isn't one,
+    // there is no resolver, its types don't match, or the store doesn't know
+    // the id.
+    pub fn data<V: 'static>(&self, key: &str) -> Option<Rc<V>> {
+        let id = self.references.get(self.resolve_key(key).as_ref())?;
+        let value = self.resolver.as_ref()?.lookup(&[id.as_ref()]).pop()??;
+        value.downcast().ok()
+    }
+
+    // Resolves every not yet cached reference among `keys` in one batch.
+    pub fn resolve_references(&self, keys: &[&str]) {
+        let Some(resolver) = self.resolver.as_ref() else { return };
+        let ids: Vec<&dyn Any> = keys.iter()
+            .filter_map(|key| self.references.get(self.resolve_key(key).as_ref()))
+            .map(|id| id.as_ref())
+            .collect();
+        if !ids.is_empty() {
+            resolver.lookup(&ids);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    struct Users {
+        batches: Rc<Cell<usize>>,
+    }
+
+    impl Resolver for Users {
+        type Id = u32;
+        type Value = String;
+
+        fn resolve_batch(&self, ids: &[u32]) -> Vec<Option<String>> {
+            self.batches.set(self.batches.get() + 1);
+            ids.iter().map(|id| (*id < 100).then(|| format!("user-{}", id))).collect()
+        }
+    }
+
+    #[test]
+    fn references_resolve_once_per_dispatch() {
+        let batches = Rc::new(Cell::new(0));
+        let mut dag = Dag::new();
+        dag.set_resolver(Users { batches: batches.clone() });
+        dag.add_reference("alice", 1u32);
+        dag.add_reference("bob", 2u32).depends_on("alice");
+        dag.add_reference("ghost", 404u32);
+        dag.update("alice", ForeignKey(1u32));
+        assert_eq!(format!("{:?}", dag.get("bob").unwrap().borrow().data), "ForeignKey(2)");
+
+        let mut seen = vec![];
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.data::<String>().map(|name| name.to_string()))).unwrap();
+        assert_eq!(seen, vec![None, Some("user-2".to_string())]);
+        assert_eq!(batches.get(), 1);
+
+        dag.add_reference("alice", 1u32);
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.data::<String>().map(|name| name.to_string()))).unwrap();
+        assert_eq!(seen[2..], [Some("user-1".to_string()), Some("user-2".to_string())]);
+        assert_eq!(batches.get(), 2);
+        assert_eq!(dag.data::<u64>("alice"), None);
+        assert_eq!(dag.reference::<u32>("bob"), Some(&2));
+        assert_eq!(dag.data::<String>("ghost"), None);
+        assert_eq!(batches.get(), 3);
+    }
+}
@@ -0,0 +1,490 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::interchange::{import_error, push_bytes, Reader};
+use crate::{Dag, DagError, DispatchId, NodeStrongRef};
+
+const STATE_MAGIC: &[u8; 4] = b"DAGF";
+const READY: u8 = 1;
+const COMPLETED: u8 = 2;
+const FAILED: u8 = 4;
+const BLOCKED: u8 = 8;
+const ROOT: u8 = 16;
+
+// One externally executed run over the dirty region. The region is planned
+// when the run starts; edits made while it is in progress apply to the next
+// one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frontier {
+    id: DispatchId,
+    successors: BTreeMap<String, Vec<String>>,
+    waiting: BTreeMap<String, usize>,
+    priority: BTreeMap<String, usize>,
+    ready: BTreeSet<(Reverse<usize>, String)>,
+    completed: HashSet<String>,
+    failed: BTreeSet<String>,
+    soft: BTreeSet<(String, String)>,
+    blocked: HashSet<String>,
+    // What was invalidated when the run was planned.
+    roots: BTreeSet<String>,
+}
+
+impl Frontier {
+    fn release(&mut self, key: &str) -> Vec<String> {
+        self.release_to(self.successors[key].clone())
+    }
+
+    fn is_soft(&self, from_key: &str, to_key: &str) -> bool {
+        self.soft.contains(&(from_key.to_string(), to_key.to_string()))
+    }
+
+    fn release_to(&mut self, successors: Vec<String>) -> Vec<String> {
+        let mut released = vec![];
+        for next in successors {
+            let waiting = self.waiting.get_mut(&next).expect("Region successor missing");
+            *waiting -= 1;
+            if *waiting == 0 {
+                self.ready.insert((Reverse(self.priority[&next]), next.clone()));
+                released.push(next);
+            }
+        }
+        released
+    }
+}
+
+// A run lifted out of its graph so it can outlive the process. It holds
+// keys and bookkeeping only; the payloads stay with the graph it is resumed
+// into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontierState(Frontier);
+
+impl FrontierState {
+    pub fn id(&self) -> DispatchId {
+        self.0.id
+    }
+
+    // Little-endian, with length-prefixed strings, like `Dag::to_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let frontier = &self.0;
+        let mut buffer = STATE_MAGIC.to_vec();
+        buffer.extend(frontier.id.0.to_le_bytes());
+        buffer.extend((frontier.successors.len() as u32).to_le_bytes());
+        for (key, successors) in frontier.successors.iter() {
+            let priority = frontier.priority[key];
+            let flags = [
+                (READY, frontier.ready.contains(&(Reverse(priority), key.clone()))),
+                (COMPLETED, frontier.completed.contains(key)),
+                (FAILED, frontier.failed.contains(key)),
+                (BLOCKED, frontier.blocked.contains(key)),
+                (ROOT, frontier.roots.contains(key)),
+            ];
+            push_bytes(&mut buffer, key);
+            buffer.extend((frontier.waiting[key] as u32).to_le_bytes());
+            buffer.extend((priority as u32).to_le_bytes());
+            buffer.push(flags.iter().filter(|(_, set)| *set).fold(0, |all, (flag, _)| all | flag));
+            buffer.extend((successors.len() as u32).to_le_bytes());
+            for next in successors {
+                push_bytes(&mut buffer, next);
+                buffer.push(frontier.is_soft(key, next) as u8);
+            }
+        }
+        buffer
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<FrontierState, DagError> {
+        parse_state(bytes).map_err(|err| err.during("FrontierState::from_bytes"))
+    }
+}
+
+fn parse_state(bytes: &[u8]) -> Result<FrontierState, DagError> {
+    let mut reader = Reader { bytes };
+    if reader.take(4)? != STATE_MAGIC {
+        return Err(import_error("not a dispatch frontier"));
+    }
+    let mut frontier = Frontier {
+        id: DispatchId(reader.u64()?),
+        successors: BTreeMap::new(),
+        waiting: BTreeMap::new(),
+        priority: BTreeMap::new(),
+        ready: BTreeSet::new(),
+        completed: HashSet::new(),
+        failed: BTreeSet::new(),
+        soft: BTreeSet::new(),
+        blocked: HashSet::new(),
+        roots: BTreeSet::new(),
+    };
+    for _ in 0..reader.u32()? {
+        let key = reader.text()?;
+        let (waiting, priority) = (reader.u32()? as usize, reader.u32()? as usize);
+        let flags = reader.take(1)?[0];
+        let mut successors = vec![];
+        for _ in 0..reader.u32()? {
+            let next = reader.text()?;
+            if reader.take(1)?[0] != 0 {
+                frontier.soft.insert((key.clone(), next.clone()));
+            }
+            successors.push(next);
+        }
+        if flags & READY != 0 {
+            frontier.ready.insert((Reverse(priority), key.clone()));
+        }
+        if flags & COMPLETED != 0 {
+            frontier.completed.insert(key.clone());
+        }
+        if flags & FAILED != 0 {
+            frontier.failed.insert(key.clone());
+        }
+        if flags & BLOCKED != 0 {
+            frontier.blocked.insert(key.clone());
+        }
+        if flags & ROOT != 0 {
+            frontier.roots.insert(key.clone());
+        }
+        frontier.waiting.insert(key.clone(), waiting);
+        frontier.priority.insert(key.clone(), priority);
+        frontier.successors.insert(key, successors);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(import_error("trailing bytes"));
+    }
+    if let Some(next) = frontier.successors.values().flatten().find(|next| !frontier.successors.contains_key(*next)) {
+        return Err(import_error(format!("successor {} is not part of the run", next)));
+    }
+    Ok(FrontierState(frontier))
+}
+
+impl Dag {
+    // A copy of the run in progress, which carries on undisturbed.
+    pub fn checkpoint_run(&self) -> Option<FrontierState> {
+        self.frontier.clone().map(FrontierState)
+    }
+
+    // Takes the run in progress out of the graph. Nothing is ready until it
+    // is resumed, and a new run won't start while nodes stay invalidated.
+    pub fn suspend_run(&mut self) -> Option<FrontierState> {
+        self.frontier.take().map(FrontierState)
+    }
+
+    // Picks the run back up where the state left it, replacing any run in
+    // progress; after a restart this is usually the freshly loaded graph.
+    // Invalidation marks are brought in line with what the run already
+    // settled. Fails if the graph lacks a node of the run.
+    pub fn resume_run(&mut self, state: FrontierState) -> Result<(), DagError> {
+        let frontier = state.0;
+        if let Some(key) = frontier.successors.keys().find(|key| self.get(key).is_none()) {
+            return Err(DagError::NodeNotFound(key.clone()));
+        }
+        for key in frontier.completed.iter() {
+            self.invalidated.remove(key);
+            self.priorities.remove(key);
+        }
+        self.invalidated.extend(frontier.failed.iter().cloned());
+        self.resume_dispatch_ids(frontier.id);
+        self.frontier = Some(frontier);
+        self.finish_frontier_if_drained();
+        Ok(())
+    }
+
+    // Ready nodes of the current run, most urgent first: a node is more
+    // urgent the longer the chain of work still hanging off it. Starts a run
+    // over the dirty region if none is in progress.
+    pub fn ready_nodes(&mut self) -> Result<Vec<String>, DagError> {
+        if self.frontier.is_none() && !self.invalidated.is_empty() {
+            self.start_frontier()?;
+        }
+        Ok(self.frontier.as_ref()
+            .map(|frontier| frontier.ready.iter().map(|(_, key)| key.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    // Returns the nodes this completion made ready.
+    pub fn mark_complete(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        let key: &str = &self.resolve_key(key);
+        let frontier = self.take_ready(key)?;
+        frontier.completed.insert(key.to_string());
+        let released = frontier.release(key);
+        self.invalidated.remove(key);
+        self.priorities.remove(key);
+        self.finish_frontier_if_drained();
+        Ok(released)
+    }
+
+    // A failed node stays invalidated, and nothing downstream of it over hard
+    // edges runs in this run; nodes behind it over soft edges stop waiting
+    // for it. Returns the nodes that are now blocked.
+    pub fn mark_failed(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        let key: &str = &self.resolve_key(key);
+        let frontier = self.take_ready(key)?;
+        frontier.failed.insert(key.to_string());
+        let mut blocked = vec![];
+        let mut stack = vec![key.to_string()];
+        let mut seen: HashSet<String> = HashSet::new();
+        while let Some(current) = stack.pop() {
+            for next in frontier.successors[&current].iter() {
+                if !frontier.is_soft(&current, next) && seen.insert(next.clone()) {
+                    stack.push(next.clone());
+                    blocked.push(next.clone());
+                }
+            }
+        }
+        blocked.sort();
+        let settled: Vec<String> = std::iter::once(key.to_string())
+            .chain(blocked.iter().filter(|next| !frontier.blocked.contains(*next)).cloned())
+            .collect();
+        frontier.blocked.extend(blocked.iter().cloned());
+        for from_key in settled {
+            let soft: Vec<String> = frontier.successors[&from_key].iter()
+                .filter(|next| frontier.is_soft(&from_key, next) && !frontier.blocked.contains(*next))
+                .cloned()
+                .collect();
+            frontier.release_to(soft);
+        }
+        self.invalidated.insert(key.to_string());
+        self.finish_frontier_if_drained();
+        Ok(blocked)
+    }
+
+    // Pull-based run over the frontier: the stream hands out ready nodes, at
+    // most `max_in_flight` of them unacknowledged at a time, and dependents
+    // only become available once the consumer acks what they wait on.
+    pub fn dispatch_stream(&mut self, max_in_flight: usize) -> Result<DispatchStream<'_>, DagError> {
+        assert!(max_in_flight > 0, "A dispatch stream needs room for at least one node in flight");
+        self.ready_nodes()?;
+        Ok(DispatchStream { dag: self, max_in_flight, in_flight: BTreeSet::new() })
+    }
+
+    pub fn failed_nodes(&self) -> Vec<String> {
+        self.frontier.as_ref()
+            .map(|frontier| frontier.failed.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn current_run(&self) -> Option<DispatchId> {
+        self.frontier.as_ref().map(|frontier| frontier.id)
+    }
+
+    fn start_frontier(&mut self) -> Result<(), DagError> {
+        let region = self.dirty_region()?;
+        let order = region.topological_order()?;
+        let mut priority: BTreeMap<String, usize> = BTreeMap::new();
+        for key in order.iter().rev() {
+            let below = region.successors(key).iter().map(|(next, _)| priority[next]).max().unwrap_or(0);
+            priority.insert(key.clone(), below + 1);
+        }
+        let waiting = region.in_degrees();
+        let ready = waiting.iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(key, _)| (Reverse(priority[key]), key.clone()))
+            .collect();
+        let successors = order.iter()
+            .map(|key| (key.clone(), region.successors(key).iter().map(|(next, _)| next.clone()).collect()))
+            .collect();
+        let id = self.next_dispatch_id();
+        self.frontier = Some(Frontier {
+            id,
+            successors,
+            waiting,
+            priority,
+            ready,
+            completed: HashSet::new(),
+            failed: BTreeSet::new(),
+            soft: order.iter()
+                .flat_map(|key| region.successors(key).iter()
+                    .filter(|(next, _)| region.is_soft(key, next))
+                    .map(|(next, _)| (key.clone(), next.clone())))
+                .collect(),
+            blocked: HashSet::new(),
+            roots: self.invalidated.iter().filter(|key| region.contains(key)).cloned().collect(),
+        });
+        Ok(())
+    }
+
+    fn take_ready(&mut self, key: &str) -> Result<&mut Frontier, DagError> {
+        let frontier = self.frontier.as_mut().ok_or_else(|| DagError::NotReady(key.to_string()))?;
+        let entry = frontier.priority.get(key).map(|priority| (Reverse(*priority), key.to_string()));
+        match entry {
+            Some(entry) if frontier.ready.remove(&entry) => Ok(frontier),
+            _ => Err(DagError::NotReady(key.to_string())),
+        }
+    }
+
+    // With nothing left ready, everything still outstanding is blocked behind
+    // a failure, so the run is over.
+    fn finish_frontier_if_drained(&mut self) {
+        if self.frontier.as_ref().is_some_and(|frontier| frontier.ready.is_empty()) {
+            let frontier = self.frontier.take().expect("Frontier checked above");
+            self.quarantine = frontier.failed;
+            self.mark_dispatched_from(frontier.completed, &frontier.roots, frontier.id);
+        }
+    }
+}
+
+pub struct DispatchStream<'a> {
+    dag: &'a mut Dag,
+    max_in_flight: usize,
+    in_flight: BTreeSet<String>,
+}
+
+impl DispatchStream<'_> {
+    pub fn ack(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        self.settle(key)?;
+        self.dag.mark_complete(key)
+    }
+
+    pub fn fail(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        self.settle(key)?;
+        self.dag.mark_failed(key)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    // `next` also returns `None` while acks are outstanding; the run is only
+    // over once nothing is in flight either.
+    pub fn is_finished(&self) -> bool {
+        self.in_flight.is_empty() && self.dag.frontier.is_none()
+    }
+
+    fn settle(&mut self, key: &str) -> Result<(), DagError> {
+        if self.in_flight.remove(key) {
+            Ok(())
+        } else {
+            Err(DagError::NotReady(key.to_string()))
+        }
+    }
+}
+
+impl Iterator for DispatchStream<'_> {
+    type Item = NodeStrongRef;
+
+    fn next(&mut self) -> Option<NodeStrongRef> {
+        if self.in_flight.len() >= self.max_in_flight {
+            return None;
+        }
+        let frontier = self.dag.frontier.as_ref()?;
+        let key = frontier.ready.iter()
+            .map(|(_, key)| key)
+            .find(|key| !self.in_flight.contains(*key))?
+            .clone();
+        self.in_flight.insert(key.clone());
+        self.dag.get(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A feeds a long chain B -> C and a short leaf D.
+    fn fork() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C", "D"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.add_edge_directed("A", "D");
+        dag
+    }
+
+    #[test]
+    fn executor_pulls_work_in_priority_order() {
+        let mut dag = fork();
+        dag.dispatch(|_| ());
+        dag.update("A", ());
+        assert_eq!(dag.ready_nodes().unwrap(), vec!["A".to_string()]);
+        assert_eq!(dag.mark_complete("B"), Err(DagError::NotReady("B".to_string())));
+        assert_eq!(dag.mark_complete("A").unwrap(), vec!["B".to_string(), "D".to_string()]);
+        assert_eq!(dag.ready_nodes().unwrap(), vec!["B".to_string(), "D".to_string()]);
+        dag.mark_complete("D").unwrap();
+        dag.mark_complete("B").unwrap();
+        let run = dag.current_run().unwrap();
+        dag.mark_complete("C").unwrap();
+        assert!(dag.current_run().is_none());
+        assert_eq!(dag.last_dispatched("C"), Some(run));
+        assert!(dag.ready_nodes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn failure_blocks_descendants_and_keeps_them_dirty() {
+        let mut dag = fork();
+        dag.dispatch(|_| ());
+        dag.update("A", ());
+        dag.ready_nodes().unwrap();
+        dag.mark_complete("A").unwrap();
+        assert_eq!(dag.mark_failed("B").unwrap(), vec!["C".to_string()]);
+        assert_eq!(dag.failed_nodes(), vec!["B".to_string()]);
+        dag.mark_complete("D").unwrap();
+        assert!(dag.current_run().is_none());
+
+        assert_eq!(dag.ready_nodes().unwrap(), vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn stream_waits_for_acks_and_bounds_in_flight_work() {
+        let mut dag = fork();
+        dag.dispatch(|_| ());
+        dag.update("A", ());
+        let mut stream = dag.dispatch_stream(1).unwrap();
+        let key = |node: NodeStrongRef| node.borrow().key.clone();
+        assert_eq!(stream.next().map(key), Some("A".to_string()));
+        assert!(stream.next().is_none());
+        assert!(!stream.is_finished());
+
+        assert_eq!(stream.ack("A").unwrap(), vec!["B".to_string(), "D".to_string()]);
+        assert_eq!(stream.next().map(key), Some("B".to_string()));
+        assert!(stream.next().is_none());
+        assert_eq!(stream.ack("D"), Err(DagError::NotReady("D".to_string())));
+
+        let mut order = vec!["A".to_string(), "B".to_string()];
+        stream.ack("B").unwrap();
+        while let Some(node) = stream.next() {
+            let key = key(node);
+            stream.ack(&key).unwrap();
+            order.push(key);
+        }
+        assert_eq!(order, vec!["A", "B", "C", "D"]);
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn soft_successors_of_a_failure_still_run() {
+        let mut dag = fork();
+        dag.set_edge_soft("A", "D", true);
+        dag.dispatch(|_| ());
+        dag.update("A", ());
+        dag.update("D", ());
+        dag.ready_nodes().unwrap();
+        assert_eq!(dag.mark_failed("A").unwrap(), vec!["B".to_string(), "C".to_string()]);
+        assert_eq!(dag.ready_nodes().unwrap(), vec!["D".to_string()]);
+    }
+
+    #[test]
+    fn runs_survive_a_restart() {
+        let mut dag = fork();
+        dag.update("A", ());
+        dag.ready_nodes().unwrap();
+        dag.mark_complete("A").unwrap();
+        dag.mark_failed("D").unwrap();
+        let bytes = dag.checkpoint_run().unwrap().to_bytes();
+        let run = dag.current_run().unwrap();
+
+        let mut restarted = fork();
+        restarted.update("A", ());
+        restarted.resume_run(FrontierState::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(restarted.current_run(), Some(run));
+        assert_eq!(restarted.ready_nodes().unwrap(), vec!["B".to_string()]);
+        restarted.mark_complete("B").unwrap();
+        restarted.mark_complete("C").unwrap();
+        assert_eq!(restarted.last_dispatched("C"), Some(run));
+        assert_eq!(restarted.quarantined(), vec!["D"]);
+        assert!(restarted.dispatch_with_ctx(|_| ()).unwrap() > run);
+
+        assert!(matches!(FrontierState::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err().root(), DagError::Import(_)));
+        let mut smaller = Dag::new();
+        smaller.add("A", ());
+        let state = FrontierState::from_bytes(&bytes).unwrap();
+        assert_eq!(smaller.resume_run(state), Err(DagError::NodeNotFound("B".to_string())));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{borrow, Dag, NodeId};
+
+// Chained construction around one node, e.g.
+// `dag.add("report", r).depends_on("clean").weight(3)`. Every call names
+// other nodes only; the handle's own key is never repeated.
+pub struct NodeHandle<'a> {
+    dag: &'a mut Dag,
+    key: String,
+    last_edge: Option<(String, String)>,
+}
+
+impl<'a> NodeHandle<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.dag.id_of(&self.key).expect("Handle outlived its node")
+    }
+
+    // `dependency` runs before this node: adds the edge `dependency -> key`.
+    pub fn depends_on(mut self, dependency: &str) -> NodeHandle<'a> {
+        let dependency = self.dag.resolve_key(dependency).into_owned();
+        self.dag.add_edge_directed(&dependency, &self.key);
+        self.last_edge = Some((dependency, self.key.clone()));
+        self
+    }
+
+    // `dependent` runs after this node: adds the edge `key -> dependent`.
+    pub fn feeds(mut self, dependent: &str) -> NodeHandle<'a> {
+        let dependent = self.dag.resolve_key(dependent).into_owned();
+        self.dag.add_edge_directed(&self.key, &dependent);
+        self.last_edge = Some((self.key.clone(), dependent));
+        self
+    }
+
+    // Weighs the edge added by the previous `depends_on` or `feeds`.
+    pub fn weight(self, weight: i32) -> NodeHandle<'a> {
+        let (from_key, to_key) = self.last_edge.as_ref().expect("weight() needs an edge added before it");
+        let node = self.dag.get(from_key).expect("Cannot find node to weigh edge from");
+        let mut borrowed_node = borrow::or_panic(borrow::write(&node, from_key, "weight"));
+        if let Some(edge) = borrowed_node.edges.iter_mut().rev().find(|edge| &edge.to_key == to_key) {
+            edge.weight = weight;
+        }
+        self
+    }
+}
+
+impl Dag {
+    // Panics if `key` isn't in the graph, like `add_edge`.
+    pub fn node(&mut self, key: &str) -> NodeHandle<'_> {
+        let key = self.resolve_key(key).into_owned();
+        self.get(&key).expect("Cannot find node for handle");
+        NodeHandle { dag: self, key, last_edge: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construction_chains_from_a_handle() {
+        let mut dag = Dag::new();
+        dag.add("extract", 1);
+        dag.add("clean", 2);
+        let report = dag.add("report", 3).depends_on("clean").weight(3).depends_on("extract").key().to_string();
+        assert_eq!(report, "report");
+        dag.node("extract").feeds("clean");
+
+        assert_eq!(dag.edge_weight("clean", "report"), Some(3));
+        assert_eq!(dag.edge_weight("extract", "report"), Some(1));
+        assert_eq!(dag.successors("extract"), vec!["report".to_string(), "clean".to_string()]);
+        assert_eq!(dag.node("clean").id(), dag.id_of("clean").unwrap());
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, SystemTime};
+
+use crate::Dag;
+
+// A point-in-time summary for monitoring. Counts are cheap to alert on;
+// the key lists name what needs looking at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    pub nodes: usize,
+    pub max_nodes: Option<usize>,
+    pub edges: usize,
+    pub max_edges: Option<usize>,
+    // Edges holding a node that goes by another key; see `invariant_violations`.
+    pub dangling_edges: usize,
+    // Edges to removed nodes, awaiting `compact`.
+    pub dead_edges: usize,
+    pub orphans: usize,
+    pub invalidated: usize,
+    pub stale_invalidations: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl Health {
+    // Orphans and dead edges are normal wear, so they don't count against it.
+    pub fn is_healthy(&self) -> bool {
+        self.dangling_edges == 0
+            && self.stale_invalidations.is_empty()
+            && self.failed.is_empty()
+            && self.max_nodes.is_none_or(|maximum| self.nodes < maximum)
+            && self.max_edges.is_none_or(|maximum| self.edges < maximum)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "healthy": self.is_healthy(),
+            "nodes": self.nodes,
+            "max_nodes": self.max_nodes,
+            "edges": self.edges,
+            "max_edges": self.max_edges,
+            "dangling_edges": self.dangling_edges,
+            "dead_edges": self.dead_edges,
+            "orphans": self.orphans,
+            "invalidated": self.invalidated,
+            "stale_invalidations": self.stale_invalidations,
+            "failed": self.failed,
+        }).to_string()
+    }
+}
+
+impl Dag {
+    // An invalidation is stale once it has waited longer than `stale_after`
+    // for a dispatch. Only `update` stamps invalidations; nodes marked
+    // dirty some other way are never reported stale.
+    pub fn health(&self, stale_after: Duration) -> Health {
+        self.health_at(SystemTime::now(), stale_after)
+    }
+
+    pub fn health_at(&self, now: SystemTime, stale_after: Duration) -> Health {
+        let (mut dangling_edges, mut dead_edges) = (0, 0);
+        for node in self.nodes.borrow().values() {
+            for edge in node.borrow().edges.iter() {
+                if self.is_dangling(edge) {
+                    dangling_edges += 1;
+                } else if self.live_target(edge).is_none() {
+                    dead_edges += 1;
+                }
+            }
+        }
+        let topology = self.topology();
+        let in_degrees = topology.in_degrees();
+        let orphans = topology.keys()
+            .filter(|key| in_degrees[*key] == 0 && topology.successors(key).is_empty())
+            .count();
+        let mut stale_invalidations: Vec<String> = self.invalidated.iter()
+            .filter(|key| self.invalidated_at.get(*key)
+                .is_some_and(|since| now.duration_since(*since).unwrap_or_default() > stale_after))
+            .cloned()
+            .collect();
+        stale_invalidations.sort();
+        Health {
+            nodes: self.node_count(),
+            max_nodes: self.limits.max_nodes,
+            edges: self.edge_count(),
+            max_edges: self.limits.max_edges,
+            dangling_edges,
+            dead_edges,
+            orphans,
+            invalidated: self.invalidated.len(),
+            stale_invalidations,
+            failed: self.quarantined(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Limits;
+
+    #[test]
+    fn summarizes_what_needs_attention() {
+        let mut dag = Dag::with_limits(Limits { max_nodes: Some(10), ..Limits::default() });
+        for key in ["fetch", "build", "lonely", "gone"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("fetch", "build");
+        dag.add_edge_directed("build", "gone");
+        dag.remove("gone");
+        dag.update("fetch", ());
+        let health = dag.health(Duration::from_secs(60));
+        assert_eq!((health.nodes, health.edges, health.dead_edges, health.orphans), (3, 2, 1, 1));
+        assert!(health.is_healthy());
+
+        let later = SystemTime::now() + Duration::from_secs(120);
+        assert_eq!(dag.health_at(later, Duration::from_secs(60)).stale_invalidations, vec!["fetch"]);
+        dag.try_dispatch(|ctx| if ctx.key() == "build" { Err("broken") } else { Ok(()) }).unwrap();
+        let health = dag.health_at(later, Duration::from_secs(60));
+        assert!(health.stale_invalidations.is_empty());
+        assert_eq!(health.failed, vec!["build"]);
+        assert!(!health.is_healthy());
+        #[cfg(feature = "json")]
+        assert!(health.to_json().contains(r#""failed":["build"]"#));
+    }
+}
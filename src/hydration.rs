@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::{Dag, Node, NodeData, NodeStrongRef};
+
+type NodeLoader = Box<dyn Fn(&str) -> Option<Box<NodeData>>>;
+type EdgeLoader = Box<dyn Fn(&str) -> Vec<(String, i32)>>;
+type PayloadResolver = Box<dyn Fn(&str) -> Option<Box<NodeData>>>;
+
+#[derive(Default)]
+pub(crate) struct Loaders {
+    node: Option<NodeLoader>,
+    edges: Option<EdgeLoader>,
+    // Hydrated nodes whose outgoing edges haven't been asked for yet.
+    undiscovered: RefCell<HashSet<String>>,
+    // Hydrated nodes that don't have an id or a place in the key index yet.
+    unregistered: RefCell<Vec<String>>,
+    resolver: Option<PayloadResolver>,
+    // Nodes holding a stand-in until the resolver supplies their payload.
+    unresolved: RefCell<HashSet<String>>,
+}
+
+impl Loaders {
+    pub(crate) fn forget(&self, key: &str) {
+        self.undiscovered.borrow_mut().remove(key);
+        self.unresolved.borrow_mut().remove(key);
+    }
+
+    // For payloads set directly, which the resolver must not overwrite.
+    pub(crate) fn mark_resolved(&self, key: &str) {
+        self.unresolved.borrow_mut().remove(key);
+    }
+}
+
+impl Dag {
+    // `get` on a key the graph doesn't hold asks the node loader for it. A
+    // hydrated node's outgoing edges come from the edge loader the first time
+    // the node is fetched or traversed; targets are hydrated as needed, but
+    // their own edges wait until they are visited in turn.
+    pub fn set_node_loader<T, F>(&mut self, loader: F)
+        where T: Debug + 'static, F: Fn(&str) -> Option<T> + 'static {
+        self.loaders.node = Some(Box::new(move |key| loader(key).map(|data| Box::new(data) as Box<NodeData>)));
+    }
+
+    pub fn set_edge_loader<F>(&mut self, loader: F) where F: Fn(&str) -> Vec<(String, i32)> + 'static {
+        self.loaders.edges = Some(Box::new(loader));
+    }
+
+    pub fn clear_loaders(&mut self) {
+        self.loaders.node = None;
+        self.loaders.edges = None;
+        self.loaders.undiscovered.borrow_mut().clear();
+        self.loaders.resolver = None;
+        self.loaders.unresolved.borrow_mut().clear();
+    }
+
+    pub fn has_undiscovered_edges(&self, key: &str) -> bool {
+        self.loaders.undiscovered.borrow().contains(key)
+    }
+
+    // Every node now in the graph gets its payload from `resolver` the first
+    // time it is fetched or traversed, replacing what it holds. A node the
+    // resolver returns `None` for is asked about again next time.
+    pub fn set_payload_resolver<T, F>(&mut self, resolver: F)
+        where T: Debug + 'static, F: Fn(&str) -> Option<T> + 'static {
+        self.loaders.resolver = Some(Box::new(move |key| resolver(key).map(|data| Box::new(data) as Box<NodeData>)));
+        *self.loaders.unresolved.borrow_mut() = self.nodes.borrow().keys().cloned().collect();
+    }
+
+    pub fn has_unresolved_payload(&self, key: &str) -> bool {
+        self.loaders.unresolved.borrow().contains(self.resolve_key(key).as_ref())
+    }
+
+    pub(crate) fn resolve_payload(&self, node: &NodeStrongRef) {
+        let Some(resolver) = self.loaders.resolver.as_ref() else {
+            return;
+        };
+        let Ok(mut borrowed) = node.try_borrow_mut() else {
+            return;
+        };
+        if !self.loaders.unresolved.borrow().contains(&borrowed.key) {
+            return;
+        }
+        if let Some(data) = resolver(&borrowed.key) {
+            borrowed.data = data;
+            self.loaders.unresolved.borrow_mut().remove(&borrowed.key);
+        }
+    }
+
+    // Hydration happens behind `&self`, so ids and index entries for the new
+    // nodes are only handed out at the next mutation.
+    pub(crate) fn register_hydrated(&mut self) {
+        let keys: Vec<String> = self.loaders.unregistered.borrow_mut().drain(..).collect();
+        for key in keys {
+            if self.nodes.borrow().contains_key(&key) {
+                self.index.insert(key.clone());
+                self.ids.assign(&key);
+            }
+        }
+    }
+
+    pub(crate) fn hydrate_missing(&self, key: &str) -> Option<NodeStrongRef> {
+        let loader = self.loaders.node.as_ref()?;
+        if self.check_node_quota(&[key]).is_err() {
+            return None;
+        }
+        let data = loader(key)?;
+        let node = Rc::new(RefCell::new(Node::new(key.to_string(), data)));
+        // Inserting fails if a caller further up is iterating the node map.
+        self.nodes.try_borrow_mut().ok()?.insert(key.to_string(), Rc::clone(&node));
+        let sequence = self.next_sequence();
+        self.sequences.borrow_mut().insert(key.to_string(), sequence);
+        self.loaders.unregistered.borrow_mut().push(key.to_string());
+        if self.loaders.edges.is_some() {
+            self.loaders.undiscovered.borrow_mut().insert(key.to_string());
+        }
+        Some(node)
+    }
+
+    pub(crate) fn discover_edges(&self, node: &NodeStrongRef) {
+        let Some(loader) = self.loaders.edges.as_ref() else {
+            return;
+        };
+        let Ok(mut borrowed) = node.try_borrow_mut() else {
+            return;
+        };
+        let key = borrowed.key.clone();
+        if !self.loaders.undiscovered.borrow_mut().remove(&key) {
+            return;
+        }
+        for (to_key, weight) in loader(&key) {
+            let existing = self.nodes.borrow().get(&to_key).cloned();
+            let target = if to_key == key {
+                Some(Rc::clone(node))
+            } else {
+                existing.or_else(|| self.hydrate_missing(&to_key))
+            };
+            if let Some(target) = target {
+                borrowed.add_edge(target, weight);
+                borrowed.edges.last_mut().expect("Edge just pushed").sequence = self.next_sequence();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A chain 0 -> 1 -> 2 -> ... that only exists on demand.
+    fn external() -> Dag {
+        let mut dag = Dag::new();
+        dag.set_node_loader(|key| key.parse::<u32>().ok().filter(|n| *n < 1000));
+        dag.set_edge_loader(|key| match key.parse::<u32>() {
+            Ok(n) if n + 1 < 1000 => vec![((n + 1).to_string(), 1)],
+            _ => vec![],
+        });
+        dag
+    }
+
+    #[test]
+    fn get_hydrates_one_step_at_a_time() {
+        let mut dag = external();
+        assert!(dag.get("missing").is_none());
+        let node = dag.get("10").unwrap();
+        assert_eq!(format!("{:?}", node.borrow().data), "10");
+        assert_eq!(dag.node_count(), 2);
+        assert!(dag.has_undiscovered_edges("11"));
+        assert_eq!(dag.successors("10"), vec!["11".to_string()]);
+
+        assert!(dag.id_of("10").is_none());
+        dag.add("local", 0);
+        assert!(dag.id_of("10").is_some());
+        assert_eq!(dag.find_keys("1*").count(), 2);
+        assert!(dag.node_sequence("10") < dag.node_sequence("11"));
+        assert!(dag.edge_sequence("10", "11") > dag.node_sequence("11"));
+        assert!(dag.node_sequence("local") > dag.edge_sequence("10", "11"));
+    }
+
+    #[test]
+    fn dispatch_discovers_edges_as_it_goes() {
+        let mut dag = external();
+        dag.update("995", 995);
+        let mut visited = vec![];
+        dag.dispatch_with_id(|node, _| visited.push(node.borrow().key.clone()));
+        assert_eq!(visited, vec!["995", "996", "997", "998", "999"]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::Dag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+// Dense ids: slots freed by removals are handed out again, so ids stay below
+// `id_bound()` and can index plain arrays. An id is only stable while its
+// node is in the graph.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IdMap {
+    ids: HashMap<String, NodeId>,
+    keys: Vec<Option<String>>,
+    free: Vec<usize>,
+}
+
+impl IdMap {
+    pub(crate) fn assign(&mut self, key: &str) -> NodeId {
+        if let Some(id) = self.ids.get(key) {
+            return *id;
+        }
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.keys[slot] = Some(key.to_string());
+                slot
+            },
+            None => {
+                self.keys.push(Some(key.to_string()));
+                self.keys.len() - 1
+            },
+        };
+        self.ids.insert(key.to_string(), NodeId(slot));
+        NodeId(slot)
+    }
+
+    pub(crate) fn slot(&self, key: &str) -> Option<usize> {
+        self.ids.get(key).map(|id| id.0)
+    }
+
+    pub(crate) fn release(&mut self, key: &str) {
+        if let Some(NodeId(slot)) = self.ids.remove(key) {
+            self.keys[slot] = None;
+            self.free.push(slot);
+        }
+    }
+}
+
+impl Dag {
+    pub fn id_of(&self, key: &str) -> Option<NodeId> {
+        self.ids.ids.get(self.resolve_key(key).as_ref()).copied()
+    }
+
+    pub fn key_of(&self, id: NodeId) -> Option<&str> {
+        self.ids.keys.get(id.0).and_then(|key| key.as_deref())
+    }
+
+    pub fn iter_ids(&self) -> impl Iterator<Item = (NodeId, &str)> {
+        self.ids.keys.iter().enumerate()
+            .filter_map(|(slot, key)| key.as_deref().map(|key| (NodeId(slot), key)))
+    }
+
+    // Exclusive upper bound on ids currently in use, for sizing arrays.
+    pub fn id_bound(&self) -> usize {
+        self.ids.keys.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_map_both_ways_and_are_reused() {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        let a = dag.id_of("A").unwrap();
+        assert_eq!(dag.key_of(a), Some("A"));
+        dag.add("A", 10);
+        assert_eq!(dag.id_of("A"), Some(a));
+
+        dag.remove("A");
+        assert_eq!(dag.key_of(a), None);
+        dag.add("C", 3);
+        assert_eq!(dag.id_of("C"), Some(a));
+        assert_eq!(dag.id_bound(), 2);
+
+        let mut ids: Vec<(usize, &str)> = dag.iter_ids().map(|(id, key)| (id.index(), key)).collect();
+        ids.sort();
+        assert_eq!(ids, vec![(0, "C"), (1, "B")]);
+    }
+}
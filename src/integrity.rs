@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::merkle::ContentHash;
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub nodes: BTreeMap<String, ContentHash>,
+    pub edges: Vec<(String, String, i32)>,
+    pub keyed: bool,
+    pub digest: ContentHash,
+}
+
+impl Manifest {
+    fn build(nodes: BTreeMap<String, ContentHash>, mut edges: Vec<(String, String, i32)>, secret: Option<&[u8]>) -> Manifest {
+        edges.sort();
+        let digest = digest(&nodes, &edges, secret);
+        Manifest {
+            nodes,
+            edges,
+            keyed: secret.is_some(),
+            digest,
+        }
+    }
+}
+
+fn sha256(parts: &[&[u8]]) -> ContentHash {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    ContentHash::from_bytes(hasher.finalize().into())
+}
+
+// Length-prefixed so that key boundaries can't be shifted between fields.
+fn canonical_bytes(nodes: &BTreeMap<String, ContentHash>, edges: &[(String, String, i32)]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut push = |field: &[u8]| {
+        bytes.extend((field.len() as u64).to_le_bytes());
+        bytes.extend(field);
+    };
+    for (key, hash) in nodes.iter() {
+        push(key.as_bytes());
+        push(hash.as_bytes());
+    }
+    for (from_key, to_key, weight) in edges.iter() {
+        push(from_key.as_bytes());
+        push(to_key.as_bytes());
+        push(&weight.to_le_bytes());
+    }
+    bytes
+}
+
+fn digest(nodes: &BTreeMap<String, ContentHash>, edges: &[(String, String, i32)], secret: Option<&[u8]>) -> ContentHash {
+    let message = canonical_bytes(nodes, edges);
+    match secret {
+        Some(secret) => hmac_sha256(secret, &message),
+        None => sha256(&[&message]),
+    }
+}
+
+// HMAC (RFC 2104) over SHA-256.
+fn hmac_sha256(secret: &[u8], message: &[u8]) -> ContentHash {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if secret.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(sha256(&[secret]).as_bytes());
+    } else {
+        block[..secret.len()].copy_from_slice(secret);
+    }
+    let inner_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+    let inner = sha256(&[&inner_pad, message]);
+    sha256(&[&outer_pad, inner.as_bytes()])
+}
+
+impl Dag {
+    pub fn seal(&self) -> Manifest {
+        self.build_manifest(None)
+    }
+
+    pub fn seal_with_key(&self, secret: &[u8]) -> Manifest {
+        self.build_manifest(Some(secret))
+    }
+
+    pub fn verify(&self, manifest: &Manifest) -> Result<(), DagError> {
+        self.check_manifest(manifest, None)
+    }
+
+    pub fn verify_with_key(&self, manifest: &Manifest, secret: &[u8]) -> Result<(), DagError> {
+        self.check_manifest(manifest, Some(secret))
+    }
+
+    fn build_manifest(&self, secret: Option<&[u8]>) -> Manifest {
+        let topology = self.topology();
+        let nodes = topology.keys()
+            .map(|key| {
+                let node = self.get(key).expect("Topology node missing from graph");
+                let rendered = format!("{:?}", node.borrow().data);
+                (key.clone(), sha256(&[key.as_bytes(), &[0], rendered.as_bytes()]))
+            })
+            .collect();
+        let edges = topology.keys()
+            .flat_map(|key| topology.successors(key).iter()
+                .map(move |(to_key, weight)| (key.clone(), to_key.clone(), *weight)))
+            .collect();
+        Manifest::build(nodes, edges, secret)
+    }
+
+    fn check_manifest(&self, manifest: &Manifest, secret: Option<&[u8]>) -> Result<(), DagError> {
+        let mismatch = |detail: String| Err(DagError::IntegrityMismatch(detail));
+        if manifest.keyed != secret.is_some() {
+            return mismatch("manifest keying does not match verification".to_string());
+        }
+        if digest(&manifest.nodes, &manifest.edges, secret) != manifest.digest {
+            return mismatch("manifest digest does not match its contents".to_string());
+        }
+        let actual = self.build_manifest(secret);
+        for (key, hash) in manifest.nodes.iter() {
+            match actual.nodes.get(key) {
+                Some(found) if found == hash => (),
+                Some(_) => return mismatch(format!("node {} has been modified", key)),
+                None => return mismatch(format!("node {} is missing", key)),
+            }
+        }
+        if let Some(key) = actual.nodes.keys().find(|key| !manifest.nodes.contains_key(*key)) {
+            return mismatch(format!("node {} is not in the manifest", key));
+        }
+        if actual.edges != manifest.edges {
+            return mismatch("edges differ from the manifest".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.link("A", "B", 1);
+        dag
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(mac.to_string(), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn untouched_graph_verifies() {
+        let dag = graph();
+        assert!(dag.verify(&dag.seal()).is_ok());
+        let manifest = dag.seal_with_key(b"secret");
+        assert!(dag.verify_with_key(&manifest, b"secret").is_ok());
+        assert!(dag.verify_with_key(&manifest, b"other").is_err());
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let mut dag = graph();
+        let manifest = dag.seal();
+        dag.update("B", 3);
+        assert_eq!(dag.verify(&manifest), Err(DagError::IntegrityMismatch("node B has been modified".to_string())));
+
+        let dag = graph();
+        let mut forged = manifest.clone();
+        forged.edges.clear();
+        assert!(dag.verify(&forged).is_err());
+    }
+}
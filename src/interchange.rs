@@ -0,0 +1,869 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::store::stored_payload;
+use crate::{borrow, Dag, DagError, Migrations, StoredGraph};
+
+// Every format but the skeleton carries the content of a `StoredGraph`:
+// node keys with their rendered payloads and invalidation marks, and edges
+// with their weights and softness in the order they were added. Attributes
+// other tools add are ignored on import. Imported payloads come back as
+// `StoredPayload`s.
+
+pub(crate) const MAGIC: &[u8; 4] = b"DAGB";
+const SKELETON_MAGIC: &[u8; 4] = b"DAGS";
+// Version 1 files predate soft edges and version 2 files payload schema
+// versions; both still load, as schema version 0.
+const VERSION: u8 = 3;
+
+pub(crate) fn import_error(detail: impl Into<String>) -> DagError {
+    DagError::Import(detail.into())
+}
+
+struct Record {
+    key: String,
+    payload: String,
+    invalidated: bool,
+}
+
+fn records(stored: &StoredGraph) -> Vec<Record> {
+    let invalidated: BTreeSet<&String> = stored.invalidated.iter().collect();
+    stored.nodes.iter()
+        .map(|(key, payload)| Record { key: key.clone(), payload: payload.clone(), invalidated: invalidated.contains(key) })
+        .collect()
+}
+
+struct Link {
+    from: String,
+    to: String,
+    weight: i32,
+    soft: bool,
+}
+
+impl Link {
+    fn new(from: String, to: String, weight: i32) -> Link {
+        Link { from, to, weight, soft: false }
+    }
+}
+
+fn links(stored: &StoredGraph) -> Vec<Link> {
+    let soft: BTreeSet<(&String, &String)> = stored.soft_edges.iter().map(|(from, to)| (from, to)).collect();
+    stored.edges.iter()
+        .map(|(from, to, weight)| Link {
+            soft: soft.contains(&(from, to)),
+            ..Link::new(from.clone(), to.clone(), *weight)
+        })
+        .collect()
+}
+
+// A parsed binary or JSON file, which unlike the other formats say which
+// payload schema they were written with.
+struct Snapshot {
+    schema_version: u32,
+    nodes: Vec<Record>,
+    edges: Vec<Link>,
+}
+
+impl Snapshot {
+    fn load(mut self, migrations: Option<&Migrations>) -> Result<Dag, DagError> {
+        if let Some(migrations) = migrations {
+            migrations.upgrade(self.schema_version, self.nodes.iter_mut().map(|record| &mut record.payload))?;
+            self.schema_version = migrations.current();
+        }
+        let mut dag = assemble(self.nodes, self.edges)?;
+        dag.set_schema_version(self.schema_version);
+        Ok(dag)
+    }
+}
+
+fn assemble(nodes: Vec<Record>, edges: Vec<Link>) -> Result<Dag, DagError> {
+    let keys: BTreeSet<&String> = nodes.iter().map(|record| &record.key).collect();
+    if keys.len() != nodes.len() {
+        let mut seen = BTreeSet::new();
+        let duplicate = nodes.iter().find(|record| !seen.insert(&record.key)).expect("Duplicate counted above");
+        return Err(DagError::DuplicateNode(duplicate.key.clone()));
+    }
+    if let Some(missing) = edges.iter().flat_map(|link| [&link.from, &link.to]).find(|key| !keys.contains(key)) {
+        return Err(DagError::NodeNotFound(missing.clone()));
+    }
+    let stored = StoredGraph {
+        invalidated: nodes.iter().filter(|record| record.invalidated).map(|record| record.key.clone()).collect(),
+        nodes: nodes.into_iter().map(|record| (record.key, record.payload)).collect(),
+        soft_edges: edges.iter().filter(|link| link.soft).map(|link| (link.from.clone(), link.to.clone())).collect(),
+        edges: edges.into_iter().map(|link| (link.from, link.to, link.weight)).collect(),
+    };
+    Ok(Dag::from_stored_graph(stored, stored_payload))
+}
+
+fn dot_quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Debug, PartialEq)]
+enum DotToken {
+    Id(String),
+    Arrow,
+    Symbol(char),
+}
+
+fn dot_tokens(text: &str) -> Result<Vec<DotToken>, DagError> {
+    let mut tokens = vec![];
+    let mut chars: Peekable<Chars> = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|c| *c == '\n');
+            },
+            '#' => {
+                chars.by_ref().find(|c| *c == '\n');
+            },
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                tokens.push(DotToken::Arrow);
+            },
+            '{' | '}' | '[' | ']' | ';' | ',' | '=' => tokens.push(DotToken::Symbol(c)),
+            '"' => {
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => id.push('\n'),
+                            Some('"') => id.push('"'),
+                            Some('\\') => id.push('\\'),
+                            Some(other) => {
+                                id.push('\\');
+                                id.push(other);
+                            },
+                            None => return Err(import_error("unterminated string")),
+                        },
+                        Some(c) => id.push(c),
+                        None => return Err(import_error("unterminated string")),
+                    }
+                }
+                tokens.push(DotToken::Id(id));
+            },
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+                let mut id = String::from(c);
+                while let Some(next) = chars.peek().copied().filter(|c| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    id.push(next);
+                    chars.next();
+                }
+                tokens.push(DotToken::Id(id));
+            },
+            other => return Err(import_error(format!("unexpected character {:?}", other))),
+        }
+    }
+    Ok(tokens)
+}
+
+fn dot_attributes(tokens: &[DotToken], at: &mut usize) -> Result<HashMap<String, String>, DagError> {
+    let mut attributes = HashMap::new();
+    while tokens.get(*at) == Some(&DotToken::Symbol('[')) {
+        *at += 1;
+        loop {
+            match (tokens.get(*at), tokens.get(*at + 1), tokens.get(*at + 2)) {
+                (Some(DotToken::Symbol(']')), _, _) => {
+                    *at += 1;
+                    break;
+                },
+                (Some(DotToken::Symbol(',' | ';')), _, _) => *at += 1,
+                (Some(DotToken::Id(name)), Some(DotToken::Symbol('=')), Some(DotToken::Id(value))) => {
+                    attributes.insert(name.clone(), value.clone());
+                    *at += 3;
+                },
+                _ => return Err(import_error("malformed attribute list")),
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+fn parse_flag(value: &str) -> Result<bool, DagError> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(import_error(format!("invalid flag {:?}", other))),
+    }
+}
+
+fn parse_weight(value: &str) -> Result<i32, DagError> {
+    value.trim().parse().map_err(|_| import_error(format!("invalid weight {:?}", value)))
+}
+
+fn parse_dot(text: &str) -> Result<Dag, DagError> {
+    let tokens = dot_tokens(text)?;
+    let mut at = 0;
+    if !matches!(tokens.first(), Some(DotToken::Id(keyword)) if keyword == "digraph") {
+        return Err(import_error("expected digraph"));
+    }
+    at += 1;
+    if matches!(tokens.get(at), Some(DotToken::Id(_))) {
+        at += 1;
+    }
+    if tokens.get(at) != Some(&DotToken::Symbol('{')) {
+        return Err(import_error("expected {"));
+    }
+    at += 1;
+    let (mut nodes, mut edges) = (vec![], vec![]);
+    loop {
+        match tokens.get(at) {
+            Some(DotToken::Symbol('}')) => break,
+            Some(DotToken::Symbol(';')) => at += 1,
+            Some(DotToken::Id(first)) => {
+                at += 1;
+                let mut chain = vec![first.clone()];
+                while tokens.get(at) == Some(&DotToken::Arrow) {
+                    match tokens.get(at + 1) {
+                        Some(DotToken::Id(next)) => chain.push(next.clone()),
+                        _ => return Err(import_error("edge without a target")),
+                    }
+                    at += 2;
+                }
+                let attributes = dot_attributes(&tokens, &mut at)?;
+                if chain.len() > 1 {
+                    let weight = attributes.get("weight").map(|value| parse_weight(value)).transpose()?.unwrap_or(1);
+                    let soft = attributes.get("soft").map(|value| parse_flag(value)).transpose()?.unwrap_or(false);
+                    for pair in chain.windows(2) {
+                        edges.push(Link { soft, ..Link::new(pair[0].clone(), pair[1].clone(), weight) });
+                    }
+                } else if !matches!(first.as_str(), "graph" | "node" | "edge") {
+                    nodes.push(Record {
+                        key: first.clone(),
+                        payload: attributes.get("payload").cloned().unwrap_or_default(),
+                        invalidated: attributes.get("invalidated").map(|value| parse_flag(value)).transpose()?.unwrap_or(false),
+                    });
+                }
+            },
+            _ => return Err(import_error("expected a statement or }")),
+        }
+    }
+    assemble(nodes, edges)
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn xml_unescape(text: &str) -> Result<String, DagError> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| import_error("unterminated entity"))? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity.strip_prefix("#x").map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|decimal| decimal.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| import_error(format!("unknown entity &{};", entity)))?,
+        };
+        unescaped.push(c);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+struct XmlTag {
+    name: String,
+    attributes: HashMap<String, String>,
+    closing: bool,
+    empty: bool,
+}
+
+fn xml_tag(body: &str) -> Result<XmlTag, DagError> {
+    let (closing, body) = match body.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, body),
+    };
+    let (empty, body) = match body.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, body),
+    };
+    let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+    let mut attributes = HashMap::new();
+    let mut rest = body[name_end..].trim_start();
+    while !rest.is_empty() {
+        let equals = rest.find('=').ok_or_else(|| import_error("malformed attribute"))?;
+        let name = rest[..equals].trim().to_string();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| import_error("unquoted attribute"))?;
+        let end = value[1..].find(quote).ok_or_else(|| import_error("unterminated attribute"))? + 1;
+        attributes.insert(name, xml_unescape(&value[1..end])?);
+        rest = value[end + 1..].trim_start();
+    }
+    Ok(XmlTag { name: body[..name_end].to_string(), attributes, closing, empty })
+}
+
+fn parse_graphml(text: &str) -> Result<Dag, DagError> {
+    let (mut nodes, mut edges) = (vec![], vec![]);
+    let mut key_names: HashMap<String, String> = HashMap::new();
+    let mut node: Option<Record> = None;
+    let mut edge: Option<Link> = None;
+    let mut data: Option<(String, String)> = None;
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        if let Some((_, value)) = data.as_mut() {
+            value.push_str(&xml_unescape(&rest[..start])?);
+        }
+        rest = &rest[start..];
+        let skip_to = |marker: &str, rest: &str| rest.find(marker).map(|end| end + marker.len())
+            .ok_or_else(|| import_error(format!("missing {}", marker)));
+        if rest.starts_with("<?") {
+            rest = &rest[skip_to("?>", rest)?..];
+            continue;
+        }
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to("-->", rest)?..];
+            continue;
+        }
+        let end = skip_to(">", rest)?;
+        let tag = xml_tag(&rest[1..end - 1])?;
+        rest = &rest[end..];
+        let attribute = |name: &str| tag.attributes.get(name).cloned()
+            .ok_or_else(|| import_error(format!("<{}> needs a {} attribute", tag.name, name)));
+        match (tag.name.as_str(), tag.closing) {
+            ("key", false) => {
+                let name = tag.attributes.get("attr.name").cloned().unwrap_or(attribute("id")?);
+                key_names.insert(attribute("id")?, name);
+            },
+            ("node", false) => {
+                node = Some(Record { key: attribute("id")?, payload: String::new(), invalidated: false });
+            },
+            ("edge", false) => {
+                edge = Some(Link::new(attribute("source")?, attribute("target")?, 1));
+            },
+            ("data", false) if !tag.empty => data = Some((attribute("key")?, String::new())),
+            ("data", true) => {
+                let (key, value) = data.take().ok_or_else(|| import_error("unmatched </data>"))?;
+                match (key_names.get(&key).map(|name| name.as_str()).unwrap_or(&key), node.as_mut(), edge.as_mut()) {
+                    ("payload", Some(node), _) => node.payload = value,
+                    ("invalidated", Some(node), _) => node.invalidated = parse_flag(value.trim())?,
+                    ("weight", _, Some(edge)) => edge.weight = parse_weight(&value)?,
+                    ("soft", _, Some(edge)) => edge.soft = parse_flag(value.trim())?,
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+        if tag.closing || tag.empty {
+            match tag.name.as_str() {
+                "node" => nodes.extend(node.take()),
+                "edge" => edges.extend(edge.take()),
+                _ => {},
+            }
+        }
+    }
+    assemble(nodes, edges)
+}
+
+pub(crate) fn push_bytes(buffer: &mut Vec<u8>, text: &str) {
+    buffer.extend((text.len() as u32).to_le_bytes());
+    buffer.extend(text.as_bytes());
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    pub(crate) fn take(&mut self, count: usize) -> Result<&[u8], DagError> {
+        if self.bytes.len() < count {
+            return Err(import_error("truncated input"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DagError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("Took four bytes")))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DagError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("Took eight bytes")))
+    }
+
+    pub(crate) fn text(&mut self) -> Result<String, DagError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| import_error("invalid UTF-8"))
+    }
+}
+
+fn parse_bytes(bytes: &[u8]) -> Result<Snapshot, DagError> {
+    let mut reader = Reader { bytes };
+    if reader.take(4)? != MAGIC {
+        return Err(import_error("not a binary graph"));
+    }
+    let version = reader.take(1)?[0];
+    if version == 0 || version > VERSION {
+        return Err(import_error(format!("unsupported version {}", version)));
+    }
+    let schema_version = if version >= 3 { reader.u32()? } else { 0 };
+    let mut nodes = vec![];
+    for _ in 0..reader.u32()? {
+        let (key, payload) = (reader.text()?, reader.text()?);
+        nodes.push(Record { key, payload, invalidated: reader.take(1)?[0] != 0 });
+    }
+    let mut edges = vec![];
+    for _ in 0..reader.u32()? {
+        let (from, to) = (reader.text()?, reader.text()?);
+        let mut link = Link::new(from, to, reader.u32()? as i32);
+        if version >= 2 {
+            link.soft = reader.take(1)?[0] != 0;
+        }
+        edges.push(link);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(import_error("trailing bytes"));
+    }
+    Ok(Snapshot { schema_version, nodes, edges })
+}
+
+// Binary layout without payloads or invalidation marks; edges keep their
+// weight and softness.
+fn parse_skeleton(bytes: &[u8]) -> Result<StoredGraph, DagError> {
+    let mut reader = Reader { bytes };
+    if reader.take(4)? != SKELETON_MAGIC {
+        return Err(import_error("not a graph skeleton"));
+    }
+    let mut nodes = vec![];
+    for _ in 0..reader.u32()? {
+        nodes.push(Record { key: reader.text()?, payload: String::new(), invalidated: false });
+    }
+    let mut edges = vec![];
+    for _ in 0..reader.u32()? {
+        let (from, to) = (reader.text()?, reader.text()?);
+        let link = Link::new(from, to, reader.u32()? as i32);
+        edges.push(Link { soft: reader.take(1)?[0] != 0, ..link });
+    }
+    if !reader.bytes.is_empty() {
+        return Err(import_error("trailing bytes"));
+    }
+    Ok(assemble(nodes, edges)?.to_stored_graph())
+}
+
+impl Dag {
+    pub fn to_dot(&self) -> String {
+        let stored = self.to_stored_graph();
+        let mut dot = String::from("digraph {\n");
+        for record in records(&stored) {
+            dot.push_str(&format!("    {} [payload={}", dot_quote(&record.key), dot_quote(&record.payload)));
+            if record.invalidated {
+                dot.push_str(", invalidated=true");
+            }
+            dot.push_str("];\n");
+        }
+        for link in links(&stored) {
+            dot.push_str(&format!("    {} -> {} [weight={}", dot_quote(&link.from), dot_quote(&link.to), link.weight));
+            if link.soft {
+                dot.push_str(", soft=true");
+            }
+            dot.push_str("];\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // The union of both graphs for review: what `older` lacks is green, what
+    // is gone since is red and dashed, and the rest is drawn plainly.
+    // Payloads and marks are left out.
+    pub fn to_dot_diff(&self, older: &Dag) -> String {
+        const ADDED: &str = " [color=green, fontcolor=green]";
+        const REMOVED: &str = " [color=red, fontcolor=red, style=dashed]";
+        let (current, older) = (self.to_stored_graph(), older.to_stored_graph());
+        let keys = |stored: &StoredGraph| -> BTreeSet<String> { stored.nodes.iter().map(|(key, _)| key.clone()).collect() };
+        let edges = |stored: &StoredGraph| -> BTreeSet<(String, String)> {
+            stored.edges.iter().map(|(from, to, _)| (from.clone(), to.clone())).collect()
+        };
+        let (now, before) = (keys(&current), keys(&older));
+        let mut dot = String::from("digraph {\n");
+        for key in now.union(&before) {
+            let style = match (now.contains(key), before.contains(key)) {
+                (true, false) => ADDED,
+                (false, true) => REMOVED,
+                _ => "",
+            };
+            dot.push_str(&format!("    {}{};\n", dot_quote(key), style));
+        }
+        let (now, before) = (edges(&current), edges(&older));
+        for (from, to) in now.union(&before) {
+            let edge = (from.clone(), to.clone());
+            let style = match (now.contains(&edge), before.contains(&edge)) {
+                (true, false) => ADDED,
+                (false, true) => REMOVED,
+                _ => "",
+            };
+            dot.push_str(&format!("    {} -> {}{};\n", dot_quote(from), dot_quote(to), style));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // For dense graphs: a node with more than `max_edges` outgoing edges
+    // gets one bold edge to a summary of its dependents instead, and likewise
+    // for incoming edges that are still drawn. Payloads and marks are left
+    // out.
+    pub fn to_dot_bundled(&self, max_edges: usize) -> String {
+        let stored = self.to_stored_graph();
+        let mut outgoing: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+        for (from, to, _) in stored.edges.iter() {
+            outgoing.entry(from).or_default().push(to);
+        }
+        let mut drawn: Vec<(&String, &String)> = vec![];
+        let mut bundled_out: Vec<(&String, usize)> = vec![];
+        for (from, targets) in outgoing {
+            if targets.len() > max_edges {
+                bundled_out.push((from, targets.len()));
+            } else {
+                drawn.extend(targets.into_iter().map(|to| (from, to)));
+            }
+        }
+        let mut incoming: BTreeMap<&String, usize> = BTreeMap::new();
+        for (_, to) in drawn.iter() {
+            *incoming.entry(to).or_default() += 1;
+        }
+        let bundled_in: BTreeMap<&String, usize> = incoming.into_iter().filter(|(_, count)| *count > max_edges).collect();
+
+        let mut dot = String::from("digraph {\n");
+        for (key, _) in stored.nodes.iter() {
+            dot.push_str(&format!("    {};\n", dot_quote(key)));
+        }
+        for (from, count) in bundled_out {
+            let summary = dot_quote(&format!("{} (fan-out)", from));
+            dot.push_str(&format!("    {} [shape=box, style=dashed, label=\"{} dependents\"];\n", summary, count));
+            dot.push_str(&format!("    {} -> {} [style=bold, label=\"{} edges\"];\n", dot_quote(from), summary, count));
+        }
+        for (to, count) in bundled_in.iter() {
+            let summary = dot_quote(&format!("{} (fan-in)", to));
+            dot.push_str(&format!("    {} [shape=box, style=dashed, label=\"{} dependencies\"];\n", summary, count));
+            dot.push_str(&format!("    {} -> {} [style=bold, label=\"{} edges\"];\n", summary, dot_quote(to), count));
+        }
+        for (from, to) in drawn.into_iter().filter(|(_, to)| !bundled_in.contains_key(to)) {
+            dot.push_str(&format!("    {} -> {};\n", dot_quote(from), dot_quote(to)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Nodes must be declared before an edge can name them, in any order.
+    pub fn from_dot(text: &str) -> Result<Dag, DagError> {
+        parse_dot(text).map_err(|err| err.during("from_dot"))
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let stored = self.to_stored_graph();
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"payload\" for=\"node\" attr.name=\"payload\" attr.type=\"string\"/>\n",
+            "  <key id=\"invalidated\" for=\"node\" attr.name=\"invalidated\" attr.type=\"boolean\"/>\n",
+            "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n",
+            "  <key id=\"soft\" for=\"edge\" attr.name=\"soft\" attr.type=\"boolean\"/>\n",
+            "  <graph edgedefault=\"directed\">\n",
+        ));
+        for record in records(&stored) {
+            xml.push_str(&format!("    <node id=\"{}\"><data key=\"payload\">{}</data>",
+                xml_escape(&record.key), xml_escape(&record.payload)));
+            if record.invalidated {
+                xml.push_str("<data key=\"invalidated\">true</data>");
+            }
+            xml.push_str("</node>\n");
+        }
+        for link in links(&stored) {
+            xml.push_str(&format!("    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data>",
+                xml_escape(&link.from), xml_escape(&link.to), link.weight));
+            if link.soft {
+                xml.push_str("<data key=\"soft\">true</data>");
+            }
+            xml.push_str("</edge>\n");
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    // Data keys are matched by their `attr.name`, so files rewritten by tools
+    // that renumber them still import.
+    pub fn from_graphml(text: &str) -> Result<Dag, DagError> {
+        parse_graphml(text).map_err(|err| err.during("from_graphml"))
+    }
+
+    // Little-endian, with length-prefixed strings, behind a magic number and
+    // a format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let stored = self.to_stored_graph();
+        let mut buffer = MAGIC.to_vec();
+        buffer.push(VERSION);
+        buffer.extend(self.schema_version().to_le_bytes());
+        let nodes = records(&stored);
+        buffer.extend((nodes.len() as u32).to_le_bytes());
+        for record in nodes {
+            push_bytes(&mut buffer, &record.key);
+            push_bytes(&mut buffer, &record.payload);
+            buffer.push(record.invalidated as u8);
+        }
+        buffer.extend((stored.edges.len() as u32).to_le_bytes());
+        for link in links(&stored) {
+            push_bytes(&mut buffer, &link.from);
+            push_bytes(&mut buffer, &link.to);
+            buffer.extend(link.weight.to_le_bytes());
+            buffer.push(link.soft as u8);
+        }
+        buffer
+    }
+
+    // Only the shape of the graph, for payloads that live elsewhere. Nothing
+    // is resolved while writing it.
+    pub fn to_skeleton(&self) -> Vec<u8> {
+        let mut buffer = SKELETON_MAGIC.to_vec();
+        let nodes = self.nodes.borrow();
+        buffer.extend((self.index.len() as u32).to_le_bytes());
+        for key in self.index.iter() {
+            push_bytes(&mut buffer, key);
+        }
+        let mut edges = vec![];
+        for key in self.index.iter() {
+            let node = borrow::or_panic(borrow::read(&nodes[key], key, "to_skeleton"));
+            edges.extend(node.edges.iter()
+                .filter(|edge| self.live_target(edge).is_some())
+                .map(|edge| (key, edge.to_key.clone(), edge.weight, edge.soft)));
+        }
+        buffer.extend((edges.len() as u32).to_le_bytes());
+        for (from, to, weight, soft) in edges {
+            push_bytes(&mut buffer, from);
+            push_bytes(&mut buffer, &to);
+            buffer.extend(weight.to_le_bytes());
+            buffer.push(soft as u8);
+        }
+        buffer
+    }
+
+    // Payloads come from `resolver` as nodes are first used; see
+    // `set_payload_resolver`.
+    pub fn from_skeleton<T, F>(bytes: &[u8], resolver: F) -> Result<Dag, DagError>
+        where T: Debug + 'static, F: Fn(&str) -> Option<T> + 'static {
+        let stored = parse_skeleton(bytes).map_err(|err| err.during("from_skeleton"))?;
+        let mut dag = Dag::from_stored_graph(stored, stored_payload);
+        dag.set_payload_resolver(resolver);
+        Ok(dag)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dag, DagError> {
+        parse_bytes(bytes).and_then(|snapshot| snapshot.load(None)).map_err(|err| err.during("from_bytes"))
+    }
+
+    // Brings payloads written under an older schema version up to the
+    // current one on the way in.
+    pub fn from_bytes_migrating(bytes: &[u8], migrations: &Migrations) -> Result<Dag, DagError> {
+        parse_bytes(bytes).and_then(|snapshot| snapshot.load(Some(migrations)))
+            .map_err(|err| err.during("from_bytes_migrating"))
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        let stored = self.to_stored_graph();
+        let nodes: Vec<serde_json::Value> = records(&stored).into_iter()
+            .map(|record| serde_json::json!({ "key": record.key, "payload": record.payload, "invalidated": record.invalidated }))
+            .collect();
+        let edges: Vec<serde_json::Value> = links(&stored).into_iter()
+            .map(|link| serde_json::json!({ "from": link.from, "to": link.to, "weight": link.weight, "soft": link.soft }))
+            .collect();
+        serde_json::json!({ "schema_version": self.schema_version(), "nodes": nodes, "edges": edges }).to_string()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<Dag, DagError> {
+        parse_json(text).and_then(|snapshot| snapshot.load(None)).map_err(|err| err.during("from_json"))
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json_migrating(text: &str, migrations: &Migrations) -> Result<Dag, DagError> {
+        parse_json(text).and_then(|snapshot| snapshot.load(Some(migrations)))
+            .map_err(|err| err.during("from_json_migrating"))
+    }
+}
+
+#[cfg(feature = "json")]
+fn parse_json(text: &str) -> Result<Snapshot, DagError> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|err| import_error(err.to_string()))?;
+    let schema_version = match &value["schema_version"] {
+        serde_json::Value::Null => 0,
+        version => version.as_u64().and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| import_error(format!("invalid schema version {}", version)))?,
+    };
+    let entries = |field: &str| value[field].as_array().cloned()
+        .ok_or_else(|| import_error(format!("missing {} array", field)));
+    let text = |entry: &serde_json::Value, field: &str| entry[field].as_str().map(|text| text.to_string())
+        .ok_or_else(|| import_error(format!("entry without a {}", field)));
+    let mut nodes = vec![];
+    for entry in entries("nodes")? {
+        nodes.push(Record {
+            key: text(&entry, "key")?,
+            payload: text(&entry, "payload").unwrap_or_default(),
+            invalidated: entry["invalidated"].as_bool().unwrap_or(false),
+        });
+    }
+    let mut edges = vec![];
+    for entry in entries("edges")? {
+        let weight = match &entry["weight"] {
+            serde_json::Value::Null => 1,
+            weight => weight.as_i64().and_then(|weight| i32::try_from(weight).ok())
+                .ok_or_else(|| import_error(format!("invalid weight {}", weight)))?,
+        };
+        let soft = entry["soft"].as_bool().unwrap_or(false);
+        edges.push(Link { soft, ..Link::new(text(&entry, "from")?, text(&entry, "to")?, weight) });
+    }
+    Ok(Snapshot { schema_version, nodes, edges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keys and payloads every format has to escape, plus a weighted fan-in
+    // whose edge order matters.
+    fn awkward() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("say \"hi\"", "line\nbreak");
+        dag.add("a<b>&c", 'é');
+        dag.add("back\\slash", vec![1, 2]);
+        dag.link("say \"hi\"", "back\\slash", -7);
+        dag.link("a<b>&c", "back\\slash", 3);
+        dag.link("say \"hi\"", "a<b>&c", 1);
+        dag.set_edge_soft("a<b>&c", "back\\slash", true);
+        dag.update("a<b>&c", 'ü');
+        dag
+    }
+
+    #[test]
+    fn every_format_round_trips_edges_and_marks() {
+        let dag = awkward();
+        let expected = dag.to_stored_graph();
+        assert_eq!(expected.edges.iter().filter(|(_, to, _)| to == "back\\slash").count(), 2);
+        assert_eq!(expected.soft_edges, vec![("a<b>&c".to_string(), "back\\slash".to_string())]);
+        assert_eq!(Dag::from_dot(&dag.to_dot()).unwrap().to_stored_graph(), expected);
+        assert_eq!(Dag::from_graphml(&dag.to_graphml()).unwrap().to_stored_graph(), expected);
+        assert_eq!(Dag::from_bytes(&dag.to_bytes()).unwrap().to_stored_graph(), expected);
+        #[cfg(feature = "json")]
+        assert_eq!(Dag::from_json(&dag.to_json()).unwrap().to_stored_graph(), expected);
+    }
+
+    #[test]
+    fn externally_edited_files_import() {
+        let dot = "digraph pipeline {\n  node [shape=box];\n  // added by hand\n  a [payload=\"1\"]; b; c\n  a -> b -> c [weight=4, color=red]\n}";
+        let dag = Dag::from_dot(dot).unwrap();
+        assert_eq!(dag.edge_weight("b", "c"), Some(4));
+        assert_eq!(dag.successors("a"), vec!["b".to_string()]);
+
+        let graphml = "<graphml><key id=\"d0\" for=\"edge\" attr.name=\"weight\"/><graph>\
+            <node id=\"x\"/><node id=\"y\"/><edge source=\"x\" target=\"y\"><data key=\"d0\">9</data></edge>\
+            </graph></graphml>";
+        assert_eq!(Dag::from_graphml(graphml).unwrap().edge_weight("x", "y"), Some(9));
+
+        let err = Dag::from_dot("digraph { a; a -> b }").err().unwrap();
+        assert_eq!(err.operation(), Some("from_dot"));
+        assert_eq!(err.root(), &DagError::NodeNotFound("b".to_string()));
+        assert!(matches!(Dag::from_bytes(&dag.to_bytes()[..9]).err().unwrap().root(), DagError::Import(_)));
+    }
+
+    #[test]
+    fn dot_diffs_mark_both_sides() {
+        let mut older = Dag::new();
+        for key in ["extract", "clean", "load"] {
+            older.add(key, ());
+        }
+        older.add_edge_directed("extract", "clean");
+        older.add_edge_directed("clean", "load");
+        let mut newer = Dag::new();
+        for key in ["extract", "load", "audit"] {
+            newer.add(key, ());
+        }
+        newer.add_edge_directed("extract", "load");
+        newer.add_edge_directed("load", "audit");
+
+        assert_eq!(newer.to_dot_diff(&older), concat!(
+            "digraph {\n",
+            "    \"audit\" [color=green, fontcolor=green];\n",
+            "    \"clean\" [color=red, fontcolor=red, style=dashed];\n",
+            "    \"extract\";\n",
+            "    \"load\";\n",
+            "    \"clean\" -> \"load\" [color=red, fontcolor=red, style=dashed];\n",
+            "    \"extract\" -> \"clean\" [color=red, fontcolor=red, style=dashed];\n",
+            "    \"extract\" -> \"load\" [color=green, fontcolor=green];\n",
+            "    \"load\" -> \"audit\" [color=green, fontcolor=green];\n",
+            "}\n",
+        ));
+        assert!(Dag::from_dot(&newer.to_dot_diff(&older)).is_ok());
+    }
+
+    #[test]
+    fn dense_fans_collapse_into_summary_edges() {
+        let mut dag = Dag::new();
+        for key in ["config", "a", "b", "c", "report"] {
+            dag.add(key, ());
+        }
+        for key in ["a", "b", "c", "report"] {
+            dag.add_edge_directed("config", key);
+        }
+        for key in ["a", "b", "c"] {
+            dag.add_edge_directed(key, "report");
+        }
+        let bundled = dag.to_dot_bundled(2);
+        assert!(bundled.contains("    \"config\" -> \"config (fan-out)\" [style=bold, label=\"4 edges\"];\n"));
+        assert!(bundled.contains("    \"report (fan-in)\" -> \"report\" [style=bold, label=\"3 edges\"];\n"));
+        assert_eq!(bundled.matches(" -> ").count(), 2);
+        assert_eq!(dag.to_dot_bundled(4).matches(" -> ").count(), 7);
+    }
+
+    #[test]
+    fn skeletons_resolve_payloads_on_first_use() {
+        let dag = awkward();
+        let resolved = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = resolved.clone();
+        let skeleton = Dag::from_skeleton(&dag.to_skeleton(), move |key| {
+            counter.set(counter.get() + 1);
+            Some(format!("blob:{}", key))
+        }).unwrap();
+        assert_eq!(resolved.get(), 0);
+        assert!(skeleton.has_unresolved_payload("a<b>&c"));
+        assert_eq!(format!("{:?}", skeleton.get("a<b>&c").unwrap().borrow().data), "\"blob:a<b>&c\"");
+        assert!(skeleton.is_soft_edge("a<b>&c", "back\\slash"));
+        assert_eq!(resolved.get(), 1);
+        assert!(!skeleton.has_unresolved_payload("a<b>&c"));
+        assert!(skeleton.has_unresolved_payload("back\\slash"));
+        assert_eq!(skeleton.edge_weight("say \"hi\"", "back\\slash"), Some(-7));
+        assert_eq!(Dag::from_skeleton(&skeleton.to_skeleton(), |_| None::<()>).unwrap().to_skeleton(), dag.to_skeleton());
+    }
+}
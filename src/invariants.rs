@@ -0,0 +1,110 @@
+use crate::{Dag, Edge};
+
+// Edges left pointing at a removed node are dead rather than dangling, even
+// once a new node takes the key: nothing follows them and `compact`
+// reclaims them. An edge dangles when the node it holds goes by another key
+// than the one it names, which no graph operation produces.
+impl Dag {
+    // Every broken invariant, one line each: cycles, dangling edges and
+    // invalidation marks on keys the graph doesn't hold.
+    pub fn invariant_violations(&self) -> Vec<String> {
+        // Reads the node map directly: `get` would hydrate and page in.
+        let mut violations = vec![];
+        if let Some(cycle) = self.topology().find_cycle() {
+            let mut path = cycle.clone();
+            path.push(cycle[0].clone());
+            violations.push(format!("cycle {}", path.join(" -> ")));
+        }
+        let nodes = self.nodes.borrow();
+        let mut dangling = vec![];
+        for (key, node) in nodes.iter() {
+            for edge in node.borrow().edges.iter() {
+                if self.is_dangling(edge) {
+                    dangling.push(format!("dangling edge {} -> {}", key, edge.to_key));
+                }
+            }
+        }
+        dangling.sort();
+        violations.extend(dangling);
+        let mut unknown: Vec<&String> = self.invalidated.iter().filter(|key| !nodes.contains_key(*key)).collect();
+        unknown.sort();
+        violations.extend(unknown.into_iter().map(|key| format!("invalidated key {} has no node", key)));
+        violations
+    }
+
+    // Panics listing every violation, in debug builds only. With
+    // `set_invariant_checks(true)` this also runs after every mutation.
+    pub fn debug_assert_invariants(&self) {
+        if cfg!(debug_assertions) {
+            let violations = self.invariant_violations();
+            assert!(violations.is_empty(), "Graph invariants violated: {}", violations.join("; "));
+        }
+    }
+
+    // Off by default: each check walks the whole graph. Graphs may
+    // legitimately pass through states these checks reject, e.g. a cycle
+    // that `find_cycle` is about to report; turn them off around such edits.
+    pub fn set_invariant_checks(&mut self, enabled: bool) {
+        self.invariant_checks = enabled;
+    }
+
+    pub(crate) fn is_dangling(&self, edge: &Edge) -> bool {
+        edge.to_node.upgrade().is_some_and(|target| target.try_borrow().is_ok_and(|target| target.key != edge.to_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corruption_is_reported_where_it_happens() {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.remove("B");
+        dag.add("B", ());
+        assert!(dag.invariant_violations().is_empty());
+        dag.add_edge_directed("C", "B");
+        dag.add_edge_directed("B", "C");
+        dag.get("C").unwrap().borrow_mut().key = "renamed".to_string();
+        assert_eq!(dag.invariant_violations(), vec![
+            "cycle B -> C -> B".to_string(),
+            "dangling edge B -> C".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn checked_graphs_assert_after_each_mutation() {
+        let mut dag = Dag::new();
+        dag.set_invariant_checks(true);
+        dag.add("A", ());
+        dag.add("B", ());
+        dag.add_edge_directed("A", "B");
+        dag.update("B", ());
+        dag.remove("B");
+        dag.add("B", ());
+        dag.update("A", ());
+        dag.dispatch(|_| ());
+        let cyclic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            dag.add("C", ());
+            dag.add_edge_directed("C", "A");
+            dag.add_edge_directed("A", "C");
+        }));
+        assert_eq!(cyclic.is_err(), cfg!(debug_assertions));
+    }
+
+    #[test]
+    fn checks_are_opt_in_and_load_nothing() {
+        let mut dag = Dag::new();
+        assert!(!dag.invariant_checks);
+        dag.set_node_loader(|key| Some(key.to_string()));
+        dag.add("A", ());
+        dag.invalidated.insert("lazy".to_string());
+        assert_eq!(dag.invariant_violations(), vec!["invalidated key lazy has no node".to_string()]);
+        assert_eq!(dag.node_count(), 1);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use crate::{Dag, DagError, DispatchId, NodeStrongRef};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchPlan {
+    pub id: DispatchId,
+    pub order: Vec<String>,
+    pub completed: HashSet<String>,
+}
+
+// Durable record of a dispatch in progress. Implementations persist the plan
+// before any callback runs and every acknowledgement as it happens, so that
+// `pending` can hand back an unfinished plan after a crash.
+pub trait DispatchJournal {
+    fn pending(&mut self) -> Result<Option<DispatchPlan>, DagError>;
+    fn record_plan(&mut self, id: DispatchId, order: &[String]) -> Result<(), DagError>;
+    fn acknowledge(&mut self, id: DispatchId, key: &str) -> Result<(), DagError>;
+    fn complete(&mut self, id: DispatchId) -> Result<(), DagError>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryJournal {
+    plan: Option<DispatchPlan>,
+}
+
+impl DispatchJournal for MemoryJournal {
+    fn pending(&mut self) -> Result<Option<DispatchPlan>, DagError> {
+        Ok(self.plan.clone())
+    }
+
+    fn record_plan(&mut self, id: DispatchId, order: &[String]) -> Result<(), DagError> {
+        self.plan = Some(DispatchPlan { id, order: order.to_vec(), completed: HashSet::new() });
+        Ok(())
+    }
+
+    fn acknowledge(&mut self, id: DispatchId, key: &str) -> Result<(), DagError> {
+        match self.plan.as_mut() {
+            Some(plan) if plan.id == id => {
+                plan.completed.insert(key.to_string());
+                Ok(())
+            },
+            _ => Err(DagError::Journal(format!("no plan recorded for dispatch {}", id.0))),
+        }
+    }
+
+    fn complete(&mut self, id: DispatchId) -> Result<(), DagError> {
+        if self.plan.as_ref().is_some_and(|plan| plan.id == id) {
+            self.plan = None;
+        }
+        Ok(())
+    }
+}
+
+impl Dag {
+    // Runs dirty nodes once each in dependency order. An unfinished plan in
+    // the journal is resumed instead of planning a new run, skipping every
+    // node it already acknowledged.
+    pub fn dispatch_journaled<J, F>(&mut self, journal: &mut J, mut callback: F) -> Result<DispatchId, DagError>
+        where J: DispatchJournal, F: FnMut(NodeStrongRef, DispatchId) {
+        let plan = match journal.pending()? {
+            Some(plan) => {
+                self.resume_dispatch_ids(plan.id);
+                plan
+            },
+            None => {
+                let order = self.dirty_order()?;
+                let id = self.next_dispatch_id();
+                journal.record_plan(id, &order)?;
+                DispatchPlan { id, order, completed: HashSet::new() }
+            },
+        };
+
+        for key in plan.order.iter().filter(|key| !plan.completed.contains(*key)) {
+            if let Some(node) = self.get(key) {
+                callback(node, plan.id);
+            }
+            journal.acknowledge(plan.id, key)?;
+        }
+        journal.complete(plan.id)?;
+
+        self.mark_dispatched(plan.order.iter().cloned().collect(), plan.id);
+        for key in plan.order.iter() {
+            self.invalidated.remove(key);
+        }
+        Ok(plan.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C"] {
+            dag.add(key, key);
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.update("A", "a");
+        dag
+    }
+
+    #[test]
+    fn crashed_dispatch_resumes_without_redoing_nodes() {
+        let mut journal = MemoryJournal::default();
+        let mut dag = chain();
+        let order = dag.dirty_order().unwrap();
+        journal.record_plan(DispatchId(7), &order).unwrap();
+        journal.acknowledge(DispatchId(7), "A").unwrap();
+
+        // A fresh process rebuilds the graph and resumes from the journal.
+        let mut ran = vec![];
+        let id = dag.dispatch_journaled(&mut journal, |node, _| ran.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(id, DispatchId(7));
+        assert_eq!(ran, vec!["B", "C"]);
+        assert!(journal.pending().unwrap().is_none());
+        assert_eq!(dag.last_dispatched("C"), Some(DispatchId(7)));
+        assert!(dag.next_dispatch_id() > DispatchId(7));
+    }
+
+    #[test]
+    fn fresh_dispatch_is_planned_and_acknowledged() {
+        let mut journal = MemoryJournal::default();
+        let mut dag = chain();
+        let mut ran = vec![];
+        dag.dispatch_journaled(&mut journal, |node, _| ran.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(ran, vec!["A", "B", "C"]);
+        assert!(dag.invalidated.is_empty());
+    }
+}
@@ -1,7 +1,166 @@
-use std::collections::{HashMap, HashSet};
-use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::cell::{Cell, RefCell};
 use std::rc::{Rc, Weak};
 use std::fmt::Debug;
+use std::time::SystemTime;
+
+use smallvec::SmallVec;
+
+mod accumulate;
+mod alias;
+mod analysis;
+mod annotate;
+mod anonymize;
+mod approx;
+mod assertions;
+mod barrier;
+mod batch;
+mod borrow;
+mod bubble;
+mod bulk;
+mod cache;
+mod centrality;
+mod chaos;
+#[cfg(feature = "arrow")]
+mod columnar;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compression;
+mod context;
+mod cost;
+mod coverage;
+mod dataflow;
+#[cfg(feature = "debug-server")]
+mod debug_server;
+mod degree;
+mod direction;
+mod dispatch;
+mod distribution;
+mod error;
+mod expiry;
+mod explain;
+mod extensions;
+mod fingerprint;
+mod health;
+mod foreign;
+mod frontier;
+mod handle;
+mod hydration;
+mod ids;
+mod integrity;
+mod interchange;
+mod invariants;
+mod journal;
+mod lint;
+mod maintenance;
+#[cfg(feature = "ndarray")]
+mod matrix;
+mod merkle;
+mod migration;
+mod namespace;
+mod neighborhood;
+mod normalize;
+mod overlay;
+mod parallel;
+mod partition;
+mod paths;
+#[cfg(feature = "toml")]
+mod pipeline;
+mod placeholder;
+mod preference;
+mod priority;
+mod probability;
+mod projection;
+mod provenance;
+mod query_cache;
+mod quarantine;
+mod query;
+mod quota;
+mod replay;
+#[cfg(feature = "rpc")]
+mod rpc;
+mod schedule;
+mod search;
+mod selection;
+mod sequence;
+mod sizing;
+mod soft;
+#[cfg(feature = "csv")]
+mod tabular;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+mod sync;
+mod template;
+mod topology;
+mod transform;
+mod traverser;
+mod upstream;
+mod view;
+mod window;
+mod workspace;
+
+pub use approx::ReachabilityFilter;
+pub use batch::{EdgeChunks, EdgeRecord};
+pub use cache::{CachePolicy, Eviction};
+pub use centrality::Centrality;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compression::{Compression, SnapshotFormat};
+pub use context::Ctx;
+pub use cost::{CostEstimate, Operation};
+pub use coverage::Coverage;
+pub use dataflow::DataflowRun;
+#[cfg(feature = "debug-server")]
+pub use debug_server::DebugServer;
+pub use degree::Order;
+pub use dispatch::DispatchId;
+pub use distribution::WeightDistribution;
+pub use error::DagError;
+pub use expiry::WeakNode;
+pub use explain::Explanation;
+pub use extensions::Extensions;
+pub use fingerprint::{DataFingerprint, Fingerprint};
+pub use foreign::{ForeignKey, Resolver};
+pub use frontier::{DispatchStream, FrontierState};
+pub use handle::NodeHandle;
+pub use health::Health;
+pub use ids::NodeId;
+pub use integrity::Manifest;
+pub use journal::{DispatchJournal, DispatchPlan, MemoryJournal};
+pub use lint::{Diagnostic, FanOut, LintContext, LintRule, LongChains, MissingTags, Orphans, Severity, Tags};
+pub use maintenance::{CompactionReport, MaintenanceOptions, MaintenanceReport};
+pub use merkle::ContentHash;
+pub use migration::Migrations;
+pub use neighborhood::Direction;
+pub use normalize::KeyNormalizer;
+pub use overlay::{PatchedDag, PatchedNode, WhatIf};
+pub use partition::{CutEdge, PartitionStrategy, Partitioning, RemoteTracker, Stitch};
+pub use placeholder::Placeholder;
+pub use preference::PreferredOrder;
+pub use projection::Layer;
+pub use provenance::{Mutation, Provenance, ProvenanceRecord};
+pub use query_cache::QueryCacheStats;
+pub use quarantine::DispatchOutcome;
+pub use query::{Bounded, Budget, Layout};
+pub use quota::{Limit, Limits};
+pub use replay::{DispatchTrace, TraceStep};
+#[cfg(feature = "rpc")]
+pub use rpc::{GraphServer, GraphService, JsonPayload, RemoteDag};
+pub use schedule::{ScheduledNode, SimulatedSchedule};
+pub use search::Glob;
+pub use selection::{Algorithm, GraphStats, Query};
+pub use sequence::TieBreak;
+#[cfg(feature = "csv")]
+pub use tabular::{CsvMapping, CsvRow};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use store::{GraphStore, MemoryStore, StoreOp, StoredGraph, StoredPayload};
+pub use sync::{ImportPolicy, ImportReport};
+pub use template::{DagTemplate, TemplateParams};
+pub use transform::CollapsedChain;
+pub use traverser::Traverser;
+pub use upstream::Ancestors;
+pub use window::TimeWindow;
+pub use workspace::{BrokenLink, NodeRef, Workspace};
 
 type NodeData = dyn Debug + 'static;
 
@@ -9,65 +168,195 @@ type NodeWeakRef = Weak<RefCell<Node>>;
 type NodeStrongRef = Rc<RefCell<Node>>;
 
 pub struct Dag {
-    nodes: HashMap<String, NodeStrongRef>,
+    nodes: RefCell<HashMap<String, NodeStrongRef>>,
+    index: BTreeSet<String>,
     invalidated: HashSet<String>,
+    provenance_context: Option<Provenance>,
+    provenance: HashMap<String, Vec<ProvenanceRecord>>,
+    placeholders: HashSet<String>,
+    placeholder_factory: fn() -> Box<NodeData>,
+    limits: Limits,
+    ids: ids::IdMap,
+    fingerprints: HashMap<String, Fingerprint>,
+    last_dispatch_id: u64,
+    last_dispatched: HashMap<String, DispatchId>,
+    store: Option<store::Attached>,
+    loaders: hydration::Loaders,
+    frontier: Option<frontier::Frontier>,
+    normalizer: KeyNormalizer,
+    aliases: HashMap<String, String>,
+    windows: HashMap<String, TimeWindow>,
+    priorities: HashMap<String, i64>,
+    quarantine: BTreeSet<String>,
+    concurrency_groups: HashMap<String, String>,
+    group_limits: HashMap<String, usize>,
+    invariant_checks: bool,
+    annotations: HashMap<String, Box<dyn std::any::Any>>,
+    barriers: HashSet<String>,
+    held_barriers: BTreeSet<String>,
+    schema_version: u32,
+    references: HashMap<String, Box<dyn std::any::Any>>,
+    resolver: Option<Box<dyn foreign::AnyResolver>>,
+    coverage: RefCell<Option<coverage::CoverageLog>>,
+    reach_index: Option<selection::ReachIndex>,
+    shape: Cell<Option<(usize, usize)>>,
+    query_cache: Option<RefCell<query_cache::QueryCache>>,
+    watchers: expiry::Watchers,
+    views: view::Views,
+    explanations: Option<HashMap<String, Explanation>>,
+    // Cells, since hydration hands out sequences behind `&self`.
+    sequences: RefCell<HashMap<String, u64>>,
+    last_sequence: Cell<u64>,
+    tie_break: TieBreak,
+    payload_sizer: Option<sizing::PayloadSizer>,
+    invalidated_at: HashMap<String, SystemTime>,
+    algorithm_overrides: HashMap<Query, Algorithm>,
 }
 
 #[derive(Debug)]
 pub struct Node {
     pub key: String,
     pub data: Box<NodeData>,
-    pub edges: Vec<Edge>,
+    pub edges: EdgeList,
+    extensions: Extensions,
 }
 
 #[derive(Debug)]
 pub struct Edge {
     weight: i32,
+    to_key: String,
     to_node: NodeWeakRef,
+    soft: bool,
+    probability: f64,
+    sequence: u64,
 }
 
+// Most nodes have no more than a few edges; those live inline in the node
+// instead of in an allocation of their own.
+pub type EdgeList = SmallVec<[Edge; 3]>;
+
 impl Dag {
     pub fn new() -> Dag {
-        let nodes = HashMap::new();
+        let nodes = RefCell::new(HashMap::new());
         let invalidated = HashSet::new();
         Dag {
             nodes,
+            index: BTreeSet::new(),
             invalidated,
+            provenance_context: None,
+            provenance: HashMap::new(),
+            placeholders: HashSet::new(),
+            placeholder_factory: placeholder::default_payload::<Placeholder>,
+            limits: Limits::default(),
+            ids: ids::IdMap::default(),
+            fingerprints: HashMap::new(),
+            last_dispatch_id: 0,
+            last_dispatched: HashMap::new(),
+            store: None,
+            loaders: hydration::Loaders::default(),
+            frontier: None,
+            normalizer: KeyNormalizer::default(),
+            aliases: HashMap::new(),
+            windows: HashMap::new(),
+            priorities: HashMap::new(),
+            quarantine: BTreeSet::new(),
+            concurrency_groups: HashMap::new(),
+            group_limits: HashMap::new(),
+            invariant_checks: false,
+            annotations: HashMap::new(),
+            barriers: HashSet::new(),
+            held_barriers: BTreeSet::new(),
+            schema_version: 0,
+            references: HashMap::new(),
+            resolver: None,
+            coverage: RefCell::new(None),
+            reach_index: None,
+            shape: Cell::new(None),
+            query_cache: None,
+            watchers: expiry::Watchers::default(),
+            views: view::Views::default(),
+            explanations: None,
+            sequences: RefCell::new(HashMap::new()),
+            last_sequence: Cell::new(0),
+            tie_break: TieBreak::Key,
+            payload_sizer: None,
+            invalidated_at: HashMap::new(),
+            algorithm_overrides: HashMap::new(),
         }
     }
 
-    pub fn add<T>(&mut self, key: &str, data: T) where T: Debug + 'static {
-        let node = Node::new(String::from(key), Box::new(data));
-        let node_ref = Rc::new(RefCell::new(node));
-        self.nodes.insert(String::from(key), node_ref);
+    pub fn add<T>(&mut self, key: &str, data: T) -> NodeHandle<'_> where T: Debug + 'static {
+        self.add_or_replace(key, data);
+        self.node(key)
     }
 
-    pub fn update<T>(&mut self, key: &str, data: T) where T: Debug + 'static {
+    // An existing node keeps its place and its edges in both directions; only
+    // the payload is swapped, and the node is invalidated as by `update`.
+    pub fn add_or_replace<T>(&mut self, key: &str, data: T) where T: Debug + 'static {
+        let key: &str = &self.resolve_key(key);
+        self.check_node_quota(&[key]).unwrap_or_else(|err| panic!("{}", err));
+        self.insert_boxed(key, Box::new(data));
+    }
+
+    // `data` is dropped if `key` already names a node.
+    pub fn add_or_get<T>(&mut self, key: &str, data: T) -> NodeStrongRef where T: Debug + 'static {
         match self.get(key) {
-            Some(node) => {
-                node.borrow_mut().data = Box::new(data);
-                self.invalidated.insert(key.to_string());
+            Some(node) if !self.is_placeholder(key) => node,
+            _ => {
+                self.add(key, data);
+                self.get(key).expect("Node just added")
             },
-            None => (),
         }
     }
 
+    pub fn update<T>(&mut self, key: &str, data: T) where T: Debug + 'static {
+        self.update_boxed(key, Box::new(data));
+    }
+
     pub fn remove(&mut self, key: &str) -> bool {
-        self.nodes.remove(key).is_some()
+        let key: &str = &self.resolve_key(key);
+        let removed = self.nodes.borrow_mut().remove(key).is_some();
+        if removed {
+            self.index.remove(key);
+            self.ids.release(key);
+            self.fingerprints.remove(key);
+            self.last_dispatched.remove(key);
+            self.placeholders.remove(key);
+            self.loaders.forget(key);
+            self.drop_aliases_to(key);
+            self.windows.remove(key);
+            self.priorities.remove(key);
+            self.quarantine.remove(key);
+            self.concurrency_groups.remove(key);
+            self.invalidated.remove(key);
+            self.invalidated_at.remove(key);
+            self.annotations.remove(key);
+            if let Some(explanations) = self.explanations.as_mut() {
+                explanations.remove(key);
+            }
+            self.barriers.remove(key);
+            self.held_barriers.remove(key);
+            self.references.remove(key);
+            self.views.forget(key);
+            self.sequences.get_mut().remove(key);
+            self.record(&[key], Mutation::RemoveNode);
+            self.watchers.expire(key);
+        }
+        removed
     }
 
+    #[deprecated(note = "use `add_edge_directed(from, to)` or `add_dependency(dependent, dependency)`")]
     pub fn add_edge(&mut self, to_node_key: &str, from_node_key: &str) {
-        let to_node = self.get(to_node_key).expect("Cannot find node to add edge to");
-        let from_node = self.get(from_node_key).expect("Cannot find node to add edge from");
-        to_node.borrow_mut().add_edge(from_node, 1);
+        self.add_edge_directed(to_node_key, from_node_key);
     }
 
+    #[deprecated(note = "use `edge_weight(from, to)`")]
     pub fn get_edge_weight(&self, to_node_key: &str, from_node_key: &str) -> i32 {
-        let from_node = self.get(from_node_key).expect(&format!("Cannot find node ${}", from_node_key));
-        let borrowed_from_node = from_node.borrow();
-        let edge = borrowed_from_node.edges.iter().find(|edge|
-            edge.to_node.upgrade().expect("Failed to find edge reference").borrow().key == to_node_key
-        );
+        let to_node_key: &str = &self.resolve_key(to_node_key);
+        let from_node = self.get(from_node_key).unwrap_or_else(|| panic!("Cannot find node ${}", from_node_key));
+        let borrowed_from_node = borrow::or_panic(borrow::read(&from_node, from_node_key, "get_edge_weight"));
+        let edge = borrowed_from_node.edges.iter()
+            .find(|edge| edge.to_key == to_node_key && self.live_target(edge).is_some());
         match edge {
             Some(found) => found.weight,
             None => -1,
@@ -75,36 +364,206 @@ impl Dag {
     }
 
     pub fn get(&self, key: &str) -> Option<NodeStrongRef> {
-        match self.nodes.get(key) {
-            Some(node) => Some(Rc::clone(node)),
-            None => None
-        }
+        let key = self.resolve_key(key);
+        let found = self.nodes.borrow().get(key.as_ref()).cloned();
+        let node = match found {
+            Some(node) => node,
+            None => self.hydrate_missing(&key)?,
+        };
+        self.page_in(&node);
+        self.resolve_payload(&node);
+        self.discover_edges(&node);
+        Some(node)
     }
 
     pub fn traverse(&self, node: NodeStrongRef, validated: &mut HashSet<String>, callback: fn(NodeStrongRef) -> ()) {
+        self.page_in(&node);
+        self.resolve_payload(&node);
+        self.discover_edges(&node);
         let borrowed_node = node.borrow();
         if !validated.contains(&borrowed_node.key) {
             validated.insert(borrowed_node.key.clone());
+            self.check_walk(&borrowed_node.key, validated.len(), 0).unwrap_or_else(|err| panic!("{}", err));
+            self.cover_node(&borrowed_node.key);
             callback(node.clone());
-            for edge in borrowed_node.edges.iter() {
-                self.traverse(edge.to_node.upgrade().expect("Failed to find edge reference"), validated, callback);
+            if self.barriers.contains(&borrowed_node.key) {
+                return;
+            }
+            for edge in borrowed_node.edges.iter().filter(|edge| !edge.soft) {
+                let Some(target) = self.live_target(edge) else { continue };
+                self.cover_edge(&borrowed_node.key, &edge.to_key);
+                self.traverse(target, validated, callback);
             }
         }
     }
 
     pub fn dispatch(&mut self, callback: fn(NodeStrongRef) -> ()) {
         println!("Dispatching...");
+        let id = self.next_dispatch_id();
+        let mut dispatched: HashSet<String> = HashSet::new();
         for key in self.invalidated.iter() {
-            let node = self.get(&key);
-            match node {
-                Some(found) => {
-                    let mut validated: HashSet<String> = HashSet::new();
-                    self.traverse(found, &mut validated, callback)
-                },
-                None => (),
+            if let Some(found) = self.get(key) {
+                let mut validated: HashSet<String> = HashSet::new();
+                self.traverse(found, &mut validated, callback);
+                dispatched.extend(validated);
+            }
+        }
+        self.mark_dispatched(dispatched, id);
+        self.clear_invalidated();
+    }
+
+    pub(crate) fn insert_boxed(&mut self, key: &str, data: Box<NodeData>) {
+        let key: &str = &self.resolve_key(key);
+        self.fingerprints.remove(key);
+        if self.placeholders.remove(key) {
+            // Placeholders are materialized in place so edges already
+            // pointing at them stay intact.
+            self.get(key).expect("Placeholder missing from graph").borrow_mut().data = data;
+            self.record(&[key], Mutation::AddNode);
+            return;
+        }
+        if self.get(key).is_some() {
+            self.update_boxed(key, data);
+            return;
+        }
+        let node = Node::new(String::from(key), data);
+        let node_ref = Rc::new(RefCell::new(node));
+        self.nodes.borrow_mut().insert(String::from(key), node_ref);
+        self.index.insert(String::from(key));
+        self.ids.assign(key);
+        let sequence = self.next_sequence();
+        self.sequences.get_mut().insert(key.to_string(), sequence);
+        self.record(&[key], Mutation::AddNode);
+    }
+
+    #[allow(clippy::single_match)]
+    pub(crate) fn update_boxed(&mut self, key: &str, data: Box<NodeData>) {
+        let key: &str = &self.resolve_key(key);
+        match self.get(key) {
+            Some(node) => {
+                borrow::or_panic(borrow::write(&node, key, "update")).data = data;
+                self.loaders.mark_resolved(key);
+                self.references.remove(key);
+                self.views.invalidate(key);
+                if self.invalidated.insert(key.to_string()) {
+                    self.priorities.remove(key);
+                    self.invalidated_at.insert(key.to_string(), SystemTime::now());
+                }
+                self.fingerprints.remove(key);
+                self.record(&[key], Mutation::UpdateNode);
+            },
+            None => (),
+        }
+    }
+
+    pub(crate) fn successors(&self, key: &str) -> Vec<String> {
+        let key = self.resolve_key(key);
+        match self.nodes.borrow().get(key.as_ref()) {
+            Some(node) => borrow::or_panic(borrow::read(node, &key, "successors")).edges.iter()
+                .filter(|edge| self.live_target(edge).is_some())
+                .map(|edge| edge.to_key.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // Successors that invalidation and failure propagate to. Nothing gets
+    // past a barrier.
+    pub(crate) fn hard_successors(&self, key: &str) -> Vec<String> {
+        let key = self.resolve_key(key);
+        if self.barriers.contains(key.as_ref()) {
+            return vec![];
+        }
+        match self.nodes.borrow().get(key.as_ref()) {
+            Some(node) => borrow::or_panic(borrow::read(node, &key, "successors")).edges.iter()
+                .filter(|edge| !edge.soft && self.live_target(edge).is_some())
+                .map(|edge| edge.to_key.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn edge_weight(&self, from_key: &str, to_key: &str) -> Option<i32> {
+        let to_key: &str = &self.resolve_key(to_key);
+        let from_key = self.resolve_key(from_key);
+        self.nodes.borrow().get(from_key.as_ref())
+            .and_then(|node| borrow::or_panic(borrow::read(node, &from_key, "edge_weight")).edges.iter()
+                .find(|edge| edge.to_key == to_key && self.live_target(edge).is_some())
+                .map(|edge| edge.weight))
+    }
+
+    pub(crate) fn reaches(&self, from_key: &str, to_key: &str) -> bool {
+        let to_key: &str = &self.resolve_key(to_key);
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut stack = vec![self.resolve_key(from_key).into_owned()];
+        while let Some(key) = stack.pop() {
+            if key == to_key {
+                return true;
             }
+            if visited.insert(key.clone()) {
+                stack.extend(self.successors(&key));
+            }
+        }
+        false
+    }
+
+    pub(crate) fn link(&mut self, from_key: &str, to_key: &str, weight: i32) {
+        borrow::or_panic(self.try_link(from_key, to_key, weight));
+    }
+
+    pub(crate) fn try_link(&mut self, from_key: &str, to_key: &str, weight: i32) -> Result<(), DagError> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let from_node = self.get(from_key).expect("Cannot find node to add edge from");
+        let to_node = self.get(to_key).expect("Cannot find node to add edge to");
+        let sequence = self.next_sequence();
+        let mut borrowed_node = borrow::write(&from_node, from_key, "add_edge")?;
+        borrowed_node.push_edge(to_node, to_key, weight);
+        borrowed_node.edges.last_mut().expect("Edge just pushed").sequence = sequence;
+        drop(borrowed_node);
+        self.record(&[from_key, to_key], Mutation::AddEdge {
+            from: from_key.to_string(),
+            to: to_key.to_string(),
+        });
+        Ok(())
+    }
+
+    pub(crate) fn unlink(&mut self, from_key: &str, to_key: &str) -> bool {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let removed = match self.get(from_key) {
+            Some(node) => {
+                let mut borrowed_node = borrow::or_panic(borrow::write(&node, from_key, "remove_edge"));
+                let before = borrowed_node.edges.len();
+                borrowed_node.edges.retain(|edge| edge.to_key != to_key);
+                borrowed_node.edges.len() != before
+            },
+            None => false,
+        };
+        if removed {
+            self.record(&[from_key, to_key], Mutation::RemoveEdge {
+                from: from_key.to_string(),
+                to: to_key.to_string(),
+            });
         }
-        self.invalidated.clear();
+        removed
+    }
+
+    // Edges only hold weak references, so a target may have been dropped or
+    // replaced by a newer node under the same key.
+    fn live_target(&self, edge: &Edge) -> Option<NodeStrongRef> {
+        let target = edge.to_node.upgrade()?;
+        let is_current = self.nodes.borrow().get(&edge.to_key)
+            .is_some_and(|current| Rc::ptr_eq(current, &target));
+        if is_current {
+            Some(target)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Dag {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -113,20 +572,35 @@ impl Node {
         Node {
             key,
             data,
-            edges: vec![],
+            edges: EdgeList::new(),
+            extensions: Extensions::default(),
         }
     }
 
     pub fn add_edge(&mut self, to_node: NodeStrongRef, weight: i32) {
+        // The only node that can already be borrowed here is this one.
+        let to_key = match to_node.try_borrow() {
+            Ok(target) => target.key.clone(),
+            Err(_) => self.key.clone(),
+        };
+        self.push_edge(to_node, &to_key, weight);
+    }
+
+    pub(crate) fn push_edge(&mut self, to_node: NodeStrongRef, to_key: &str, weight: i32) {
         let edge = Edge {
             weight,
+            to_key: to_key.to_string(),
             to_node: Rc::downgrade(&to_node),
+            soft: false,
+            probability: 1.0,
+            sequence: 0,
         };
         self.edges.push(edge);
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -146,4 +620,22 @@ mod tests {
         dag.remove(key1);
         assert_eq!(dag.get(key1).is_none(), true);
     }
+
+    #[test]
+    fn re_adding_a_node_keeps_its_edges() {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.add("C", 3);
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        assert_eq!(dag.try_add("B", 3), Err(DagError::DuplicateNode("B".to_string())));
+
+        dag.add_or_replace("B", 20);
+        assert_eq!(dag.successors("A"), vec!["B".to_string()]);
+        assert_eq!(dag.successors("B"), vec!["C".to_string()]);
+        assert!(dag.invalidated.contains("B"));
+        let node = dag.add_or_get("B", 30);
+        assert_eq!(format!("{:?}", node.borrow().data), "20");
+    }
 }
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::topology::Topology;
+use crate::{Dag, NodeStrongRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    // Filled in by `Dag::lint` from the rule that raised it.
+    pub rule: String,
+    pub severity: Severity,
+    pub keys: Vec<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, keys: Vec<String>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic { rule: String::new(), severity, keys, message: message.into() }
+    }
+}
+
+// The graph as rules see it: live edges only, soft ones included, with
+// lookups in both directions prepared once for every rule of a run.
+pub struct LintContext<'a> {
+    dag: &'a Dag,
+    topology: Topology,
+    predecessors: BTreeMap<String, Vec<(String, i32)>>,
+}
+
+impl LintContext<'_> {
+    pub fn dag(&self) -> &Dag {
+        self.dag
+    }
+
+    // In key order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.topology.keys().map(|key| key.as_str())
+    }
+
+    pub fn node(&self, key: &str) -> Option<NodeStrongRef> {
+        self.dag.get(key)
+    }
+
+    // Each neighbour with the weight of the edge between them.
+    pub fn successors(&self, key: &str) -> &[(String, i32)] {
+        if self.topology.contains(key) { self.topology.successors(key) } else { &[] }
+    }
+
+    pub fn predecessors(&self, key: &str) -> &[(String, i32)] {
+        self.predecessors.get(key).map(|inputs| inputs.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn is_soft(&self, from_key: &str, to_key: &str) -> bool {
+        self.topology.is_soft(from_key, to_key)
+    }
+}
+
+pub trait LintRule {
+    fn name(&self) -> &str;
+    fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic>;
+}
+
+// Tags a node carries as an extension, for rules like `MissingTags`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags(pub BTreeSet<String>);
+
+impl Tags {
+    pub fn new<'a>(tags: impl IntoIterator<Item = &'a str>) -> Tags {
+        Tags(tags.into_iter().map(|tag| tag.to_string()).collect())
+    }
+}
+
+// Nodes with no edges either way, usually left behind by a refactor.
+pub struct Orphans;
+
+impl LintRule for Orphans {
+    fn name(&self) -> &str {
+        "orphans"
+    }
+
+    fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic> {
+        graph.keys()
+            .filter(|key| graph.successors(key).is_empty() && graph.predecessors(key).is_empty())
+            .map(|key| Diagnostic::new(Severity::Warning, vec![key.to_string()], format!("{} has no edges", key)))
+            .collect()
+    }
+}
+
+pub struct FanOut {
+    pub max_successors: usize,
+}
+
+impl LintRule for FanOut {
+    fn name(&self) -> &str {
+        "fan-out"
+    }
+
+    fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic> {
+        graph.keys()
+            .filter(|key| graph.successors(key).len() > self.max_successors)
+            .map(|key| Diagnostic::new(Severity::Warning, vec![key.to_string()], format!(
+                "{} feeds {} nodes, more than {}", key, graph.successors(key).len(), self.max_successors)))
+            .collect()
+    }
+}
+
+// Reports the longest chain of the graph, counted in nodes, if it is longer
+// than `max_nodes`. Cycles are left to `find_cycle`; a cyclic graph passes.
+pub struct LongChains {
+    pub max_nodes: usize,
+}
+
+impl LintRule for LongChains {
+    fn name(&self) -> &str {
+        "long-chains"
+    }
+
+    fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic> {
+        let Ok(order) = graph.topology.topological_order() else { return vec![] };
+        let mut longest: BTreeMap<&str, (usize, Option<&str>)> = BTreeMap::new();
+        for key in order.iter() {
+            let best = graph.predecessors(key).iter()
+                .map(|(input, _)| (longest[input.as_str()].0, input.as_str()))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(a.1)));
+            longest.insert(key, best.map_or((1, None), |(length, input)| (length + 1, Some(input))));
+        }
+        let Some((mut key, (length, _))) = longest.iter()
+            .max_by(|a, b| a.1.0.cmp(&b.1.0).then(b.0.cmp(a.0)))
+            .map(|(key, entry)| (*key, *entry))
+            .filter(|(_, (length, _))| *length > self.max_nodes) else { return vec![] };
+        let mut chain = vec![key.to_string()];
+        while let Some(input) = longest[key].1 {
+            chain.push(input.to_string());
+            key = input;
+        }
+        chain.reverse();
+        let message = format!("chain of {} nodes from {} to {}, more than {}", length, chain[0], chain[length - 1], self.max_nodes);
+        vec![Diagnostic::new(Severity::Warning, chain, message)]
+    }
+}
+
+// Nodes whose `Tags` extension lacks any of `required`; nodes without one
+// lack them all.
+pub struct MissingTags {
+    pub required: Vec<String>,
+}
+
+impl LintRule for MissingTags {
+    fn name(&self) -> &str {
+        "missing-tags"
+    }
+
+    fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic> {
+        graph.keys()
+            .filter_map(|key| {
+                let node = graph.node(key)?;
+                let borrowed = node.borrow();
+                let tags = borrowed.extensions().get::<Tags>();
+                let missing: Vec<&str> = self.required.iter()
+                    .filter(|tag| !tags.is_some_and(|tags| tags.0.contains(*tag)))
+                    .map(|tag| tag.as_str())
+                    .collect();
+                (!missing.is_empty()).then(|| Diagnostic::new(Severity::Error, vec![key.to_string()],
+                    format!("{} is missing tags {}", key, missing.join(", "))))
+            })
+            .collect()
+    }
+}
+
+impl Dag {
+    // Every diagnostic of every rule, rule by rule in the order given.
+    pub fn lint(&self, rules: &[&dyn LintRule]) -> Vec<Diagnostic> {
+        let topology = self.topology();
+        let predecessors = topology.predecessors();
+        let graph = LintContext { dag: self, topology, predecessors };
+        rules.iter()
+            .flat_map(|rule| rule.check(&graph).into_iter().map(|diagnostic| Diagnostic { rule: rule.name().to_string(), ..diagnostic }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["checkout", "build", "test", "package", "publish", "scratch"] {
+            dag.add(key, ());
+        }
+        for (from, to) in [("checkout", "build"), ("build", "test"), ("test", "package"), ("package", "publish")] {
+            dag.add_edge_directed(from, to);
+        }
+        dag.add_edge_directed("checkout", "test");
+        dag.add_edge_directed("checkout", "package");
+        for key in ["checkout", "build", "test", "package", "publish"] {
+            dag.get(key).unwrap().borrow_mut().extensions_mut().insert(Tags::new(["owner"]));
+        }
+        dag
+    }
+
+    #[test]
+    fn built_in_rules_flag_hygiene_problems() {
+        let dag = pipeline();
+        let rules: [&dyn LintRule; 4] = [
+            &Orphans,
+            &FanOut { max_successors: 2 },
+            &LongChains { max_nodes: 4 },
+            &MissingTags { required: vec!["owner".to_string()] },
+        ];
+        let diagnostics = dag.lint(&rules);
+        let summary: Vec<(&str, Severity, Vec<&str>)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.rule.as_str(), diagnostic.severity, diagnostic.keys.iter().map(|key| key.as_str()).collect()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("orphans", Severity::Warning, vec!["scratch"]),
+            ("fan-out", Severity::Warning, vec!["checkout"]),
+            ("long-chains", Severity::Warning, vec!["checkout", "build", "test", "package", "publish"]),
+            ("missing-tags", Severity::Error, vec!["scratch"]),
+        ]);
+        assert_eq!(diagnostics[2].message, "chain of 5 nodes from checkout to publish, more than 4");
+    }
+
+    #[test]
+    fn custom_rules_see_the_graph() {
+        struct NestedKeys;
+        impl LintRule for NestedKeys {
+            fn name(&self) -> &str {
+                "nested-keys"
+            }
+
+            fn check(&self, graph: &LintContext<'_>) -> Vec<Diagnostic> {
+                graph.keys()
+                    .flat_map(|key| graph.successors(key).iter()
+                        .filter(move |(next, _)| next.starts_with(key))
+                        .map(move |(next, _)| Diagnostic::new(Severity::Info, vec![key.to_string(), next.clone()], "nested")))
+                    .collect()
+            }
+        }
+        let mut dag = pipeline();
+        dag.add("build-docs", ()).depends_on("build");
+        let diagnostics = dag.lint(&[&NestedKeys]);
+        assert_eq!(diagnostics, vec![Diagnostic {
+            rule: "nested-keys".to_string(),
+            severity: Severity::Info,
+            keys: vec!["build".to_string(), "build-docs".to_string()],
+            message: "nested".to_string(),
+        }]);
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+use std::rc::Rc;
+
+use crate::provenance::Mutation;
+use crate::{Dag, Edge, NodeStrongRef};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub dead_edges_dropped: usize,
+    pub reclaimed_bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceOptions {
+    pub repair_edges: bool,
+    pub prune_orphans: bool,
+    pub refresh_index: bool,
+    pub compact: bool,
+}
+
+impl Default for MaintenanceOptions {
+    // Pruning deletes user nodes and repair brings back edges that went
+    // with a removed node, so both are left opt-in.
+    fn default() -> Self {
+        MaintenanceOptions {
+            repair_edges: false,
+            prune_orphans: false,
+            refresh_index: true,
+            compact: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub repaired_edges: usize,
+    pub pruned_orphans: Vec<String>,
+    pub index_fixes: usize,
+    pub compaction: CompactionReport,
+}
+
+impl MaintenanceReport {
+    pub fn is_clean(&self) -> bool {
+        self.repaired_edges == 0
+            && self.pruned_orphans.is_empty()
+            && self.index_fixes == 0
+            && self.compaction.dead_edges_dropped == 0
+    }
+}
+
+fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<K>() + size_of::<V>())
+}
+
+fn set_bytes<K>(set: &HashSet<K>) -> usize {
+    set.capacity() * size_of::<K>()
+}
+
+impl Dag {
+    // Drops edges whose target no longer exists under any key and releases
+    // spare capacity. Reclaimed bytes are estimated from capacities only;
+    // heap data owned by keys and payloads isn't counted.
+    pub fn compact(&mut self) -> CompactionReport {
+        let mut report = CompactionReport::default();
+        let before = self.storage_bytes();
+        for node in self.nodes.borrow().values() {
+            let mut borrowed_node = node.borrow_mut();
+            let edges_before = borrowed_node.edges.len();
+            borrowed_node.edges.retain(|edge| self.live_target(edge).is_some());
+            report.dead_edges_dropped += edges_before - borrowed_node.edges.len();
+            borrowed_node.edges.shrink_to_fit();
+        }
+        self.invalidated.retain(|key| self.nodes.borrow().contains_key(key));
+        self.nodes.borrow_mut().shrink_to_fit();
+        self.invalidated.shrink_to_fit();
+        self.placeholders.shrink_to_fit();
+        self.provenance.shrink_to_fit();
+        report.reclaimed_bytes = before.saturating_sub(self.storage_bytes());
+        report
+    }
+
+    pub fn maintain(&mut self) -> MaintenanceReport {
+        self.maintain_with(MaintenanceOptions::default())
+    }
+
+    pub fn maintain_with(&mut self, options: MaintenanceOptions) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+        if options.repair_edges {
+            report.repaired_edges = self.repair_dangling_edges();
+        }
+        if options.prune_orphans {
+            report.pruned_orphans = self.prune_orphans();
+        }
+        if options.refresh_index {
+            report.index_fixes = self.refresh_index();
+        }
+        if options.compact {
+            report.compaction = self.compact();
+        }
+        report
+    }
+
+    // Re-points edges whose target was replaced by a newer node under the
+    // same key, e.g. after a node was removed and added back, bringing
+    // those edges back to life. Nothing is left to repair once `compact`
+    // has run.
+    pub fn repair_dangling_edges(&mut self) -> usize {
+        let mut repaired = vec![];
+        {
+            let nodes = self.nodes.borrow();
+            for (from_key, node) in nodes.iter() {
+                let mut borrowed_node = node.borrow_mut();
+                for edge in borrowed_node.edges.iter_mut() {
+                    let current = match nodes.get(&edge.to_key) {
+                        Some(current) => current,
+                        None => continue,
+                    };
+                    let is_stale = edge.to_node.upgrade().is_none_or(|target| !Rc::ptr_eq(&target, current));
+                    if is_stale {
+                        edge.to_node = Rc::downgrade(current);
+                        repaired.push((from_key.clone(), edge.to_key.clone()));
+                    }
+                }
+            }
+        }
+        for (from, to) in repaired.iter() {
+            self.record(&[from, to], Mutation::AddEdge { from: from.clone(), to: to.clone() });
+        }
+        repaired.len()
+    }
+
+    // Orphans have no live edges in either direction.
+    pub fn prune_orphans(&mut self) -> Vec<String> {
+        let topology = self.topology();
+        let degrees = topology.in_degrees();
+        let orphans: Vec<String> = topology.keys()
+            .filter(|key| degrees[*key] == 0 && topology.successors(key).is_empty())
+            .cloned()
+            .collect();
+        for key in orphans.iter() {
+            self.invalidated.remove(key);
+            self.remove(key);
+        }
+        orphans
+    }
+
+    fn refresh_index(&mut self) -> usize {
+        let mut fixes = 0;
+        let before = self.index.len();
+        self.index.retain(|key| self.nodes.borrow().contains_key(key));
+        fixes += before - self.index.len();
+        for key in self.nodes.borrow().keys() {
+            if self.index.insert(key.clone()) {
+                fixes += 1;
+            }
+        }
+        for set in [&mut self.invalidated, &mut self.placeholders] {
+            let before = set.len();
+            set.retain(|key| self.nodes.borrow().contains_key(key));
+            fixes += before - set.len();
+        }
+        fixes
+    }
+
+    fn storage_bytes(&self) -> usize {
+        let edges: usize = self.nodes.borrow().values()
+            .map(|node| node.borrow().edges.capacity() * size_of::<Edge>())
+            .sum();
+        edges
+            + map_bytes::<String, NodeStrongRef>(&self.nodes.borrow())
+            + set_bytes(&self.invalidated)
+            + set_bytes(&self.placeholders)
+            + map_bytes(&self.provenance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compaction_drops_dead_edges_and_slack() {
+        let mut dag = Dag::new();
+        dag.add("hub", 0);
+        for i in 0..100 {
+            let key = format!("leaf-{}", i);
+            dag.add(&key, i);
+            dag.add_edge_directed("hub", &key);
+        }
+        for i in 0..100 {
+            dag.update(&format!("leaf-{}", i), -1);
+            dag.remove(&format!("leaf-{}", i));
+        }
+        let report = dag.compact();
+        assert_eq!(report.dead_edges_dropped, 100);
+        assert!(report.reclaimed_bytes > 0);
+        assert!(dag.get("hub").unwrap().borrow().edges.is_empty());
+        assert_eq!(dag.compact(), CompactionReport::default());
+    }
+
+    #[test]
+    fn replaced_targets_are_repaired_only_on_request() {
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.add("lonely", 3);
+        dag.add_edge_directed("A", "B");
+        dag.remove("B");
+        dag.add("B", 20);
+        assert!(dag.successors("A").is_empty());
+
+        assert_eq!(dag.maintain().repaired_edges, 0);
+        assert_eq!(dag.edge_weight("A", "B"), None);
+        dag.add_edge_directed("A", "B");
+        dag.remove("B");
+        dag.add("B", 30);
+        let report = dag.maintain_with(MaintenanceOptions { repair_edges: true, ..MaintenanceOptions::default() });
+        assert_eq!(report.repaired_edges, 1);
+        assert!(report.pruned_orphans.is_empty());
+        assert_eq!(dag.successors("A"), vec!["B".to_string()]);
+
+        let options = MaintenanceOptions { prune_orphans: true, ..MaintenanceOptions::default() };
+        assert_eq!(dag.maintain_with(options).pruned_orphans, vec!["lonely"]);
+        assert!(dag.maintain_with(options).is_clean());
+    }
+
+    #[test]
+    fn repairs_reach_cached_queries() {
+        let mut dag = Dag::new();
+        dag.set_query_cache(true);
+        dag.add("A", 1);
+        dag.add("B", 2);
+        dag.add_edge_directed("A", "B");
+        dag.remove("B");
+        dag.add("B", 20);
+        assert!(dag.descendants("A").is_empty());
+        assert!(!dag.is_reachable("A", "B"));
+
+        assert_eq!(dag.repair_dangling_edges(), 1);
+        assert_eq!(dag.descendants("A"), vec!["B".to_string()]);
+        assert!(dag.is_reachable("A", "B"));
+    }
+}
@@ -0,0 +1,69 @@
+use ndarray::{Array1, Array2};
+
+use crate::Dag;
+
+// Rows and columns are NodeId indices, so use `Dag::key_of` to map back to
+// keys. Slots of removed nodes stay in place as all-zero rows and columns.
+impl Dag {
+    pub fn to_adjacency_matrix(&self) -> Array2<i32> {
+        let size = self.id_bound();
+        let mut matrix = Array2::zeros((size, size));
+        for (from_id, to_id, weight) in self.id_edges() {
+            matrix[[from_id, to_id]] += weight;
+        }
+        matrix
+    }
+
+    // COO layout as used by GNN libraries: row 0 holds edge sources, row 1
+    // edge targets, with the matching weights alongside.
+    pub fn to_edge_index(&self) -> (Array2<i64>, Array1<i32>) {
+        let edges = self.id_edges();
+        let mut index = Array2::zeros((2, edges.len()));
+        let mut weights = Array1::zeros(edges.len());
+        for (column, (from_id, to_id, weight)) in edges.into_iter().enumerate() {
+            index[[0, column]] = from_id as i64;
+            index[[1, column]] = to_id as i64;
+            weights[column] = weight;
+        }
+        (index, weights)
+    }
+
+    fn id_edges(&self) -> Vec<(usize, usize, i32)> {
+        let topology = self.topology();
+        let id = |key: &str| self.id_of(key).expect("Node without an id").index();
+        let mut edges: Vec<(usize, usize, i32)> = topology.keys()
+            .flat_map(|from_key| topology.successors(from_key).iter()
+                .map(move |(to_key, weight)| (id(from_key), id(to_key), *weight)))
+            .collect();
+        edges.sort();
+        edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrays_are_indexed_by_node_id() {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C"] {
+            dag.add(key, key);
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("A", "C");
+        dag.add_edge_directed("B", "C");
+        let (a, b, c) = (dag.id_of("A").unwrap().index(), dag.id_of("B").unwrap().index(), dag.id_of("C").unwrap().index());
+
+        let matrix = dag.to_adjacency_matrix();
+        assert_eq!(matrix.shape(), &[3, 3]);
+        assert_eq!(matrix[[a, b]], 1);
+        assert_eq!(matrix[[b, a]], 0);
+        assert_eq!(matrix.sum(), 3);
+
+        let (index, weights) = dag.to_edge_index();
+        assert_eq!(index.shape(), &[2, 3]);
+        assert_eq!(weights.len(), 3);
+        assert_eq!((index[[0, 2]], index[[1, 2]]), (b as i64, c as i64));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+
+use sha2::{Digest, Sha256};
+
+use crate::topology::Topology;
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub fn from_bytes(bytes: [u8; 32]) -> ContentHash {
+        ContentHash(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+// Payloads are only known to be Debug, so their rendered form is what gets
+// hashed. Inputs are sorted so the hash doesn't depend on edge order.
+fn content_hash(data: &dyn Debug, inputs: &mut [(ContentHash, i32)]) -> ContentHash {
+    inputs.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}", data).as_bytes());
+    for (input, weight) in inputs.iter() {
+        hasher.update(input.0);
+        hasher.update(weight.to_le_bytes());
+    }
+    ContentHash(hasher.finalize().into())
+}
+
+impl Dag {
+    pub fn merkle_root(&self, key: &str) -> Result<ContentHash, DagError> {
+        let key = &self.resolve_key(key);
+        if self.get(key).is_none() {
+            return Err(DagError::NodeNotFound(key.to_string()));
+        }
+        let hashes = self.merkle_hashes()?;
+        hashes.get(key.as_ref()).copied().ok_or_else(|| DagError::NodeNotFound(key.to_string()))
+    }
+
+    pub fn merkle_hashes(&self) -> Result<BTreeMap<String, ContentHash>, DagError> {
+        let topology = self.topology();
+        self.hashes_for(&topology)
+    }
+
+    // Content-addressed insertion: the key is the node's hash, so adding the
+    // same payload over the same inputs twice yields the existing node.
+    pub fn add_content<T>(&mut self, data: T, inputs: &[&str]) -> Result<String, DagError> where T: Debug + 'static {
+        let mut input_hashes = inputs.iter()
+            .map(|input| Ok((self.merkle_root(input)?, 1)))
+            .collect::<Result<Vec<_>, DagError>>()?;
+        let key = content_hash(&data, &mut input_hashes).to_string();
+        if self.get(&key).is_none() {
+            self.check_node_quota(&[&key])?;
+            self.check_edge_quota(inputs)?;
+            self.add(&key, data);
+            for input in inputs {
+                self.link(input, &key, 1);
+            }
+        }
+        Ok(key)
+    }
+
+    // Collapses nodes whose merkle hashes match onto the smallest key among
+    // them, returning each removed key with the key that replaced it.
+    pub fn dedup_subgraphs(&mut self) -> Result<BTreeMap<String, String>, DagError> {
+        let topology = self.topology();
+        let hashes = self.hashes_for(&topology)?;
+        let mut canonical: BTreeMap<ContentHash, String> = BTreeMap::new();
+        let mut replaced = BTreeMap::new();
+        for key in topology.topological_order()? {
+            let keep = canonical.entry(hashes[&key]).or_insert_with(|| key.clone()).clone();
+            if keep != key {
+                replaced.insert(key, keep);
+            }
+        }
+
+        let predecessors = topology.predecessors();
+        for (duplicate, keep) in replaced.iter() {
+            for successor in self.successors(duplicate) {
+                if !self.successors(keep).contains(&successor) {
+                    let weight = self.edge_weight(duplicate, &successor).expect("Successor edge vanished");
+                    self.link(keep, &successor, weight);
+                    if self.is_soft_edge(duplicate, &successor) {
+                        self.set_edge_soft(keep, &successor, true);
+                    }
+                }
+            }
+            for (predecessor, _) in predecessors[duplicate].iter() {
+                self.unlink(predecessor, duplicate);
+            }
+            if self.invalidated.remove(duplicate) {
+                self.invalidated.insert(keep.clone());
+            }
+            self.remove(duplicate);
+        }
+        Ok(replaced)
+    }
+
+    fn hashes_for(&self, topology: &Topology) -> Result<BTreeMap<String, ContentHash>, DagError> {
+        let predecessors = topology.predecessors();
+        let mut hashes = BTreeMap::new();
+        for key in topology.topological_order()? {
+            let mut inputs: Vec<(ContentHash, i32)> = predecessors[&key].iter()
+                .map(|(input, weight)| (hashes[input], *weight))
+                .collect();
+            let node = self.get(&key).expect("Topology node missing from graph");
+            let hash = content_hash(&node.borrow().data, &mut inputs);
+            hashes.insert(key, hash);
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyNormalizer;
+
+    #[test]
+    fn identical_content_is_deduplicated_on_insert() {
+        let mut dag = Dag::new();
+        let source = dag.add_content("source", &[]).unwrap();
+        let first = dag.add_content("compile", &[&source]).unwrap();
+        let second = dag.add_content("compile", &[&source]).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(dag.merkle_root(&first).unwrap().to_string(), first);
+        assert_ne!(dag.add_content("compile", &[]).unwrap(), first);
+    }
+
+    #[test]
+    fn roots_resolve_normalized_keys_and_aliases() {
+        let mut dag = Dag::with_key_normalizer(KeyNormalizer::case_insensitive());
+        dag.add("orders", "orders");
+        dag.alias("orders-v1", "orders").unwrap();
+        let root = dag.merkle_root("orders").unwrap();
+        assert_eq!(dag.merkle_root("ORDERS").unwrap(), root);
+        assert_eq!(dag.merkle_root("orders-v1").unwrap(), root);
+        assert_eq!(dag.merkle_root("missing"), Err(DagError::NodeNotFound("missing".to_string())));
+    }
+
+    #[test]
+    fn identical_subgraphs_are_merged() {
+        let mut dag = Dag::new();
+        for (key, data) in [("a1", "leaf"), ("b1", "mid"), ("a2", "leaf"), ("b2", "mid"), ("top", "top")] {
+            dag.add(key, data);
+        }
+        dag.link("a1", "b1", 1);
+        dag.link("a2", "b2", 1);
+        dag.link("b2", "top", 1);
+        assert_eq!(dag.merkle_root("b1").unwrap(), dag.merkle_root("b2").unwrap());
+
+        let before = dag.merkle_root("top").unwrap();
+        let replaced = dag.dedup_subgraphs().unwrap();
+        assert_eq!(replaced["a2"], "a1");
+        assert_eq!(replaced["b2"], "b1");
+        assert!(dag.get("b2").is_none());
+        assert_eq!(dag.successors("b1"), vec!["top".to_string()]);
+        assert_eq!(dag.merkle_root("top").unwrap(), before);
+    }
+
+    #[test]
+    fn merged_duplicates_keep_soft_edges() {
+        let mut dag = Dag::new();
+        for (key, data) in [("a1", "leaf"), ("a2", "leaf"), ("cleanup", "cleanup")] {
+            dag.add(key, data);
+        }
+        dag.add_soft_edge("a2", "cleanup");
+        dag.dedup_subgraphs().unwrap();
+        assert!(dag.get("a2").is_none());
+        assert!(dag.is_soft_edge("a1", "cleanup"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use crate::{Dag, DagError};
+
+type Migration = Box<dyn Fn(&str) -> String>;
+
+// Rewrites of rendered payloads from one schema version to a later one.
+// Loading a snapshot runs every step from the version it was written with
+// up to `current`, in order.
+pub struct Migrations {
+    current: u32,
+    steps: BTreeMap<u32, (u32, Migration)>,
+}
+
+impl Migrations {
+    pub fn new(current: u32) -> Migrations {
+        Migrations { current, steps: BTreeMap::new() }
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    // Replaces any migration already registered from `from`.
+    pub fn register_migration<F>(&mut self, from: u32, to: u32, migrate: F) -> &mut Migrations
+        where F: Fn(&str) -> String + 'static {
+        assert!(from < to && to <= self.current, "Migrations run forward, up to the current schema version");
+        self.steps.insert(from, (to, Box::new(migrate)));
+        self
+    }
+
+    // Fails, leaving the payloads alone, if there is no chain of steps from
+    // `from` to exactly the current version.
+    pub(crate) fn upgrade<'a>(&self, from: u32, payloads: impl Iterator<Item = &'a mut String>) -> Result<(), DagError> {
+        if from > self.current {
+            return Err(DagError::Import(format!("schema version {} is newer than {}", from, self.current)));
+        }
+        let mut chain = vec![];
+        let mut version = from;
+        while version < self.current {
+            let (to, migrate) = self.steps.get(&version)
+                .ok_or_else(|| DagError::Import(format!("no migration from schema version {}", version)))?;
+            chain.push(migrate);
+            version = *to;
+        }
+        for payload in payloads {
+            for migrate in chain.iter() {
+                *payload = migrate(payload);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dag {
+    // The payload schema the graph's payloads follow, written into binary
+    // and JSON snapshots. 0 unless set or loaded.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    pub fn set_schema_version(&mut self, version: u32) {
+        self.schema_version = version;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_snapshots_migrate_on_load() {
+        let mut dag = Dag::new();
+        dag.add("order", "42");
+        dag.set_schema_version(1);
+        let v1 = dag.to_bytes();
+
+        let mut migrations = Migrations::new(3);
+        migrations
+            .register_migration(1, 2, |payload| format!("{{\"id\": {}}}", payload.trim_matches('"')))
+            .register_migration(2, 3, |payload| payload.replace("\"id\"", "\"order_id\""));
+        let loaded = Dag::from_bytes_migrating(&v1, &migrations).unwrap();
+        assert_eq!(loaded.schema_version(), 3);
+        assert_eq!(format!("{:?}", loaded.get("order").unwrap().borrow().data), "{\"order_id\": 42}");
+        assert_eq!(Dag::from_bytes(&loaded.to_bytes()).unwrap().schema_version(), 3);
+
+        dag.set_schema_version(0);
+        let err = Dag::from_bytes_migrating(&dag.to_bytes(), &migrations).err().unwrap();
+        assert_eq!(err.root(), &DagError::Import("no migration from schema version 0".to_string()));
+    }
+}
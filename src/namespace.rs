@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::ops::Bound;
+
+use crate::{Dag, NodeStrongRef};
+
+impl Dag {
+    pub fn nodes_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.keys_with_prefix(prefix).cloned().collect()
+    }
+
+    pub fn edges_within_prefix(&self, prefix: &str) -> Vec<(String, String)> {
+        let prefix: &str = &self.normalize_key(prefix);
+        self.keys_with_prefix(prefix)
+            .flat_map(|key| self.successors(key).into_iter()
+                .filter(|to_key| to_key.starts_with(prefix))
+                .map(move |to_key| (key.clone(), to_key)))
+            .collect()
+    }
+
+    // Traversal that never leaves the namespace: edges to keys outside
+    // `prefix` are not followed.
+    pub fn traverse_within_prefix(&self, start_key: &str, prefix: &str, callback: fn(NodeStrongRef) -> ()) {
+        let prefix: &str = &self.normalize_key(prefix);
+        let mut validated: HashSet<String> = HashSet::new();
+        let mut stack = vec![self.resolve_key(start_key).into_owned()];
+        while let Some(key) = stack.pop() {
+            if !key.starts_with(prefix) || !validated.insert(key.clone()) {
+                continue;
+            }
+            if let Some(node) = self.get(&key) {
+                callback(node);
+                stack.extend(self.successors(&key).into_iter().rev());
+            }
+        }
+    }
+
+    // `prefix` goes through the key normalizer, so it matches the keys as
+    // stored.
+    pub(crate) fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = &String> + '_ {
+        let prefix = self.normalize_key(prefix).into_owned();
+        self.index.range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            .take_while(move |key| key.starts_with(prefix.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn namespaced() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["team/api/build", "team/api/test", "team/web/build", "teamwork"] {
+            dag.add(key, key);
+        }
+        dag.add_edge_directed("team/api/build", "team/api/test");
+        dag.add_edge_directed("team/api/test", "team/web/build");
+        dag
+    }
+
+    #[test]
+    fn prefix_queries_use_the_sorted_index() {
+        let mut dag = namespaced();
+        assert_eq!(dag.nodes_with_prefix("team/"), vec!["team/api/build", "team/api/test", "team/web/build"]);
+        assert_eq!(dag.edges_within_prefix("team/api/"), vec![("team/api/build".to_string(), "team/api/test".to_string())]);
+        dag.remove("team/api/test");
+        assert_eq!(dag.nodes_with_prefix("team/api/"), vec!["team/api/build"]);
+    }
+
+    #[test]
+    fn traversal_stays_inside_prefix() {
+        use std::cell::RefCell;
+        thread_local!(static VISITED: RefCell<Vec<String>> = const { RefCell::new(vec![]) });
+        fn visit(node: NodeStrongRef) {
+            VISITED.with(|visited| visited.borrow_mut().push(node.borrow().key.clone()));
+        }
+        namespaced().traverse_within_prefix("team/api/build", "team/api/", visit);
+        VISITED.with(|visited| assert_eq!(*visited.borrow(), vec!["team/api/build", "team/api/test"]));
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use crate::Dag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+    Both,
+}
+
+impl Dag {
+    // Every node within `radius` hops of `key`, including `key` itself,
+    // nearest first and by key within the same distance. Empty if `key`
+    // isn't in the graph.
+    pub fn neighborhood(&self, key: &str, radius: usize, direction: Direction) -> Vec<String> {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return vec![];
+        }
+        let topology = self.topology();
+        let predecessors = match direction {
+            Direction::Outgoing => BTreeMap::new(),
+            Direction::Incoming | Direction::Both => topology.predecessors(),
+        };
+        let mut frontier = vec![key.clone()];
+        let mut distances: BTreeMap<String, usize> = BTreeMap::from([(key, 0)]);
+        for distance in 1..=radius {
+            let mut next = vec![];
+            for current in frontier.iter() {
+                let outgoing = match direction {
+                    Direction::Incoming => &[][..],
+                    Direction::Outgoing | Direction::Both => topology.successors(current),
+                };
+                let incoming = predecessors.get(current).map(|edges| edges.as_slice()).unwrap_or(&[]);
+                for (neighbour, _) in outgoing.iter().chain(incoming) {
+                    if !distances.contains_key(neighbour) {
+                        distances.insert(neighbour.clone(), distance);
+                        next.push(neighbour.clone());
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        let mut nearest: Vec<(usize, String)> = distances.into_iter().map(|(key, distance)| (distance, key)).collect();
+        nearest.sort();
+        nearest.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighborhood_ignores_direction_only_when_asked() {
+        let mut dag = Dag::new();
+        for key in ["source", "a", "b", "c", "far"] {
+            dag.add(key, ());
+        }
+        dag.link("source", "b", 1);
+        dag.link("a", "b", 1);
+        dag.link("b", "c", 1);
+        dag.link("c", "far", 1);
+        assert_eq!(dag.neighborhood("b", 1, Direction::Both), vec!["b", "a", "c", "source"]);
+        assert_eq!(dag.neighborhood("b", 1, Direction::Outgoing), vec!["b", "c"]);
+        assert_eq!(dag.neighborhood("c", 2, Direction::Incoming), vec!["c", "b", "a", "source"]);
+        assert_eq!(dag.neighborhood("a", 2, Direction::Both), vec!["a", "b", "c", "source"]);
+        assert!(dag.neighborhood("missing", 3, Direction::Both).is_empty());
+    }
+}
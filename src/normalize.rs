@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "unicode")]
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::Dag;
+
+// Keys are normalized before they are stored and before every lookup, so
+// "Orders ", "orders" and "ORDERS" all name the same node once trimming and
+// lowercasing are on. NFC composition runs first, which makes precomposed
+// and decomposed spellings of the same accented key equal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyNormalizer {
+    pub trim: bool,
+    pub lowercase: bool,
+    #[cfg(feature = "unicode")]
+    pub nfc: bool,
+}
+
+impl KeyNormalizer {
+    // Everything this build supports, for keys from case-insensitive systems.
+    pub fn case_insensitive() -> KeyNormalizer {
+        KeyNormalizer {
+            trim: true,
+            lowercase: true,
+            #[cfg(feature = "unicode")]
+            nfc: true,
+        }
+    }
+
+    pub fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let mut key = Cow::Borrowed(key);
+        #[cfg(feature = "unicode")]
+        if self.nfc && !is_nfc(&key) {
+            key = Cow::Owned(key.nfc().collect());
+        }
+        if self.trim && key.trim() != key {
+            key = Cow::Owned(key.trim().to_string());
+        }
+        if self.lowercase && key.chars().any(|c| c.to_lowercase().ne(std::iter::once(c))) {
+            key = Cow::Owned(key.to_lowercase());
+        }
+        key
+    }
+}
+
+impl Dag {
+    // Fixed for the life of the graph, so keys already stored never need
+    // rewriting.
+    pub fn with_key_normalizer(normalizer: KeyNormalizer) -> Dag {
+        let mut dag = Dag::new();
+        dag.normalizer = normalizer;
+        dag
+    }
+
+    pub fn key_normalizer(&self) -> KeyNormalizer {
+        self.normalizer
+    }
+
+    // `key` as the graph spells it. Aliases are not followed; see
+    // `resolve_key` for that.
+    pub fn normalize_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        self.normalizer.normalize(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Budget, Operation};
+
+    #[test]
+    fn differently_spelled_keys_name_one_node() {
+        let mut dag = Dag::with_key_normalizer(KeyNormalizer::case_insensitive());
+        dag.add(" Orders", 1);
+        dag.add("Customers", 2);
+        dag.add("ORDERS ", 3);
+        assert_eq!(dag.node_count(), 2);
+        assert_eq!(format!("{:?}", dag.get("orders").unwrap().borrow().data), "3");
+        dag.add_edge_directed("customers", "Orders");
+        assert_eq!(dag.successors("CUSTOMERS"), vec!["orders".to_string()]);
+        assert_eq!(dag.edge_weight("Customers", " ORDERS"), Some(1));
+        dag.update("Orders", 4);
+        assert!(dag.invalidated.contains("orders"));
+        assert!(dag.remove("oRdErS"));
+        assert!(dag.get("orders").is_none());
+    }
+
+    #[test]
+    fn queries_normalize_their_keys() {
+        let mut dag = Dag::with_key_normalizer(KeyNormalizer::case_insensitive());
+        for key in ["a", "b", "c"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("a", "b");
+        dag.add_edge_directed("b", "c");
+        let paths = dag.all_paths("A", "C", Budget::unlimited()).unwrap().value;
+        assert_eq!(paths, vec![vec!["a", "b", "c"]]);
+        let cost = dag.estimate_cost(&Operation::AllPaths { from: "A".to_string(), to: "C".to_string() }).unwrap();
+        assert!(cost.nodes > 0);
+        assert_eq!(dag.nodes_with_prefix("B"), vec!["b"]);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn composed_and_decomposed_keys_match() {
+        let mut dag = Dag::with_key_normalizer(KeyNormalizer::case_insensitive());
+        dag.add("Caf\u{e9}", 1);
+        assert!(dag.get("CAFE\u{301}").is_some());
+        assert_eq!(dag.nodes_with_prefix(""), vec!["caf\u{e9}"]);
+    }
+}
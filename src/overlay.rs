@@ -0,0 +1,447 @@
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::topology::Topology;
+use crate::{Dag, DagError, Node, NodeData, NodeStrongRef};
+
+enum Change {
+    AddNode(String, Box<NodeData>),
+    RemoveNode(String),
+    AddEdge { from_key: String, to_key: String },
+    RemoveEdge { from_key: String, to_key: String },
+}
+
+// Speculative edits against a graph. Queries see the edited structure while
+// the underlying graph stays untouched until `commit`.
+pub struct WhatIf<'a> {
+    dag: &'a mut Dag,
+    topology: Topology,
+    changes: Vec<Change>,
+}
+
+impl Dag {
+    pub fn what_if(&mut self) -> WhatIf<'_> {
+        let topology = self.topology();
+        WhatIf {
+            dag: self,
+            topology,
+            changes: vec![],
+        }
+    }
+}
+
+impl<'a> WhatIf<'a> {
+    pub fn base(&self) -> &Dag {
+        self.dag
+    }
+
+    pub fn add<T>(&mut self, key: &str, data: T) where T: Debug + 'static {
+        // Re-adding a key only replaces its payload; its edges stay, as on
+        // Dag.
+        self.topology.insert_node(key);
+        self.changes.push(Change::AddNode(key.to_string(), Box::new(data)));
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        let removed = self.topology.remove_node(key);
+        if removed {
+            self.changes.push(Change::RemoveNode(key.to_string()));
+        }
+        removed
+    }
+
+    pub fn add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        for key in [from_key, to_key] {
+            if !self.topology.contains(key) {
+                return Err(DagError::NodeNotFound(key.to_string()));
+            }
+        }
+        if let Some(path) = self.topology.path(to_key, from_key) {
+            return Err(DagError::WouldCreateCycle {
+                from: from_key.to_string(),
+                to: to_key.to_string(),
+                path,
+            });
+        }
+        self.topology.insert_edge(from_key, to_key, 1);
+        self.changes.push(Change::AddEdge {
+            from_key: from_key.to_string(),
+            to_key: to_key.to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, from_key: &str, to_key: &str) -> bool {
+        let removed = self.topology.remove_edge(from_key, to_key);
+        if removed {
+            self.changes.push(Change::RemoveEdge {
+                from_key: from_key.to_string(),
+                to_key: to_key.to_string(),
+            });
+        }
+        removed
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.topology.contains(key)
+    }
+
+    pub fn is_reachable(&self, from_key: &str, to_key: &str) -> bool {
+        self.contains(from_key) && self.contains(to_key) && self.topology.reaches(from_key, to_key)
+    }
+
+    pub fn critical_path(&self) -> Result<Vec<String>, DagError> {
+        self.topology.critical_path()
+    }
+
+    pub fn topological_order(&self) -> Result<Vec<String>, DagError> {
+        self.topology.topological_order()
+    }
+
+    // Applies nothing if the changes would break the graph's quotas. Like
+    // `add_between`, removals aren't credited, so the check is conservative.
+    pub fn commit(self) -> Result<(), DagError> {
+        let mut new_keys: Vec<&str> = vec![];
+        let mut edge_sources: Vec<&str> = vec![];
+        for change in self.changes.iter() {
+            match change {
+                Change::AddNode(key, _) if !new_keys.contains(&key.as_str()) => new_keys.push(key),
+                Change::AddEdge { from_key, .. } => edge_sources.push(from_key),
+                _ => {},
+            }
+        }
+        self.dag.check_node_quota(&new_keys).map_err(|err| err.during("commit"))?;
+        self.dag.check_edge_quota(&edge_sources).map_err(|err| err.during("commit"))?;
+        for change in self.changes {
+            match change {
+                Change::AddNode(key, data) => self.dag.insert_boxed(&key, data),
+                Change::RemoveNode(key) => {
+                    self.dag.remove(&key);
+                },
+                Change::AddEdge { from_key, to_key } => self.dag.link(&from_key, &to_key, 1),
+                Change::RemoveEdge { from_key, to_key } => {
+                    self.dag.unlink(&from_key, &to_key);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    pub fn discard(self) {}
+}
+
+// Long-lived patch layered over a shared base graph. The base is never
+// mutated; every change lives in the patch, so many patches can share one
+// base definition.
+pub struct PatchedDag {
+    base: Rc<Dag>,
+    nodes: HashMap<String, NodeStrongRef>,
+    removed: HashSet<String>,
+    added_edges: BTreeMap<String, Vec<(String, i32)>>,
+    removed_edges: HashSet<(String, String)>,
+    // The combined structure, rebuilt on the first query after a patch op.
+    topology: RefCell<Option<Topology>>,
+}
+
+// Read-only handle on a node seen through a patch. Base nodes are shared
+// with every other patch, so the handle never hands out a mutable borrow.
+pub struct PatchedNode(NodeStrongRef);
+
+impl PatchedNode {
+    pub fn borrow(&self) -> Ref<'_, Node> {
+        self.0.borrow()
+    }
+}
+
+impl PatchedDag {
+    pub fn new(base: Rc<Dag>) -> PatchedDag {
+        PatchedDag {
+            base,
+            nodes: HashMap::new(),
+            removed: HashSet::new(),
+            added_edges: BTreeMap::new(),
+            removed_edges: HashSet::new(),
+            topology: RefCell::new(None),
+        }
+    }
+
+    pub fn base(&self) -> &Rc<Dag> {
+        &self.base
+    }
+
+    pub fn add<T>(&mut self, key: &str, data: T) -> Result<(), DagError> where T: Debug + 'static {
+        if self.contains(key) {
+            return Err(DagError::DuplicateNode(key.to_string()));
+        }
+        // A re-added base key stays in `removed`, which keeps its base edges
+        // hidden: the new node starts without any.
+        self.set_node(key, Box::new(data));
+        Ok(())
+    }
+
+    // Replaces the payload of a visible node, keeping its edges.
+    pub fn override_data<T>(&mut self, key: &str, data: T) -> Result<(), DagError> where T: Debug + 'static {
+        if !self.contains(key) {
+            return Err(DagError::NodeNotFound(key.to_string()));
+        }
+        self.set_node(key, Box::new(data));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+        self.topology.get_mut().take();
+        self.nodes.remove(key);
+        self.added_edges.remove(key);
+        for edges in self.added_edges.values_mut() {
+            edges.retain(|(to_key, _)| to_key != key);
+        }
+        self.removed_edges.retain(|(from_key, to_key)| from_key != key && to_key != key);
+        if self.base.get(key).is_some() {
+            self.removed.insert(key.to_string());
+        }
+        true
+    }
+
+    pub fn add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        for key in [from_key, to_key] {
+            if !self.contains(key) {
+                return Err(DagError::NodeNotFound(key.to_string()));
+            }
+        }
+        if let Some(path) = self.topology().path(to_key, from_key) {
+            return Err(DagError::WouldCreateCycle {
+                from: from_key.to_string(),
+                to: to_key.to_string(),
+                path,
+            });
+        }
+        self.topology.get_mut().take();
+        let edge = (from_key.to_string(), to_key.to_string());
+        if !self.removed_edges.remove(&edge) || !self.base_has_edge(from_key, to_key) {
+            self.added_edges.entry(edge.0).or_default().push((edge.1, 1));
+        }
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, from_key: &str, to_key: &str) -> bool {
+        let mut removed = false;
+        if let Some(edges) = self.added_edges.get_mut(from_key) {
+            let before = edges.len();
+            edges.retain(|(key, _)| key != to_key);
+            removed = edges.len() != before;
+        }
+        if self.contains(from_key) && self.contains(to_key) && self.base_has_edge(from_key, to_key) {
+            removed |= self.removed_edges.insert((from_key.to_string(), to_key.to_string()));
+        }
+        if removed {
+            self.topology.get_mut().take();
+        }
+        removed
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.nodes.contains_key(key) || (self.base.get(key).is_some() && !self.removed.contains(key))
+    }
+
+    // Patch-owned nodes carry no edges of their own; use `successors` to see
+    // the combined structure. Payload changes go through `override_data`.
+    pub fn get(&self, key: &str) -> Option<PatchedNode> {
+        let node = match self.nodes.get(key) {
+            Some(node) => Some(Rc::clone(node)),
+            None if !self.removed.contains(key) => self.base.get(key),
+            None => None,
+        };
+        node.map(PatchedNode)
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.topology().keys().cloned().collect()
+    }
+
+    pub fn successors(&self, key: &str) -> Vec<String> {
+        self.topology().successors(key).iter().map(|(to_key, _)| to_key.clone()).collect()
+    }
+
+    pub fn is_reachable(&self, from_key: &str, to_key: &str) -> bool {
+        self.contains(from_key) && self.contains(to_key) && self.topology().reaches(from_key, to_key)
+    }
+
+    pub fn critical_path(&self) -> Result<Vec<String>, DagError> {
+        self.topology().critical_path()
+    }
+
+    pub fn topological_order(&self) -> Result<Vec<String>, DagError> {
+        self.topology().topological_order()
+    }
+
+    fn set_node(&mut self, key: &str, data: Box<NodeData>) {
+        let node = Node::new(key.to_string(), data);
+        if self.nodes.insert(key.to_string(), Rc::new(RefCell::new(node))).is_none() {
+            self.topology.get_mut().take();
+        }
+    }
+
+    fn base_has_edge(&self, from_key: &str, to_key: &str) -> bool {
+        !self.removed.contains(from_key) && !self.removed.contains(to_key) && self.base.successors(from_key).iter().any(|key| key == to_key)
+    }
+
+    fn topology(&self) -> Ref<'_, Topology> {
+        if self.topology.borrow().is_none() {
+            *self.topology.borrow_mut() = Some(self.build_topology());
+        }
+        Ref::map(self.topology.borrow(), |topology| topology.as_ref().expect("Topology built above"))
+    }
+
+    fn build_topology(&self) -> Topology {
+        let base = self.base.topology();
+        let mut topology = Topology::default();
+        for key in base.keys().filter(|key| !self.removed.contains(*key)).chain(self.nodes.keys()) {
+            topology.insert_node(key);
+        }
+        for key in base.keys().filter(|key| !self.removed.contains(*key)) {
+            for (to_key, weight) in base.successors(key) {
+                let edge = (key.clone(), to_key.clone());
+                if !self.removed.contains(to_key) && !self.removed_edges.contains(&edge) {
+                    topology.insert_edge(key, to_key, *weight);
+                }
+            }
+        }
+        for (from_key, edges) in self.added_edges.iter() {
+            for (to_key, weight) in edges {
+                topology.insert_edge(from_key, to_key, *weight);
+            }
+        }
+        topology
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, Limits};
+
+    fn chain() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("A", "a");
+        dag.add("B", "b");
+        dag.link("A", "B", 1);
+        dag
+    }
+
+    #[test]
+    fn discarded_changes_leave_graph_untouched() {
+        let mut dag = chain();
+        let mut what_if = dag.what_if();
+        what_if.add("C", "c");
+        what_if.add_edge("B", "C").unwrap();
+        assert!(what_if.is_reachable("A", "C"));
+        assert_eq!(what_if.critical_path().unwrap(), vec!["A", "B", "C"]);
+        what_if.discard();
+        assert!(dag.get("C").is_none());
+    }
+
+    #[test]
+    fn committed_changes_apply_to_graph() {
+        let mut dag = chain();
+        let mut what_if = dag.what_if();
+        what_if.add("C", "c");
+        what_if.add_edge("A", "C").unwrap();
+        assert!(what_if.remove_edge("A", "B"));
+        assert!(what_if.add_edge("B", "A").is_ok());
+        assert!(what_if.add_edge("A", "B").is_err());
+        what_if.commit().unwrap();
+        assert!(dag.is_reachable("B", "C"));
+        assert!(!dag.is_reachable("A", "B"));
+    }
+
+    #[test]
+    fn re_adding_keeps_edges() {
+        let mut dag = chain();
+        let mut what_if = dag.what_if();
+        what_if.add("A", 1);
+        assert!(what_if.add_edge("B", "A").is_err());
+        assert_eq!(what_if.topological_order().unwrap(), vec!["A", "B"]);
+        what_if.commit().unwrap();
+        assert!(dag.is_reachable("A", "B"));
+        assert!(dag.find_cycle().is_none());
+    }
+
+    #[test]
+    fn over_quota_commits_apply_nothing() {
+        let mut dag = chain();
+        dag.set_limits(Limits { max_nodes: Some(3), ..Limits::default() });
+        let mut what_if = dag.what_if();
+        what_if.add("C", "c");
+        what_if.add_edge("B", "C").unwrap();
+        what_if.add("D", "d");
+        let err = what_if.commit().unwrap_err();
+        assert_eq!(err.root(), &DagError::QuotaExceeded { limit: Limit::Nodes, maximum: 3, key: "D".to_string() });
+        assert_eq!(dag.node_count(), 2);
+        assert!(dag.successors("B").is_empty());
+    }
+
+    #[test]
+    fn patches_share_an_untouched_base() {
+        let base = Rc::new(chain());
+        let mut tenant_a = PatchedDag::new(Rc::clone(&base));
+        let mut tenant_b = PatchedDag::new(Rc::clone(&base));
+
+        tenant_a.add("C", "c").unwrap();
+        tenant_a.add_edge("B", "C").unwrap();
+        tenant_a.override_data("A", "custom").unwrap();
+        assert!(tenant_b.remove_edge("A", "B"));
+
+        assert_eq!(tenant_a.topological_order().unwrap(), vec!["A", "B", "C"]);
+        assert_eq!(format!("{:?}", tenant_a.get("A").unwrap().borrow().data), "\"custom\"");
+        assert!(!tenant_b.is_reachable("A", "B"));
+        assert!(base.is_reachable("A", "B"));
+        assert!(base.get("C").is_none());
+        assert_eq!(format!("{:?}", base.get("A").unwrap().borrow().data), "\"a\"");
+    }
+
+    #[test]
+    fn patched_views_never_reach_the_base_node() {
+        let base = Rc::new(chain());
+        let mut patch = PatchedDag::new(Rc::clone(&base));
+        let seen = patch.get("A").unwrap();
+        assert_eq!(format!("{:?}", seen.borrow().data), "\"a\"");
+        drop(seen);
+
+        patch.override_data("A", "patched").unwrap();
+        assert_eq!(format!("{:?}", patch.get("A").unwrap().borrow().data), "\"patched\"");
+        assert_eq!(format!("{:?}", base.get("A").unwrap().borrow().data), "\"a\"");
+        assert_eq!(base.successors("A"), vec!["B".to_string()]);
+        assert_eq!(patch.successors("A"), vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn removed_base_nodes_are_hidden() {
+        let base = Rc::new(chain());
+        let mut patch = PatchedDag::new(base);
+        assert!(patch.remove("B"));
+        assert!(patch.get("B").is_none());
+        assert_eq!(patch.keys(), vec!["A"]);
+        patch.add("B", "new").unwrap();
+        assert!(patch.successors("A").is_empty());
+    }
+
+    #[test]
+    fn cached_structure_follows_patch_ops() {
+        let mut patch = PatchedDag::new(Rc::new(chain()));
+        assert_eq!(patch.successors("A"), vec!["B".to_string()]);
+        patch.add("C", "c").unwrap();
+        assert_eq!(patch.keys(), vec!["A", "B", "C"]);
+        patch.add_edge("B", "C").unwrap();
+        assert!(patch.is_reachable("A", "C"));
+        assert!(patch.add_edge("C", "A").is_err());
+        assert!(patch.remove_edge("A", "B"));
+        assert!(!patch.is_reachable("A", "C"));
+        assert!(patch.remove("C"));
+        assert_eq!(patch.topological_order().unwrap(), vec!["A", "B"]);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use crate::{Dag, DagError, DispatchId};
+
+// Reports a node as finished even if its callback panicked, so the
+// scheduler never waits on a worker that is gone; the panic itself resurfaces
+// when the workers are joined.
+struct Finished {
+    key: String,
+    done: Sender<String>,
+}
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        let _ = self.done.send(std::mem::take(&mut self.key));
+    }
+}
+
+impl Dag {
+    // Returns false if `key` isn't in the graph. `None` takes the node out
+    // of its group.
+    pub fn set_concurrency_group(&mut self, key: &str, group: Option<&str>) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        match group {
+            Some(group) => self.concurrency_groups.insert(key, group.to_string()),
+            None => self.concurrency_groups.remove(&key),
+        };
+        true
+    }
+
+    pub fn concurrency_group(&self, key: &str) -> Option<&str> {
+        self.concurrency_groups.get(self.resolve_key(key).as_ref()).map(|group| group.as_str())
+    }
+
+    // Groups without a limit only share the overall worker count.
+    pub fn set_group_limit(&mut self, group: &str, max_parallel: usize) {
+        assert!(max_parallel > 0, "A concurrency group needs room for at least one node");
+        self.group_limits.insert(group.to_string(), max_parallel);
+    }
+
+    // Runs the dirty region on up to `max_workers` threads at once, never
+    // more than a group's limit from the same group, each node only after
+    // everything it depends on has finished. Payloads can't cross threads,
+    // so callbacks are handed the node's key. Nothing runs if the region has
+    // a cycle.
+    pub fn dispatch_parallel<F>(&mut self, max_workers: usize, callback: F) -> Result<DispatchId, DagError>
+        where F: Fn(&str) + Sync {
+        assert!(max_workers > 0, "A parallel dispatch needs at least one worker");
+        let region = self.dirty_region()?;
+        let order = region.topological_order()?;
+        let mut waiting = region.in_degrees();
+        let mut ready: BTreeSet<String> = waiting.iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let group_of = |key: &str| self.concurrency_groups.get(key).map(|group| group.as_str());
+        let has_room = |group: Option<&str>, running: &BTreeMap<&str, usize>| group.is_none_or(|group| {
+            self.group_limits.get(group).is_none_or(|limit| running.get(group).copied().unwrap_or(0) < *limit)
+        });
+        let callback = &callback;
+        thread::scope(|scope| {
+            let (done, finished) = mpsc::channel::<String>();
+            let mut running = 0;
+            let mut group_running: BTreeMap<&str, usize> = BTreeMap::new();
+            loop {
+                let startable: Vec<String> = ready.iter()
+                    .filter(|key| has_room(group_of(key), &group_running))
+                    .cloned()
+                    .collect();
+                for key in startable {
+                    let group = group_of(&key);
+                    if running >= max_workers || !has_room(group, &group_running) {
+                        continue;
+                    }
+                    ready.remove(&key);
+                    running += 1;
+                    if let Some(group) = group {
+                        *group_running.entry(group).or_default() += 1;
+                    }
+                    let finished = Finished { key, done: done.clone() };
+                    scope.spawn(move || callback(&finished.key));
+                }
+                if running == 0 {
+                    break;
+                }
+                let key = finished.recv().expect("Workers report before exiting");
+                running -= 1;
+                if let Some(group) = group_of(&key) {
+                    *group_running.get_mut(group).expect("Running group counted") -= 1;
+                }
+                for (next, _) in region.successors(&key) {
+                    let count = waiting.get_mut(next).expect("Region successor missing");
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(next.clone());
+                    }
+                }
+            }
+        });
+        let id = self.next_dispatch_id();
+        self.mark_dispatched(order.into_iter().collect::<HashSet<String>>(), id);
+        self.clear_invalidated();
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn groups_cap_their_own_parallelism() {
+        let mut dag = Dag::new();
+        dag.add("start", ());
+        for i in 0..6 {
+            let key = format!("db-{}", i);
+            dag.add(&key, ()).depends_on("start");
+            dag.set_concurrency_group(&key, Some("database"));
+        }
+        dag.add("report", ());
+        for i in 0..6 {
+            dag.add_dependency("report", &format!("db-{}", i));
+        }
+        dag.set_group_limit("database", 2);
+        dag.update("start", ());
+
+        let (active, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let order = Mutex::new(vec![]);
+        let id = dag.dispatch_parallel(4, |key| {
+            if key.starts_with("db-") {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(5));
+                active.fetch_sub(1, Ordering::SeqCst);
+            }
+            order.lock().unwrap().push(key.to_string());
+        }).unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let order = order.into_inner().unwrap();
+        assert_eq!((order.first().unwrap().as_str(), order.last().unwrap().as_str()), ("start", "report"));
+        assert_eq!(dag.last_dispatched("report"), Some(id));
+        assert!(dag.invalidated.is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use sha2::{Digest, Sha256};
+
+use crate::chaos::SplitMix;
+use crate::topology::Topology;
+use crate::{Dag, StoredPayload};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartitionStrategy {
+    Hash,
+    // Streams nodes in breadth-first order onto the part holding most of their
+    // already placed neighbours, penalised by how full that part is, then
+    // moves single nodes across the cut while that lowers its weight.
+    #[default]
+    Greedy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CutEdge {
+    pub from: String,
+    pub to: String,
+    pub weight: i32,
+    pub from_part: usize,
+    pub to_part: usize,
+}
+
+// How a boundary node is wired to other parts, keyed by part index: which
+// remote nodes feed it and which remote nodes it feeds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stitch {
+    pub inputs: BTreeMap<usize, BTreeSet<String>>,
+    pub outputs: BTreeMap<usize, BTreeSet<String>>,
+}
+
+pub struct Partitioning {
+    pub parts: Vec<Dag>,
+    pub assignment: BTreeMap<String, usize>,
+    pub cut_edges: Vec<CutEdge>,
+    pub stitching: Vec<BTreeMap<String, Stitch>>,
+}
+
+impl Partitioning {
+    pub fn cut_weight(&self) -> i64 {
+        self.cut_edges.iter().map(|edge| edge.weight as i64).sum()
+    }
+
+    pub fn boundary_nodes(&self, part: usize) -> impl Iterator<Item = &String> + '_ {
+        self.stitching[part].keys()
+    }
+
+    pub fn remote_tracker(&self, part: usize) -> RemoteTracker {
+        let waiting = self.stitching[part].iter()
+            .filter(|(_, stitch)| !stitch.inputs.is_empty())
+            .map(|(key, stitch)| (key.clone(), stitch.inputs.values().flatten().cloned().collect()))
+            .collect();
+        RemoteTracker { waiting }
+    }
+}
+
+// Tracks, for one part, which remote inputs each boundary node still waits
+// on. Completions are reported by key as other parts finish their nodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteTracker {
+    waiting: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl RemoteTracker {
+    // Returns the boundary nodes whose last remote input this completed.
+    pub fn complete(&mut self, remote_key: &str) -> Vec<String> {
+        let mut released = vec![];
+        for (key, inputs) in self.waiting.iter_mut() {
+            if inputs.remove(remote_key) && inputs.is_empty() {
+                released.push(key.clone());
+            }
+        }
+        released
+    }
+
+    pub fn is_satisfied(&self, key: &str) -> bool {
+        self.waiting.get(key).is_none_or(|inputs| inputs.is_empty())
+    }
+
+    pub fn pending(&self, key: &str) -> Vec<String> {
+        self.waiting.get(key).map(|inputs| inputs.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+// No part may grow past this many nodes, which keeps parts within one node of
+// an even split plus a little slack for the refinement pass to work with.
+fn capacity(nodes: usize, parts: usize) -> usize {
+    let even = nodes.div_ceil(parts);
+    even + even / 20 + 1
+}
+
+fn neighbours(topology: &Topology) -> BTreeMap<String, Vec<(String, i32)>> {
+    let mut neighbours: BTreeMap<String, Vec<(String, i32)>> = topology.predecessors();
+    for key in topology.keys() {
+        for (to_key, weight) in topology.successors(key) {
+            neighbours.get_mut(key).expect("Topology node missing").push((to_key.clone(), *weight));
+        }
+    }
+    neighbours
+}
+
+// Hashes the key bytes with SHA-256, as fingerprints do, so a key lands in
+// the same part across runs, Rust releases and platforms.
+fn hash_assignment(topology: &Topology, parts: usize) -> BTreeMap<String, usize> {
+    topology.keys()
+        .map(|key| {
+            let digest = Sha256::digest(key.as_bytes());
+            let hash = u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"));
+            (key.clone(), (hash % parts as u64) as usize)
+        })
+        .collect()
+}
+
+fn greedy_assignment(topology: &Topology, neighbours: &BTreeMap<String, Vec<(String, i32)>>, parts: usize)
+    -> BTreeMap<String, usize> {
+    let limit = capacity(topology.keys().count(), parts);
+    let mut sizes = vec![0usize; parts];
+    let mut assignment: BTreeMap<String, usize> = BTreeMap::new();
+    let in_degrees = topology.in_degrees();
+    let mut roots: Vec<&String> = topology.keys().filter(|key| in_degrees[*key] == 0).collect();
+    roots.extend(topology.keys().filter(|key| in_degrees[*key] != 0));
+    for root in roots {
+        let mut queue = VecDeque::from([root.clone()]);
+        while let Some(key) = queue.pop_front() {
+            if assignment.contains_key(&key) {
+                continue;
+            }
+            let mut affinity = vec![0i64; parts];
+            for (neighbour, weight) in neighbours[&key].iter() {
+                if let Some(part) = assignment.get(neighbour) {
+                    affinity[*part] += (*weight).max(1) as i64;
+                }
+            }
+            let score = |part: usize| affinity[part] as f64 * (1.0 - sizes[part] as f64 / limit as f64);
+            let part = (0..parts)
+                .filter(|part| sizes[*part] < limit)
+                .max_by(|a, b| score(*a).total_cmp(&score(*b)).then(sizes[*b].cmp(&sizes[*a])).then(b.cmp(a)))
+                .expect("Every part is full");
+            sizes[part] += 1;
+            assignment.insert(key.clone(), part);
+            queue.extend(neighbours[&key].iter().map(|(neighbour, _)| neighbour.clone()));
+        }
+    }
+    refine(&mut assignment, neighbours, &mut sizes, limit);
+    assignment
+}
+
+fn refine(assignment: &mut BTreeMap<String, usize>, neighbours: &BTreeMap<String, Vec<(String, i32)>>,
+    sizes: &mut [usize], limit: usize) {
+    let keys: Vec<String> = assignment.keys().cloned().collect();
+    for _ in 0..8 {
+        let mut moved = false;
+        for key in keys.iter() {
+            let current = assignment[key];
+            let mut links = vec![0i64; sizes.len()];
+            for (neighbour, weight) in neighbours[key].iter() {
+                links[assignment[neighbour]] += (*weight).max(1) as i64;
+            }
+            let best = (0..sizes.len())
+                .filter(|part| *part != current && sizes[*part] < limit && sizes[current] > 1)
+                .max_by_key(|part| (links[*part], std::cmp::Reverse(*part)));
+            if let Some(best) = best {
+                if links[best] > links[current] {
+                    sizes[current] -= 1;
+                    sizes[best] += 1;
+                    assignment.insert(key.clone(), best);
+                    moved = true;
+                }
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+impl Dag {
+    // Splits the graph into `parts` subgraphs for execution on separate
+    // machines, along with every edge crossing between them. Payloads can't
+    // be copied, so subgraph nodes carry their payload's Debug rendering.
+    pub fn partition(&self, parts: usize, strategy: PartitionStrategy) -> Partitioning {
+        assert!(parts > 0, "Cannot partition into zero parts");
+        let topology = self.topology();
+        let assignment = match strategy {
+            PartitionStrategy::Hash => hash_assignment(&topology, parts),
+            PartitionStrategy::Greedy => greedy_assignment(&topology, &neighbours(&topology), parts),
+        };
+
+        let mut subgraphs: Vec<Dag> = (0..parts).map(|_| Dag::new()).collect();
+        for (key, part) in assignment.iter() {
+            let node = self.get(key).expect("Topology node missing from graph");
+            let rendered = format!("{:?}", node.borrow().data);
+            subgraphs[*part].insert_boxed(key, Box::new(StoredPayload(rendered)));
+            if self.invalidated.contains(key) {
+                subgraphs[*part].invalidated.insert(key.clone());
+            }
+        }
+        let mut cut_edges = vec![];
+        let mut stitching: Vec<BTreeMap<String, Stitch>> = vec![BTreeMap::new(); parts];
+        for from in topology.keys() {
+            for (to, weight) in topology.successors(from) {
+                let (from_part, to_part) = (assignment[from], assignment[to]);
+                if from_part == to_part {
+                    subgraphs[from_part].link(from, to, *weight);
+                    if topology.is_soft(from, to) {
+                        subgraphs[from_part].set_edge_soft(from, to, true);
+                    }
+                } else {
+                    stitching[to_part].entry(to.clone()).or_default()
+                        .inputs.entry(from_part).or_default().insert(from.clone());
+                    stitching[from_part].entry(from.clone()).or_default()
+                        .outputs.entry(to_part).or_default().insert(to.clone());
+                    cut_edges.push(CutEdge { from: from.clone(), to: to.clone(), weight: *weight, from_part, to_part });
+                }
+            }
+        }
+        Partitioning { parts: subgraphs, assignment, cut_edges, stitching }
+    }
+
+    // Hands whole connected components to `workers`, so no edge ever
+    // crosses between them, balancing node counts. Returns each key's worker.
+    pub fn assign_round_robin(&self, workers: usize, seed: u64) -> BTreeMap<String, usize> {
+        self.assign_round_robin_by(workers, seed, |_| 1)
+    }
+
+    // Biggest components go first, each to the least loaded worker; the
+    // seed decides among components of equal cost, so the same graph and
+    // seed always give the same assignment.
+    pub fn assign_round_robin_by<C>(&self, workers: usize, seed: u64, cost: C) -> BTreeMap<String, usize>
+        where C: Fn(&str) -> u64 {
+        assert!(workers > 0, "Cannot assign work to zero workers");
+        let topology = self.topology();
+        let neighbours = neighbours(&topology);
+        let mut components: Vec<(u64, Vec<String>)> = vec![];
+        let mut seen: BTreeSet<&String> = BTreeSet::new();
+        for root in topology.keys() {
+            if !seen.insert(root) {
+                continue;
+            }
+            let mut members = vec![];
+            let mut queue = VecDeque::from([root]);
+            while let Some(key) = queue.pop_front() {
+                members.push(key.clone());
+                queue.extend(neighbours[key].iter().map(|(next, _)| next).filter(|next| seen.insert(*next)));
+            }
+            components.push((members.iter().map(|key| cost(key)).sum(), members));
+        }
+        let mut rng = SplitMix(seed);
+        for i in (1..components.len()).rev() {
+            components.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        components.sort_by_key(|(component_cost, _)| std::cmp::Reverse(*component_cost));
+
+        let mut loads = vec![0u64; workers];
+        let mut assignment = BTreeMap::new();
+        for (component_cost, members) in components {
+            let worker = (0..workers).min_by_key(|worker| loads[*worker]).expect("At least one worker");
+            loads[worker] += component_cost;
+            assignment.extend(members.into_iter().map(|key| (key, worker)));
+        }
+        assignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two dense clusters joined by a single edge.
+    fn clusters() -> Dag {
+        let mut dag = Dag::new();
+        for cluster in ["a", "b"] {
+            for i in 0..4 {
+                dag.add(&format!("{}{}", cluster, i), i);
+            }
+            for i in 0..4 {
+                for j in (i + 1)..4 {
+                    dag.add_edge_directed(&format!("{}{}", cluster, i), &format!("{}{}", cluster, j));
+                }
+            }
+        }
+        dag.add_edge_directed("a3", "b0");
+        dag
+    }
+
+    #[test]
+    fn greedy_cuts_between_clusters() {
+        let dag = clusters();
+        let partitioning = dag.partition(2, PartitionStrategy::Greedy);
+        assert_eq!(partitioning.cut_edges.len(), 1);
+        assert_eq!(partitioning.cut_weight(), 1);
+        assert_eq!(partitioning.parts.iter().map(|part| part.node_count()).collect::<Vec<_>>(), vec![4, 4]);
+        let a = partitioning.assignment["a0"];
+        assert!((0..4).all(|i| partitioning.assignment[&format!("a{}", i)] == a));
+        assert_eq!(partitioning.parts[a].edge_count(), 6);
+    }
+
+    #[test]
+    fn soft_edges_stay_soft_inside_a_part() {
+        let mut dag = clusters();
+        dag.set_edge_soft("a0", "a1", true);
+        let partitioning = dag.partition(2, PartitionStrategy::Greedy);
+        let part = &partitioning.parts[partitioning.assignment["a0"]];
+        assert!(part.is_soft_edge("a0", "a1"));
+        assert!(!part.is_soft_edge("a0", "a2"));
+    }
+
+    #[test]
+    fn boundary_nodes_wait_for_every_remote_input() {
+        let mut dag = clusters();
+        dag.add_edge_directed("a2", "b1");
+        let partitioning = dag.partition(2, PartitionStrategy::Greedy);
+        let (a, b) = (partitioning.assignment["a0"], partitioning.assignment["b0"]);
+        assert_eq!(partitioning.boundary_nodes(b).collect::<Vec<_>>(), vec!["b0", "b1"]);
+        assert_eq!(partitioning.boundary_nodes(a).collect::<Vec<_>>(), vec!["a2", "a3"]);
+        assert_eq!(partitioning.stitching[a]["a3"].outputs[&b], BTreeSet::from(["b0".to_string()]));
+
+        let mut tracker = partitioning.remote_tracker(b);
+        assert!(!tracker.is_satisfied("b1"));
+        assert!(tracker.is_satisfied("b2"));
+        assert_eq!(tracker.complete("a3"), vec!["b0".to_string()]);
+        assert_eq!(tracker.pending("b1"), vec!["a2".to_string()]);
+        assert_eq!(tracker.complete("a2"), vec!["b1".to_string()]);
+    }
+
+    #[test]
+    fn every_node_lands_in_exactly_one_part() {
+        let dag = clusters();
+        let partitioning = dag.partition(3, PartitionStrategy::Hash);
+        let total: usize = partitioning.parts.iter().map(|part| part.node_count()).sum();
+        assert_eq!(total, 8);
+        let internal: usize = partitioning.parts.iter().map(|part| part.edge_count()).sum();
+        assert_eq!(internal + partitioning.cut_edges.len(), 13);
+        let rendered = format!("{:?}", partitioning.parts[partitioning.assignment["b2"]].get("b2").unwrap().borrow().data);
+        assert_eq!(rendered, "2");
+    }
+
+    #[test]
+    fn hash_parts_are_stable_across_runs() {
+        let partitioning = clusters().partition(3, PartitionStrategy::Hash);
+        let parts: Vec<usize> = partitioning.assignment.values().copied().collect();
+        assert_eq!(parts, vec![2, 0, 1, 1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn round_robin_keeps_components_whole() {
+        let mut dag = clusters();
+        for i in 0..5 {
+            dag.add(&format!("solo{}", i), ());
+        }
+        let assignment = dag.assign_round_robin(3, 7);
+        assert_eq!(assignment, dag.assign_round_robin(3, 7));
+        assert!((0..4).all(|i| assignment[&format!("a{}", i)] == assignment["a0"]));
+        let mut loads = [0; 3];
+        for worker in assignment.values() {
+            loads[*worker] += 1;
+        }
+        loads.sort();
+        assert_eq!(loads, [2, 3, 8]);
+        assert_ne!((1..20).map(|seed| dag.assign_round_robin(3, seed)).collect::<BTreeSet<_>>().len(), 1);
+
+        let weighted = dag.assign_round_robin_by(2, 7, |key| if key == "solo0" { 8 } else { 1 });
+        assert_ne!(weighted["solo0"], weighted["a0"]);
+    }
+}
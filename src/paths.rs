@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::{Dag, DagError};
+
+impl Dag {
+    // Distinct paths from `from_key` to `to_key`, saturating at `u128::MAX`
+    // on graphs wide enough to overflow it. A node has one path to itself.
+    pub fn count_paths(&self, from_key: &str, to_key: &str) -> Result<u128, DagError> {
+        let (from_key, to_key) = (self.resolve_key(from_key).into_owned(), self.resolve_key(to_key).into_owned());
+        for key in [&from_key, &to_key] {
+            if self.get(key).is_none() {
+                return Err(DagError::NodeNotFound(key.clone()));
+            }
+        }
+        let topology = self.try_topology("count_paths")?;
+        let order = topology.topological_order().map_err(|err| err.during("count_paths"))?;
+        let mut paths: HashMap<&String, u128> = HashMap::from([(&from_key, 1)]);
+        for key in order.iter().skip_while(|key| **key != from_key) {
+            let Some(&count) = paths.get(key) else { continue };
+            if *key == to_key {
+                return Ok(count);
+            }
+            for (next, _) in topology.successors(key) {
+                let entry = paths.entry(next).or_insert(0);
+                *entry = entry.saturating_add(count);
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diamonds_multiply_and_saturate() {
+        let mut dag = Dag::new();
+        dag.add("n0", ());
+        for layer in 1..=130 {
+            for side in ["left", "right"] {
+                let key = format!("{}-{}", side, layer);
+                dag.add(&key, ());
+                dag.add_dependency(&key, &format!("n{}", layer - 1));
+            }
+            dag.add(&format!("n{}", layer), ());
+            dag.add_dependency(&format!("n{}", layer), &format!("left-{}", layer));
+            dag.add_dependency(&format!("n{}", layer), &format!("right-{}", layer));
+        }
+        assert_eq!(dag.count_paths("n0", "n3"), Ok(8));
+        assert_eq!(dag.count_paths("left-2", "n2"), Ok(1));
+        assert_eq!(dag.count_paths("n2", "n1"), Ok(0));
+        assert_eq!(dag.count_paths("n0", "n130"), Ok(u128::MAX));
+        assert_eq!(dag.count_paths("n0", "nope"), Err(DagError::NodeNotFound("nope".to_string())));
+    }
+}
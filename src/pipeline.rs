@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+use crate::{Dag, DagError};
+
+// Pipelines written by hand as TOML, one table per node:
+//
+//     [nodes.load]
+//     payload = "s3://warehouse/daily"
+//     depends_on = ["extract"]
+//     after = ["vacuum"]
+//
+// `depends_on` adds hard edges and `after` soft ones. String payloads are
+// kept as written; any other payload keeps its TOML rendering.
+const FIELDS: [&str; 3] = ["payload", "depends_on", "after"];
+
+fn invalid(source: &str, span: Option<Range<usize>>, detail: String) -> DagError {
+    let before = &source[..span.map_or(0, |span| span.start).min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    DagError::Definition { line, column, detail }
+}
+
+fn node_table<'a>(source: &str, key: &str, item: &'a Item) -> Result<&'a dyn TableLike, DagError> {
+    item.as_table_like().ok_or_else(|| invalid(source, item.span(), format!("node {} must be a table", key)))
+}
+
+impl Dag {
+    // Every reference is checked before the graph is handed back: unknown
+    // fields, dependencies on undeclared nodes, and dependencies that close
+    // a cycle are reported at the offending spot in `source`.
+    pub fn from_pipeline_toml(source: &str) -> Result<Dag, DagError> {
+        let document = ImDocument::parse(source)
+            .map_err(|err| invalid(source, err.span(), err.message().to_string()))?;
+        let mut dag = Dag::new();
+        let Some(nodes) = document.get("nodes") else { return Ok(dag) };
+        let nodes = nodes.as_table_like()
+            .ok_or_else(|| invalid(source, nodes.span(), "nodes must be a table".to_string()))?;
+
+        let mut edges: Vec<(String, String, bool, Option<Range<usize>>)> = vec![];
+        for (key, item) in nodes.iter() {
+            let table = node_table(source, key, item)?;
+            if let Some((field, _)) = table.iter().find(|(field, _)| !FIELDS.contains(field)) {
+                let span = table.key(field).and_then(|field| field.span());
+                return Err(invalid(source, span, format!("node {} has unknown field {}", key, field)));
+            }
+            let payload = match table.get("payload") {
+                Some(Item::Value(Value::String(payload))) => payload.value().clone(),
+                Some(other) => other.to_string().trim().to_string(),
+                None => String::new(),
+            };
+            dag.add(key, payload);
+            for (field, soft) in [("depends_on", false), ("after", true)] {
+                let Some(item) = table.get(field) else { continue };
+                let list = item.as_array()
+                    .ok_or_else(|| invalid(source, item.span(), format!("{} of {} must be a list of keys", field, key)))?;
+                for entry in list.iter() {
+                    let dependency = entry.as_str()
+                        .ok_or_else(|| invalid(source, entry.span(), format!("{} of {} must be a list of keys", field, key)))?;
+                    edges.push((dependency.to_string(), key.to_string(), soft, entry.span()));
+                }
+            }
+        }
+
+        for (dependency, key, soft, span) in edges {
+            if dag.get(&dependency).is_none() {
+                return Err(invalid(source, span, format!("node {} depends on undeclared node {}", key, dependency)));
+            }
+            if let Some(mut path) = dag.topology().path(&key, &dependency) {
+                path.push(key.clone());
+                let detail = format!("node {} depending on {} closes the cycle {}", key, dependency, path.join(" -> "));
+                return Err(invalid(source, span, detail));
+            }
+            if soft {
+                dag.add_soft_edge(&dependency, &key);
+            } else {
+                dag.add_edge_directed(&dependency, &key);
+            }
+        }
+        Ok(dag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipelines_load_with_their_edges() {
+        let dag = Dag::from_pipeline_toml(r#"
+            [nodes.extract]
+            payload = "s3://raw"
+
+            [nodes.vacuum]
+
+            [nodes.load]
+            payload = 3
+            depends_on = ["extract"]
+            after = ["vacuum"]
+        "#).unwrap();
+        assert_eq!(dag.edge_weight("extract", "load"), Some(1));
+        assert!(dag.is_soft_edge("vacuum", "load"));
+        assert_eq!(format!("{:?}", dag.get("extract").unwrap().borrow().data), "\"s3://raw\"");
+        assert_eq!(format!("{:?}", dag.get("load").unwrap().borrow().data), "\"3\"");
+    }
+
+    #[test]
+    fn bad_references_point_at_their_line() {
+        let error = |source: &str| Dag::from_pipeline_toml(source).err().unwrap();
+        assert_eq!(error("[nodes.a]\ndepends_on = [\"b\"]\n"), DagError::Definition {
+            line: 2,
+            column: 15,
+            detail: "node a depends on undeclared node b".to_string(),
+        });
+        assert_eq!(error("[nodes.a]\ndepends_on = [\"b\"]\n[nodes.b]\n  depends_on = [\"a\"]\n"), DagError::Definition {
+            line: 4,
+            column: 17,
+            detail: "node b depending on a closes the cycle b -> a -> b".to_string(),
+        });
+        assert!(matches!(error("[nodes.a]\npayload = "), DagError::Definition { line: 2, .. }));
+        assert!(matches!(error("[nodes.a]\nweight = 3"), DagError::Definition { line: 2, column: 1, .. }));
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Debug;
+
+use crate::{Dag, NodeData};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Placeholder;
+
+pub(crate) fn default_payload<T>() -> Box<NodeData> where T: Default + Debug + 'static {
+    Box::new(T::default())
+}
+
+impl Dag {
+    pub fn set_placeholder_default<T>(&mut self) where T: Default + Debug + 'static {
+        self.placeholder_factory = default_payload::<T>;
+    }
+
+    pub fn add_placeholder(&mut self, key: &str) -> bool {
+        let key: &str = &self.resolve_key(key);
+        if self.get(key).is_some() {
+            return false;
+        }
+        self.check_node_quota(&[key]).unwrap_or_else(|err| panic!("{}", err));
+        let data = (self.placeholder_factory)();
+        self.insert_boxed(key, data);
+        self.placeholders.insert(key.to_string());
+        true
+    }
+
+    // Like add_edge_directed, but either endpoint may not have been defined
+    // yet.
+    pub fn add_edge_with_placeholders(&mut self, from_key: &str, to_key: &str) {
+        self.add_placeholder(from_key);
+        self.add_placeholder(to_key);
+        self.add_edge_directed(from_key, to_key);
+    }
+
+    pub fn is_placeholder(&self, key: &str) -> bool {
+        self.placeholders.contains(self.resolve_key(key).as_ref())
+    }
+
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.placeholders.iter().cloned().collect();
+        keys.sort();
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_materialize_in_place() {
+        let mut dag = Dag::new();
+        dag.add("A", "a");
+        dag.add_edge_with_placeholders("A", "B");
+        assert_eq!(dag.placeholders(), vec!["B"]);
+        assert_eq!(format!("{:?}", dag.get("B").unwrap().borrow().data), "Placeholder");
+
+        dag.add("B", "b");
+        assert!(dag.placeholders().is_empty());
+        assert_eq!(dag.successors("A"), vec!["B".to_string()]);
+        assert_eq!(format!("{:?}", dag.get("B").unwrap().borrow().data), "\"b\"");
+    }
+
+    #[test]
+    fn placeholder_payload_is_configurable() {
+        let mut dag = Dag::new();
+        dag.set_placeholder_default::<Vec<u8>>();
+        assert!(dag.add_placeholder("A"));
+        assert!(!dag.add_placeholder("A"));
+        assert_eq!(format!("{:?}", dag.get("A").unwrap().borrow().data), "[]");
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{Ctx, Dag, DagError, DispatchId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreferredOrder {
+    pub id: DispatchId,
+    // Preferences the run could not keep, as `(before, after)`.
+    pub violated: Vec<(String, String)>,
+}
+
+impl Dag {
+    // Dispatches like `dispatch_with_ctx`, but whenever several nodes are
+    // ready, holds back any that a preference `(before, after)` wants after
+    // a node that hasn't run yet. If every ready node is held back, the
+    // smallest key runs anyway and the preferences it breaks are reported.
+    // Preferences naming a node outside the run are ignored.
+    pub fn dispatch_with_preferences<F>(&mut self, preferences: &[(&str, &str)], mut callback: F)
+        -> Result<PreferredOrder, DagError> where F: FnMut(&Ctx) {
+        let region = self.dirty_region()?;
+        region.topological_order()?;
+        let preferences: Vec<(String, String)> = preferences.iter()
+            .map(|(before, after)| (self.resolve_key(before).into_owned(), self.resolve_key(after).into_owned()))
+            .filter(|(before, after)| before != after && region.contains(before) && region.contains(after))
+            .collect();
+
+        let mut waiting = region.in_degrees();
+        let mut ready: BTreeSet<String> = waiting.iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut ran: HashMap<String, usize> = HashMap::new();
+        let mut order = vec![];
+        while !ready.is_empty() {
+            let held = |key: &String| preferences.iter().any(|(before, after)| after == key && !ran.contains_key(before));
+            let key = ready.iter().find(|key| !held(key)).or(ready.first()).cloned().expect("Ready set is not empty");
+            ready.remove(&key);
+            for (next, _) in region.successors(&key) {
+                let count = waiting.get_mut(next).expect("Region successor missing");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(next.clone());
+                }
+            }
+            ran.insert(key.clone(), order.len());
+            order.push(key);
+        }
+        let violated = preferences.into_iter().filter(|(before, after)| ran[after] < ran[before]).collect();
+
+        let predecessors = self.topology().predecessors();
+        let id = self.next_dispatch_id();
+        let mut dispatched: HashSet<String> = HashSet::new();
+        for key in order {
+            let Some(ctx) = self.context(&key, id, &predecessors) else { continue };
+            callback(&ctx);
+            dispatched.insert(key);
+        }
+        self.mark_dispatched(dispatched, id);
+        self.clear_invalidated();
+        Ok(PreferredOrder { id, violated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferences_yield_to_dependencies() {
+        let mut dag = Dag::new();
+        for key in ["a", "b", "c", "d"] {
+            dag.add(key, ());
+            dag.update(key, ());
+        }
+        dag.add_edge_directed("a", "d");
+
+        let mut seen = vec![];
+        let outcome = dag.dispatch_with_preferences(&[("c", "a"), ("d", "b"), ("zzz", "a")], |ctx| {
+            seen.push(ctx.key().to_string());
+        }).unwrap();
+        assert_eq!(seen, vec!["c", "a", "d", "b"]);
+        assert!(outcome.violated.is_empty());
+
+        for key in ["a", "d"] {
+            dag.update(key, ());
+        }
+        seen.clear();
+        let outcome = dag.dispatch_with_preferences(&[("d", "a")], |ctx| seen.push(ctx.key().to_string())).unwrap();
+        assert_eq!(seen, vec!["a", "d"]);
+        assert_eq!(outcome.violated, vec![("d".to_string(), "a".to_string())]);
+        assert_eq!(dag.last_dispatched("d"), Some(outcome.id));
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::{Dag, DagError, DispatchId, NodeStrongRef};
+
+impl Dag {
+    // Only invalidated nodes carry a priority, and it is dropped once they
+    // are dispatched. Returns false if `key` isn't invalidated.
+    pub fn set_priority(&mut self, key: &str, priority: i64) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if !self.invalidated.contains(&key) {
+            return false;
+        }
+        self.priorities.insert(key, priority);
+        true
+    }
+
+    // Invalidated nodes default to 0; anything else has no priority.
+    pub fn priority(&self, key: &str) -> Option<i64> {
+        let key: &str = &self.resolve_key(key);
+        self.invalidated.contains(key).then(|| self.priorities.get(key).copied().unwrap_or(0))
+    }
+
+    // Dispatches at most `max_nodes` nodes of the dirty region. Each node
+    // ranks as high as the most urgent invalidated node upstream of it, and
+    // among those whose dependencies have run the highest-ranked goes next.
+    // Whatever the budget didn't reach stays invalidated, keeping that rank,
+    // for a later call.
+    pub fn dispatch_budgeted<F>(&mut self, max_nodes: usize, mut callback: F) -> Result<DispatchId, DagError>
+        where F: FnMut(NodeStrongRef, DispatchId) {
+        let region = self.dirty_region()?;
+        let order = region.topological_order()?;
+        let mut rank: BTreeMap<String, i64> = BTreeMap::new();
+        for key in order.iter() {
+            let own = self.priority(key).unwrap_or(i64::MIN);
+            let inherited = rank.get(key).copied().unwrap_or(i64::MIN);
+            let rank_here = own.max(inherited);
+            rank.insert(key.clone(), rank_here);
+            for (next, _) in region.successors(key) {
+                let entry = rank.entry(next.clone()).or_insert(i64::MIN);
+                *entry = (*entry).max(rank_here);
+            }
+        }
+
+        let id = self.next_dispatch_id();
+        let mut waiting = region.in_degrees();
+        let mut ready: BTreeSet<(Reverse<i64>, String)> = waiting.iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(key, _)| (Reverse(rank[key]), key.clone()))
+            .collect();
+        let mut dispatched: HashSet<String> = HashSet::new();
+        while dispatched.len() < max_nodes {
+            let Some((_, key)) = ready.pop_first() else { break };
+            if let Some(node) = self.get(&key) {
+                callback(node, id);
+            }
+            for (next, _) in region.successors(&key) {
+                let count = waiting.get_mut(next).expect("Region successor missing");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert((Reverse(rank[next]), next.clone()));
+                }
+            }
+            dispatched.insert(key);
+        }
+
+        // Nodes below an unreached invalidated node are found again through
+        // it, so only the edge of what ran needs marking.
+        let mut left: Vec<String> = self.invalidated.iter()
+            .filter(|key| region.contains(key) && !dispatched.contains(*key))
+            .cloned()
+            .collect();
+        for key in dispatched.iter() {
+            left.extend(region.successors(key).iter()
+                .filter(|(next, _)| !dispatched.contains(next))
+                .map(|(next, _)| next.clone()));
+        }
+        let ran: Vec<String> = dispatched.iter().cloned().collect();
+        self.mark_dispatched(dispatched, id);
+        for key in left {
+            self.priorities.insert(key.clone(), rank[&key]);
+            self.invalidated.insert(key);
+        }
+        for key in ran.iter() {
+            self.invalidated.remove(key);
+            self.priorities.remove(key);
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two independent chains, A -> B -> C and X -> Y.
+    fn chains() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C", "X", "Y"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.add_edge_directed("X", "Y");
+        dag.dispatch(|_| ());
+        dag
+    }
+
+    #[test]
+    fn urgent_regions_run_first_and_the_rest_waits() {
+        let mut dag = chains();
+        dag.update("A", ());
+        dag.update("X", ());
+        assert!(dag.set_priority("X", 5));
+        assert!(!dag.set_priority("Y", 5));
+
+        let mut seen = vec![];
+        let first = dag.dispatch_budgeted(3, |node, _| seen.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(seen, vec!["X", "Y", "A"]);
+        assert_eq!(dag.last_dispatched("Y"), Some(first));
+        assert_eq!(dag.priority("X"), None);
+        assert_eq!(dag.priority("B"), Some(0));
+        assert_eq!(dag.priority("A"), None);
+
+        seen.clear();
+        dag.dispatch_budgeted(10, |node, _| seen.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(seen, vec!["B", "C"]);
+        assert!(dag.invalidated.is_empty());
+    }
+
+    #[test]
+    fn leftover_work_keeps_the_rank_it_inherited() {
+        let mut dag = chains();
+        dag.update("A", ());
+        dag.set_priority("A", 9);
+        dag.update("X", ());
+        dag.dispatch_budgeted(1, |_, _| ()).unwrap();
+        assert_eq!(dag.priority("B"), Some(9));
+
+        let mut seen = vec![];
+        dag.dispatch_budgeted(2, |node, _| seen.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(seen, vec!["B", "C"]);
+        assert_eq!(dag.priority("X"), Some(0));
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use crate::{borrow, Dag, DagError};
+
+// Edges pass a failure on with probability 1 until told otherwise. The
+// probabilities live in memory only; stores and export formats drop them.
+impl Dag {
+    // Returns false if there is no edge from `from_key` to `to_key`.
+    pub fn set_edge_probability(&mut self, from_key: &str, to_key: &str, probability: f64) -> bool {
+        assert!((0.0..=1.0).contains(&probability), "Edge probabilities lie between 0 and 1");
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let Some(node) = self.get(from_key) else { return false };
+        let mut borrowed_node = borrow::or_panic(borrow::write(&node, from_key, "set_edge_probability"));
+        let mut found = false;
+        for edge in borrowed_node.edges.iter_mut().filter(|edge| edge.to_key == to_key) {
+            edge.probability = probability;
+            found = true;
+        }
+        found
+    }
+
+    pub fn edge_probability(&self, from_key: &str, to_key: &str) -> Option<f64> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let node = self.get(from_key)?;
+        let borrowed_node = borrow::or_panic(borrow::read(&node, from_key, "edge_probability"));
+        borrowed_node.edges.iter()
+            .find(|edge| edge.to_key == to_key && self.live_target(edge).is_some())
+            .map(|edge| edge.probability)
+    }
+
+    // The chance that a failure of every root reaches each node, treating
+    // edges as independent: a node escapes only if every edge into it fails
+    // to pass the failure on. Soft edges never pass it on. Nodes out of
+    // reach are left out.
+    pub fn propagate_probability(&self, roots: &[&str]) -> Result<BTreeMap<String, f64>, DagError> {
+        let mut reach: BTreeMap<String, f64> = BTreeMap::new();
+        for root in roots {
+            let root = self.resolve_key(root).into_owned();
+            if self.get(&root).is_none() {
+                return Err(DagError::NodeNotFound(root));
+            }
+            reach.insert(root, 1.0);
+        }
+        let topology = self.try_topology("propagate_probability")?;
+        let order = topology.topological_order().map_err(|err| err.during("propagate_probability"))?;
+        let mut escapes: BTreeMap<String, f64> = BTreeMap::new();
+        for key in order {
+            let probability = match reach.get(&key) {
+                Some(probability) => *probability,
+                None => match escapes.get(&key) {
+                    Some(escape) => 1.0 - escape,
+                    None => continue,
+                },
+            };
+            reach.insert(key.clone(), probability);
+            for next in topology.hard_successors(&key) {
+                let passed = probability * self.edge_probability(&key, next).expect("Topology edge missing");
+                *escapes.entry(next.to_string()).or_insert(1.0) *= 1.0 - passed;
+            }
+        }
+        reach.retain(|_, probability| *probability > 0.0);
+        Ok(reach)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_paths_combine() {
+        let mut dag = Dag::new();
+        for key in ["db", "api", "worker", "frontend", "metrics"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("db", "api");
+        dag.add_edge_directed("db", "worker");
+        dag.add_edge_directed("api", "frontend");
+        dag.add_edge_directed("worker", "frontend");
+        dag.add_soft_edge("db", "metrics");
+        assert!(dag.set_edge_probability("db", "api", 0.5));
+        assert!(dag.set_edge_probability("db", "worker", 0.5));
+        assert!(dag.set_edge_probability("worker", "frontend", 0.4));
+        assert!(!dag.set_edge_probability("api", "db", 0.1));
+
+        let reach = dag.propagate_probability(&["db"]).unwrap();
+        assert_eq!(reach.keys().collect::<Vec<_>>(), vec!["api", "db", "frontend", "worker"]);
+        assert_eq!(reach["api"], 0.5);
+        assert!((reach["frontend"] - (1.0 - 0.5 * 0.8)).abs() < 1e-9);
+        assert_eq!(dag.propagate_probability(&["gone"]), Err(DagError::NodeNotFound("gone".to_string())));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Sources,
+    Sinks,
+}
+
+impl Dag {
+    // One-mode projection of a two-layer graph: nodes of `onto` are joined
+    // when they share a neighbour in the other layer. For every shared
+    // neighbour the weight is folded as `combine(acc, weight_a, weight_b)`
+    // starting from 0. Payloads can't be copied, so projected nodes carry `()`,
+    // and each edge runs from the smaller key to the larger one.
+    pub fn project<F>(&self, onto: Layer, mut combine: F) -> Result<Dag, DagError> where F: FnMut(i32, i32, i32) -> i32 {
+        let topology = self.topology();
+        let in_degrees = topology.in_degrees();
+        for from_key in topology.keys() {
+            for (to_key, _) in topology.successors(from_key) {
+                if in_degrees[from_key] != 0 || !topology.successors(to_key).is_empty() {
+                    return Err(DagError::NotBipartite { from: from_key.clone(), to: to_key.clone() });
+                }
+            }
+        }
+
+        let mut projected = Dag::new();
+        let members: Vec<&String> = topology.keys()
+            .filter(|key| match onto {
+                Layer::Sources => in_degrees[*key] == 0,
+                Layer::Sinks => topology.successors(key).is_empty(),
+            })
+            .collect();
+        for key in members.iter() {
+            projected.add(key, ());
+        }
+
+        let neighbourhoods: BTreeMap<String, Vec<(String, i32)>> = match onto {
+            Layer::Sources => topology.predecessors(),
+            Layer::Sinks => topology.keys().map(|key| (key.clone(), topology.successors(key).to_vec())).collect(),
+        };
+        let mut weights: BTreeMap<(String, String), i32> = BTreeMap::new();
+        for members in neighbourhoods.values() {
+            for (i, (a, weight_a)) in members.iter().enumerate() {
+                for (b, weight_b) in members.iter().skip(i + 1) {
+                    let (pair, weights_in_order) = if a < b {
+                        ((a.clone(), b.clone()), (*weight_a, *weight_b))
+                    } else {
+                        ((b.clone(), a.clone()), (*weight_b, *weight_a))
+                    };
+                    let acc = weights.entry(pair).or_insert(0);
+                    *acc = combine(*acc, weights_in_order.0, weights_in_order.1);
+                }
+            }
+        }
+        for ((a, b), weight) in weights {
+            projected.link(&a, &b, weight);
+        }
+        Ok(projected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn purchases() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["alice", "bob", "carol", "book", "lamp"] {
+            dag.add(key, key);
+        }
+        dag.link("alice", "book", 1);
+        dag.link("bob", "book", 3);
+        dag.link("bob", "lamp", 1);
+        dag.link("carol", "lamp", 2);
+        dag.link("alice", "lamp", 1);
+        dag
+    }
+
+    #[test]
+    fn users_projected_by_shared_items() {
+        let projected = purchases().project(Layer::Sources, |acc, _, _| acc + 1).unwrap();
+        assert_eq!(projected.edge_weight("alice", "bob"), Some(2));
+        assert_eq!(projected.edge_weight("alice", "carol"), Some(1));
+        assert!(projected.get("book").is_none());
+    }
+
+    #[test]
+    fn items_projected_with_custom_weights() {
+        let projected = purchases().project(Layer::Sinks, |acc, a, b| acc + a.min(b)).unwrap();
+        assert_eq!(projected.edge_weight("book", "lamp"), Some(2));
+        assert_eq!(projected.node_count(), 2);
+    }
+
+    #[test]
+    fn deeper_graphs_are_rejected() {
+        let mut dag = purchases();
+        dag.add("shelf", "shelf");
+        dag.link("book", "shelf", 1);
+        assert!(matches!(dag.project(Layer::Sources, |acc, _, _| acc), Err(DagError::NotBipartite { .. })));
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::SystemTime;
+
+use crate::Dag;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub actor: String,
+    pub reason: String,
+    pub timestamp: SystemTime,
+}
+
+impl Provenance {
+    pub fn new(actor: &str, reason: &str) -> Provenance {
+        Provenance {
+            actor: actor.to_string(),
+            reason: reason.to_string(),
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    AddNode,
+    UpdateNode,
+    RemoveNode,
+    AddEdge { from: String, to: String },
+    RemoveEdge { from: String, to: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceRecord {
+    pub mutation: Mutation,
+    pub provenance: Provenance,
+}
+
+impl Dag {
+    // Every mutation made while a context is set is recorded against it.
+    // Records outlive the nodes they describe.
+    pub fn set_provenance(&mut self, provenance: Option<Provenance>) {
+        self.provenance_context = provenance;
+    }
+
+    pub fn provenance(&self, key: &str) -> &[ProvenanceRecord] {
+        self.provenance.get(self.resolve_key(key).as_ref()).map(|records| records.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn edge_provenance(&self, from_key: &str, to_key: &str) -> Vec<&ProvenanceRecord> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        self.provenance(from_key).iter()
+            .filter(|record| match &record.mutation {
+                Mutation::AddEdge { from, to } | Mutation::RemoveEdge { from, to } => from == from_key && to == to_key,
+                _ => false,
+            })
+            .collect()
+    }
+
+    pub(crate) fn record(&mut self, keys: &[&str], mutation: Mutation) {
+        // An attached store needs to see every mutation, tracked or not.
+        self.register_hydrated();
+        self.mark_stored_dirty(keys);
+        if mutation != Mutation::UpdateNode {
+            self.reach_index = None;
+            self.shape.set(None);
+        }
+        self.evict_queries(keys, &mutation);
+        if let Some(provenance) = &self.provenance_context {
+            for key in keys {
+                self.provenance.entry(key.to_string()).or_default().push(ProvenanceRecord {
+                    mutation: mutation.clone(),
+                    provenance: provenance.clone(),
+                });
+            }
+        }
+        if self.invariant_checks {
+            self.debug_assert_invariants();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutations_record_active_provenance() {
+        let mut dag = Dag::new();
+        dag.add("untracked", 0);
+        dag.set_provenance(Some(Provenance::new("ingest", "batch-42")));
+        dag.add("A", 1);
+        dag.add_edge_directed("A", "untracked");
+        dag.set_provenance(None);
+        dag.update("A", 2);
+
+        assert!(dag.provenance("untracked").iter().all(|record| record.mutation != Mutation::AddNode));
+        let records = dag.provenance("A");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].mutation, Mutation::AddNode);
+        assert_eq!(records[1].provenance.reason, "batch-42");
+
+        let edge = dag.edge_provenance("A", "untracked");
+        assert_eq!(edge.len(), 1);
+        assert_eq!(edge[0].provenance.actor, "ingest");
+    }
+}
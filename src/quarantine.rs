@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::topology::Topology;
+use crate::{Ctx, Dag, DagError, DispatchId};
+
+// How a fallible run went. Failed nodes and everything below them stay
+// invalidated; `blocked` is what never ran because an input failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchOutcome<E> {
+    pub id: DispatchId,
+    pub failed: BTreeMap<String, E>,
+    pub blocked: Vec<String>,
+}
+
+impl<E> DispatchOutcome<E> {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Dag {
+    // As `dispatch_with_ctx`, for callbacks that can fail. Nodes that fail
+    // are quarantined until `redispatch_failed` or a full dispatch runs them.
+    pub fn try_dispatch<E, F>(&mut self, callback: F) -> Result<DispatchOutcome<E>, DagError>
+        where F: FnMut(&Ctx) -> Result<(), E> {
+        let region = self.dirty_region()?;
+        self.run_fallible(region, callback)
+    }
+
+    // Retries the nodes that failed last time and what they blocked, leaving
+    // the rest of the dirty region for a later dispatch.
+    pub fn redispatch_failed<E, F>(&mut self, callback: F) -> Result<DispatchOutcome<E>, DagError>
+        where F: FnMut(&Ctx) -> Result<(), E> {
+        let region = self.region_below(self.quarantine.iter())?;
+        self.run_fallible(region, callback)
+    }
+
+    pub fn quarantined(&self) -> Vec<String> {
+        self.quarantine.iter().cloned().collect()
+    }
+
+    fn run_fallible<E, F>(&mut self, region: Topology, mut callback: F) -> Result<DispatchOutcome<E>, DagError>
+        where F: FnMut(&Ctx) -> Result<(), E> {
+        let order = self.ordered(&region)?;
+        let region_predecessors = region.predecessors();
+        let predecessors = self.topology().predecessors();
+        let id = self.next_dispatch_id();
+        let mut failed: BTreeMap<String, E> = BTreeMap::new();
+        let mut blocked: BTreeSet<String> = BTreeSet::new();
+        let mut dispatched: HashSet<String> = HashSet::new();
+        for key in order {
+            let upstream_failed = region_predecessors.get(&key).into_iter().flatten()
+                .filter(|(input, _)| !region.is_soft(input, &key))
+                .any(|(input, _)| failed.contains_key(input) || blocked.contains(input));
+            if upstream_failed {
+                blocked.insert(key);
+                continue;
+            }
+            let Some(ctx) = self.context(&key, id, &predecessors) else { continue };
+            match callback(&ctx) {
+                Ok(()) => {
+                    dispatched.insert(key);
+                }
+                Err(err) => {
+                    failed.insert(key, err);
+                }
+            }
+        }
+        let ran: Vec<String> = dispatched.iter().cloned().collect();
+        self.mark_dispatched(dispatched, id);
+        for key in ran.iter() {
+            self.invalidated.remove(key);
+            self.priorities.remove(key);
+        }
+        for key in failed.keys().chain(blocked.iter()) {
+            self.invalidated.insert(key.clone());
+        }
+        self.quarantine = failed.keys().cloned().collect();
+        Ok(DispatchOutcome { id, failed, blocked: blocked.into_iter().collect() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_failed_subgraph_runs_again() {
+        let mut dag = Dag::new();
+        for key in ["fetch", "parse", "report", "audit"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("fetch", "parse");
+        dag.add_edge_directed("parse", "report");
+        dag.add_edge_directed("fetch", "audit");
+        dag.update("fetch", ());
+
+        let outcome = dag.try_dispatch(|ctx| if ctx.key() == "parse" { Err("malformed") } else { Ok(()) }).unwrap();
+        assert_eq!(outcome.failed.into_iter().collect::<Vec<_>>(), vec![("parse".to_string(), "malformed")]);
+        assert_eq!(outcome.blocked, vec!["report".to_string()]);
+        assert_eq!(dag.quarantined(), vec!["parse".to_string()]);
+        assert_eq!(dag.last_dispatched("audit"), Some(outcome.id));
+
+        let mut seen = vec![];
+        let retry = dag.redispatch_failed(|ctx| {
+            seen.push(ctx.key().to_string());
+            Ok::<(), ()>(())
+        }).unwrap();
+        assert!(retry.is_success());
+        assert_eq!(seen, vec!["parse", "report"]);
+        assert!(dag.quarantined().is_empty());
+        assert!(dag.invalidated.is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use crate::{Dag, DagError};
+
+pub type Layout = BTreeMap<String, (usize, usize)>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub deadline: Option<Instant>,
+    pub max_steps: Option<usize>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Budget {
+        Budget::default()
+    }
+
+    pub fn timeout(timeout: Duration) -> Budget {
+        Budget { deadline: Some(Instant::now() + timeout), max_steps: None }
+    }
+
+    pub fn steps(max_steps: usize) -> Budget {
+        Budget { deadline: None, max_steps: Some(max_steps) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bounded<T> {
+    pub value: T,
+    pub truncated: bool,
+}
+
+// Counts units of work against a budget. The clock is only read every few
+// steps to keep the overhead of checking out of the hot loops.
+pub(crate) struct Meter {
+    budget: Budget,
+    steps: usize,
+    exhausted: bool,
+}
+
+impl Meter {
+    const CLOCK_INTERVAL: usize = 64;
+
+    pub(crate) fn new(budget: Budget) -> Meter {
+        Meter { budget, steps: 0, exhausted: false }
+    }
+
+    pub(crate) fn step(&mut self) -> bool {
+        if self.exhausted {
+            return false;
+        }
+        self.steps += 1;
+        let over_steps = self.budget.max_steps.is_some_and(|max_steps| self.steps > max_steps);
+        let over_time = self.steps % Self::CLOCK_INTERVAL == 1
+            && self.budget.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        self.exhausted = over_steps || over_time;
+        !self.exhausted
+    }
+
+    pub(crate) fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+impl Dag {
+    pub fn all_paths(&self, from_key: &str, to_key: &str, budget: Budget) -> Result<Bounded<Vec<Vec<String>>>, DagError> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        for key in [from_key, to_key] {
+            if self.get(key).is_none() {
+                return Err(DagError::NodeNotFound(key.to_string()));
+            }
+        }
+        let topology = self.topology();
+        topology.topological_order()?;
+        let mut meter = Meter::new(budget);
+        let mut paths = vec![];
+        // Depth-first with an explicit stack of (path, next successor index).
+        let mut stack: Vec<(String, usize)> = vec![(from_key.to_string(), 0)];
+        while let Some((key, index)) = stack.last().cloned() {
+            if !meter.step() {
+                break;
+            }
+            if key == to_key {
+                paths.push(stack.iter().map(|(key, _)| key.clone()).collect());
+                stack.pop();
+                continue;
+            }
+            match topology.successors(&key).get(index) {
+                Some((next, _)) => {
+                    stack.last_mut().expect("Path stack emptied").1 += 1;
+                    stack.push((next.clone(), 0));
+                },
+                None => {
+                    stack.pop();
+                },
+            }
+        }
+        Ok(Bounded { value: paths, truncated: meter.exhausted() })
+    }
+
+    // Descendants of every node. When truncated, the map only holds the nodes
+    // whose closure was completed.
+    pub fn transitive_closure(&self, budget: Budget) -> Result<Bounded<BTreeMap<String, BTreeSet<String>>>, DagError> {
+        let topology = self.topology();
+        let mut meter = Meter::new(budget);
+        let mut closure: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for key in topology.topological_order()?.into_iter().rev() {
+            let mut descendants = BTreeSet::new();
+            for (next, _) in topology.successors(&key) {
+                if !meter.step() {
+                    return Ok(Bounded { value: closure, truncated: true });
+                }
+                descendants.insert(next.clone());
+                descendants.extend(closure[next].iter().cloned());
+            }
+            closure.insert(key, descendants);
+        }
+        Ok(Bounded { value: closure, truncated: false })
+    }
+
+    // Layered drawing coordinates as (layer, position within layer). Layers
+    // come from `layer_assignment`; the budget bounds the barycenter sweeps
+    // spent reducing edge crossings, so a truncated layout is still valid.
+    pub fn layout(&self, budget: Budget) -> Result<Bounded<Layout>, DagError> {
+        const MAX_SWEEPS: usize = 24;
+        let mut layers = self.layer_assignment(None)?;
+        let topology = self.topology();
+        let predecessors = topology.predecessors();
+        let mut meter = Meter::new(budget);
+        let position_map = |layers: &Vec<Vec<String>>| -> Layout {
+            layers.iter().enumerate()
+                .flat_map(|(layer, keys)| keys.iter().enumerate().map(move |(position, key)| (key.clone(), (layer, position))))
+                .collect()
+        };
+
+        'sweeps: for sweep in 0..MAX_SWEEPS {
+            let mut changed = false;
+            let downward = sweep % 2 == 0;
+            let order: Vec<usize> = if downward { (1..layers.len()).collect() } else { (0..layers.len().saturating_sub(1)).rev().collect() };
+            for layer in order {
+                let positions = position_map(&layers);
+                let mut scored = vec![];
+                for (index, key) in layers[layer].iter().enumerate() {
+                    if !meter.step() {
+                        break 'sweeps;
+                    }
+                    let neighbours: Vec<usize> = if downward {
+                        predecessors[key].iter().map(|(other, _)| positions[other].1).collect()
+                    } else {
+                        topology.successors(key).iter().map(|(other, _)| positions[other].1).collect()
+                    };
+                    let barycenter = if neighbours.is_empty() {
+                        index as f64
+                    } else {
+                        neighbours.iter().sum::<usize>() as f64 / neighbours.len() as f64
+                    };
+                    scored.push((barycenter, index, key.clone()));
+                }
+                scored.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                let reordered: Vec<String> = scored.into_iter().map(|(_, _, key)| key).collect();
+                changed |= reordered != layers[layer];
+                layers[layer] = reordered;
+            }
+            if !changed && sweep > 0 {
+                break;
+            }
+        }
+        Ok(Bounded { value: position_map(&layers), truncated: meter.exhausted() })
+    }
+
+    // Sets of up to `max_size` nodes none of which reaches another, in key
+    // order, each set listed before the larger sets extending it. Stops at
+    // `limit` sets, marking the result truncated if any were left.
+    pub fn antichains(&self, max_size: usize, limit: usize) -> Result<Bounded<Vec<Vec<String>>>, DagError> {
+        let closure = self.transitive_closure(Budget::unlimited())?.value;
+        let keys: Vec<&String> = closure.keys().collect();
+        let comparable = |a: usize, b: usize| closure[keys[a]].contains(keys[b]) || closure[keys[b]].contains(keys[a]);
+        let mut found = vec![];
+        let mut chosen = vec![];
+        let truncated = extend_antichain(&mut chosen, 0, keys.len(), max_size, limit, &comparable, &mut found);
+        let value = found.into_iter()
+            .map(|set: Vec<usize>| set.into_iter().map(|index| keys[index].clone()).collect())
+            .collect();
+        Ok(Bounded { value, truncated })
+    }
+}
+
+// Returns true once `limit` is reached with more sets still to come.
+fn extend_antichain(chosen: &mut Vec<usize>, start: usize, count: usize, max_size: usize, limit: usize,
+    comparable: &dyn Fn(usize, usize) -> bool, found: &mut Vec<Vec<usize>>) -> bool {
+    if chosen.len() >= max_size {
+        return false;
+    }
+    for candidate in start..count {
+        if chosen.iter().any(|member| comparable(*member, candidate)) {
+            continue;
+        }
+        if found.len() == limit {
+            return true;
+        }
+        chosen.push(candidate);
+        found.push(chosen.clone());
+        if extend_antichain(chosen, candidate + 1, count, max_size, limit, comparable, found) {
+            return true;
+        }
+        chosen.pop();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two parallel ladders of `depth` rungs, giving 2^depth paths.
+    fn ladder(depth: usize) -> Dag {
+        let mut dag = Dag::new();
+        dag.add("n0", 0);
+        for i in 1..=depth {
+            for key in [format!("a{}", i), format!("b{}", i), format!("n{}", i)] {
+                dag.add(&key, i);
+            }
+            dag.add_edge_directed(&format!("n{}", i - 1), &format!("a{}", i));
+            dag.add_edge_directed(&format!("n{}", i - 1), &format!("b{}", i));
+            dag.add_edge_directed(&format!("a{}", i), &format!("n{}", i));
+            dag.add_edge_directed(&format!("b{}", i), &format!("n{}", i));
+        }
+        dag
+    }
+
+    #[test]
+    fn all_paths_within_budget() {
+        let dag = ladder(3);
+        let paths = dag.all_paths("n0", "n3", Budget::unlimited()).unwrap();
+        assert_eq!(paths.value.len(), 8);
+        assert!(!paths.truncated);
+
+        let partial = dag.all_paths("n0", "n3", Budget::steps(10)).unwrap();
+        assert!(partial.truncated);
+        assert!(partial.value.len() < 8);
+    }
+
+    #[test]
+    fn closure_truncates_cleanly() {
+        let dag = ladder(2);
+        let full = dag.transitive_closure(Budget::unlimited()).unwrap();
+        assert_eq!(full.value["n0"].len(), 6);
+        let partial = dag.transitive_closure(Budget::steps(2)).unwrap();
+        assert!(partial.truncated);
+        assert!(partial.value.len() < full.value.len());
+    }
+
+    #[test]
+    fn layout_places_every_node() {
+        let dag = ladder(2);
+        let layout = dag.layout(Budget::unlimited()).unwrap();
+        assert_eq!(layout.value.len(), 7);
+        assert_eq!(layout.value["n0"], (0, 0));
+        assert_eq!(layout.value["n2"].0, 4);
+        assert!(dag.layout(Budget::steps(1)).unwrap().truncated);
+    }
+
+    #[test]
+    fn antichains_stop_at_the_limit() {
+        let dag = ladder(1);
+        let all = dag.antichains(2, 100).unwrap();
+        assert!(!all.truncated);
+        assert_eq!(all.value, vec![vec!["a1"], vec!["a1", "b1"], vec!["b1"], vec!["n0"], vec!["n1"]]);
+        let first = dag.antichains(2, 2).unwrap();
+        assert!(first.truncated);
+        assert_eq!(first.value, vec![vec!["a1"], vec!["a1", "b1"]]);
+        assert_eq!(dag.antichains(1, 100).unwrap().value.len(), 4);
+        assert!(dag.antichains(0, 100).unwrap().value.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::provenance::Mutation;
+use crate::{Dag, Traverser};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+}
+
+type Path = Option<Vec<String>>;
+
+// Memoized `topological_order`, `descendants` and `shortest_path` results.
+// Each mutation evicts only the entries it can have changed: adding or
+// removing an edge `from -> to` touches the descendant sets that hold
+// `from`, and the paths that start upstream of `from` or run along the
+// edge; payload updates evict nothing.
+#[derive(Default)]
+pub(crate) struct QueryCache {
+    order: Option<Vec<String>>,
+    descendants: HashMap<String, BTreeSet<String>>,
+    paths: HashMap<(String, String), Path>,
+    stats: QueryCacheStats,
+}
+
+impl QueryCache {
+    fn lookup<T: Clone>(stats: &mut QueryCacheStats, found: Option<&T>) -> Option<T> {
+        match found {
+            Some(value) => {
+                stats.hits += 1;
+                Some(value.clone())
+            },
+            None => {
+                stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    fn evict_descendants(&mut self, evict: impl Fn(&String, &BTreeSet<String>) -> bool) {
+        let before = self.descendants.len();
+        self.descendants.retain(|key, reached| !evict(key, reached));
+        self.stats.evictions += before - self.descendants.len();
+    }
+
+    fn evict_paths(&mut self, evict: impl Fn(&(String, String), &Path) -> bool) {
+        let before = self.paths.len();
+        self.paths.retain(|ends, path| !evict(ends, path));
+        self.stats.evictions += before - self.paths.len();
+    }
+
+    fn evict_order(&mut self) {
+        if self.order.take().is_some() {
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+impl Dag {
+    // Off by default. Turning it off drops everything cached.
+    pub fn set_query_cache(&mut self, enabled: bool) {
+        self.query_cache = enabled.then(|| RefCell::new(QueryCache::default()));
+    }
+
+    pub fn query_cache_stats(&self) -> Option<QueryCacheStats> {
+        self.query_cache.as_ref().map(|cache| cache.borrow().stats)
+    }
+
+    // Everything reachable from `key` along any live edge, sorted; empty
+    // if `key` isn't in the graph.
+    pub fn descendants(&self, key: &str) -> Vec<String> {
+        let key = self.resolve_key(key).into_owned();
+        if let Some(cache) = self.query_cache.as_ref() {
+            let mut cache = cache.borrow_mut();
+            let QueryCache { descendants, stats, .. } = &mut *cache;
+            if let Some(reached) = QueryCache::lookup(stats, descendants.get(&key)) {
+                return reached.into_iter().collect();
+            }
+        }
+        let mut reached = BTreeSet::new();
+        let found = Traverser::new(self).walk(self, &key, |node| {
+            reached.insert(node.key.clone());
+        });
+        reached.remove(&key);
+        if let (Some(cache), Some(_)) = (self.query_cache.as_ref(), found) {
+            cache.borrow_mut().descendants.insert(key, reached.clone());
+        }
+        reached.into_iter().collect()
+    }
+
+    pub(crate) fn cached_order(&self) -> Option<Vec<String>> {
+        let mut cache = self.query_cache.as_ref()?.borrow_mut();
+        let QueryCache { order, stats, .. } = &mut *cache;
+        QueryCache::lookup(stats, order.as_ref())
+    }
+
+    pub(crate) fn store_order(&self, order: &[String]) {
+        if let Some(cache) = self.query_cache.as_ref() {
+            cache.borrow_mut().order = Some(order.to_vec());
+        }
+    }
+
+    pub(crate) fn forget_cached_order(&self) {
+        if let Some(cache) = self.query_cache.as_ref() {
+            cache.borrow_mut().evict_order();
+        }
+    }
+
+    pub(crate) fn cached_path(&self, from_key: &str, to_key: &str) -> Option<Path> {
+        let mut cache = self.query_cache.as_ref()?.borrow_mut();
+        let QueryCache { paths, stats, .. } = &mut *cache;
+        QueryCache::lookup(stats, paths.get(&(from_key.to_string(), to_key.to_string())))
+    }
+
+    pub(crate) fn store_path(&self, from_key: &str, to_key: &str, path: &Path) {
+        if let Some(cache) = self.query_cache.as_ref() {
+            cache.borrow_mut().paths.insert((from_key.to_string(), to_key.to_string()), path.clone());
+        }
+    }
+
+    // Called after the mutation has been applied.
+    pub(crate) fn evict_queries(&self, keys: &[&str], mutation: &Mutation) {
+        let Some(cache) = self.query_cache.as_ref() else { return };
+        match mutation {
+            Mutation::UpdateNode => {},
+            // A new node has no edges yet, so only its place in the order
+            // and queries that named it while it was missing change.
+            Mutation::AddNode => {
+                let mut cache = cache.borrow_mut();
+                cache.evict_order();
+                cache.evict_paths(|(from, to), _| keys.contains(&from.as_str()) || keys.contains(&to.as_str()));
+            },
+            Mutation::RemoveNode => {
+                let mut cache = cache.borrow_mut();
+                cache.evict_order();
+                cache.evict_descendants(|key, reached| keys.iter().any(|removed| key == removed || reached.contains(*removed)));
+                cache.evict_paths(|_, path| path.as_ref().is_some_and(|path| path.iter().any(|key| keys.contains(&key.as_str()))));
+            },
+            Mutation::AddEdge { from, .. } => {
+                let upstream: Vec<(String, String)> = cache.borrow().paths.keys()
+                    .filter(|(start, _)| start == from || self.reaches(start, from))
+                    .cloned()
+                    .collect();
+                let mut cache = cache.borrow_mut();
+                cache.evict_order();
+                cache.evict_descendants(|key, reached| key == from || reached.contains(from));
+                cache.evict_paths(|ends, _| upstream.contains(ends));
+            },
+            Mutation::RemoveEdge { from, to } => {
+                let mut cache = cache.borrow_mut();
+                cache.evict_order();
+                cache.evict_descendants(|key, reached| key == from || reached.contains(from));
+                cache.evict_paths(|_, path| path.as_ref().is_some_and(|path| {
+                    path.windows(2).any(|step| step[0] == *from && step[1] == *to)
+                }));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutations_evict_only_what_they_touch() {
+        let mut dag = Dag::new();
+        for key in ["a", "b", "c", "x", "y"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("a", "b");
+        dag.add_edge_directed("b", "c");
+        dag.add_edge_directed("x", "y");
+        dag.set_query_cache(true);
+
+        assert_eq!(dag.descendants("a"), vec!["b", "c"]);
+        assert_eq!(dag.descendants("x"), vec!["y"]);
+        assert_eq!(dag.shortest_path("a", "c").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(dag.shortest_path("x", "c"), None);
+        dag.topological_order().unwrap();
+        dag.update("b", ());
+        dag.descendants("a");
+        dag.topological_order().unwrap();
+        assert_eq!(dag.query_cache_stats().unwrap(), QueryCacheStats { hits: 2, misses: 5, evictions: 0 });
+
+        dag.add_edge_directed("y", "c");
+        assert_eq!(dag.query_cache_stats().unwrap().evictions, 3);
+        assert_eq!(dag.descendants("a"), vec!["b", "c"]);
+        assert_eq!(dag.descendants("x"), vec!["c", "y"]);
+        assert_eq!(dag.shortest_path("x", "c").unwrap(), vec!["x", "y", "c"]);
+
+        dag.unlink("a", "b");
+        assert_eq!(dag.descendants("a"), Vec::<String>::new());
+        assert_eq!(dag.shortest_path("a", "c"), None);
+        assert_eq!(dag.shortest_path("x", "c").unwrap(), vec!["x", "y", "c"]);
+        assert_eq!(dag.query_cache_stats().unwrap().hits, 4);
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::Debug;
+
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub max_nodes: Option<usize>,
+    pub max_edges: Option<usize>,
+    pub max_out_degree: Option<usize>,
+    // Per walk of a dispatch region: nodes reached, and nodes queued to
+    // visit at once.
+    pub max_visited: Option<usize>,
+    pub max_frontier: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Nodes,
+    Edges,
+    OutDegree,
+    Visited,
+    Frontier,
+}
+
+impl Dag {
+    pub fn with_limits(limits: Limits) -> Dag {
+        let mut dag = Dag::new();
+        dag.limits = limits;
+        dag
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    // Counts stored edges, including ones left dangling by removals until
+    // they are compacted away, since those still occupy memory.
+    pub fn edge_count(&self) -> usize {
+        self.nodes.borrow().values().map(|node| node.borrow().edges.len()).sum()
+    }
+
+    pub fn try_add<T>(&mut self, key: &str, data: T) -> Result<(), DagError> where T: Debug + 'static {
+        let key: &str = &self.resolve_key(key);
+        if self.get(key).is_some() && !self.is_placeholder(key) {
+            return Err(DagError::DuplicateNode(key.to_string()));
+        }
+        self.check_node_quota(&[key])?;
+        self.insert_boxed(key, Box::new(data));
+        Ok(())
+    }
+
+    // Rejects an edge that would close a cycle, as well as one over quota.
+    pub fn try_add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        self.check_new_edge(from_key, to_key)?;
+        if let Some(path) = self.try_topology("add_edge")?.path(to_key, from_key) {
+            return Err(DagError::WouldCreateCycle { from: from_key.to_string(), to: to_key.to_string(), path });
+        }
+        self.try_link(from_key, to_key, 1)
+    }
+
+    // The quota-checked path of the unchecked `add_edge_directed`, which
+    // like the original `add_edge` leaves cycles to `find_cycle`.
+    pub(crate) fn try_add_edge_allowing_cycles(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        self.check_new_edge(from_key, to_key)?;
+        self.try_link(from_key, to_key, 1)
+    }
+
+    fn check_new_edge(&self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        for key in [from_key, to_key] {
+            if self.get(key).is_none() {
+                return Err(DagError::NodeNotFound(key.to_string()));
+            }
+        }
+        self.check_edge_quota(&[from_key])
+    }
+
+    pub(crate) fn check_node_quota(&self, new_keys: &[&str]) -> Result<(), DagError> {
+        if let Some(maximum) = self.limits.max_nodes {
+            let added: Vec<&str> = new_keys.iter().copied().filter(|key| !self.nodes.borrow().contains_key(*key)).collect();
+            let room = maximum.saturating_sub(self.nodes.borrow().len());
+            if let Some(key) = added.get(room) {
+                return Err(DagError::QuotaExceeded { limit: Limit::Nodes, maximum, key: key.to_string() });
+            }
+        }
+        Ok(())
+    }
+
+    // One entry per edge about to be stored on `from_keys[i]`.
+    pub(crate) fn check_edge_quota(&self, from_keys: &[&str]) -> Result<(), DagError> {
+        if let Some(maximum) = self.limits.max_edges {
+            let room = maximum.saturating_sub(self.edge_count());
+            if let Some(key) = from_keys.get(room) {
+                return Err(DagError::QuotaExceeded { limit: Limit::Edges, maximum, key: key.to_string() });
+            }
+        }
+        if let Some(maximum) = self.limits.max_out_degree {
+            for key in from_keys {
+                let existing = self.get(key).map(|node| node.borrow().edges.len()).unwrap_or(0);
+                let added = from_keys.iter().filter(|other| *other == key).count();
+                if existing + added > maximum {
+                    return Err(DagError::QuotaExceeded { limit: Limit::OutDegree, maximum, key: key.to_string() });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Called once per node a walk reaches, with how many it has reached so
+    // far and how many are still queued, so a runaway walk stops early.
+    pub(crate) fn check_walk(&self, key: &str, visited: usize, frontier: usize) -> Result<(), DagError> {
+        if let Some(maximum) = self.limits.max_visited.filter(|maximum| visited > *maximum) {
+            return Err(DagError::QuotaExceeded { limit: Limit::Visited, maximum, key: key.to_string() });
+        }
+        if let Some(maximum) = self.limits.max_frontier.filter(|maximum| frontier > *maximum) {
+            return Err(DagError::QuotaExceeded { limit: Limit::Frontier, maximum, key: key.to_string() });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_quota_rejects_new_keys_only() {
+        let mut dag = Dag::with_limits(Limits { max_nodes: Some(1), ..Limits::default() });
+        dag.try_add("A", 1).unwrap();
+        assert_eq!(dag.try_add("A", 2), Err(DagError::DuplicateNode("A".to_string())));
+        dag.add_or_replace("A", 2);
+        assert_eq!(
+            dag.try_add("B", 3),
+            Err(DagError::QuotaExceeded { limit: Limit::Nodes, maximum: 1, key: "B".to_string() })
+        );
+        assert!(dag.get("B").is_none());
+    }
+
+    #[test]
+    fn edge_and_degree_quotas() {
+        let mut dag = Dag::with_limits(Limits { max_edges: Some(2), max_out_degree: Some(1), ..Limits::default() });
+        for key in ["A", "B", "C"] {
+            dag.add(key, key);
+        }
+        dag.try_add_edge("A", "B").unwrap();
+        assert!(matches!(dag.try_add_edge("A", "C"), Err(DagError::QuotaExceeded { limit: Limit::OutDegree, .. })));
+        dag.try_add_edge("B", "C").unwrap();
+        assert!(matches!(dag.try_add_edge("C", "A"), Err(DagError::QuotaExceeded { limit: Limit::Edges, .. })));
+        assert_eq!(dag.edge_count(), 2);
+    }
+
+    #[test]
+    fn checked_edges_reject_cycles() {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C"] {
+            dag.add(key, key);
+        }
+        dag.try_add_edge("A", "B").unwrap();
+        dag.try_add_edge("B", "C").unwrap();
+        assert_eq!(dag.try_add_edge("C", "A"), Err(DagError::WouldCreateCycle {
+            from: "C".to_string(),
+            to: "A".to_string(),
+            path: vec!["A".to_string(), "B".to_string(), "C".to_string()],
+        }));
+        assert!(dag.successors("C").is_empty());
+        assert!(dag.find_cycle().is_none());
+    }
+
+    #[test]
+    fn walks_stop_at_their_limits() {
+        let mut dag = Dag::with_limits(Limits { max_visited: Some(3), ..Limits::default() });
+        dag.add("root", ());
+        for i in 0..4 {
+            dag.add(&format!("leaf-{}", i), ()).depends_on("root");
+        }
+        dag.update("root", ());
+        let mut seen = 0;
+        let err = dag.dispatch_with_ctx(|_| seen += 1).unwrap_err();
+        assert!(matches!(err.root(), DagError::QuotaExceeded { limit: Limit::Visited, maximum: 3, .. }));
+        assert_eq!(seen, 0);
+        assert!(err.to_string().contains("at most 3 nodes visited in one walk"));
+
+        dag.set_limits(Limits { max_frontier: Some(2), ..Limits::default() });
+        assert!(matches!(dag.dispatch_with_ctx(|_| ()).unwrap_err().root(), DagError::QuotaExceeded { limit: Limit::Frontier, .. }));
+        dag.set_limits(Limits::default());
+        dag.dispatch_with_ctx(|_| seen += 1).unwrap();
+        assert_eq!(seen, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quota exceeded")]
+    fn unchecked_add_panics_over_quota() {
+        let mut dag = Dag::with_limits(Limits { max_nodes: Some(0), ..Limits::default() });
+        dag.add("A", 1);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use crate::{ContentHash, Dag, DagError, DispatchId, NodeStrongRef};
+
+// What a node looked like when it was dispatched: its rendered payload and
+// the inputs feeding it, sorted by key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub key: String,
+    pub payload: String,
+    pub inputs: Vec<(String, i32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchTrace {
+    pub id: DispatchId,
+    pub steps: Vec<TraceStep>,
+}
+
+impl DispatchTrace {
+    // Equal digests mean two runs visited the same nodes in the same order
+    // with the same inputs.
+    pub fn digest(&self) -> ContentHash {
+        let mut hasher = Sha256::new();
+        let mut push = |field: &[u8]| {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field);
+        };
+        for step in self.steps.iter() {
+            push(step.key.as_bytes());
+            push(step.payload.as_bytes());
+            for (input, weight) in step.inputs.iter() {
+                push(input.as_bytes());
+                push(&weight.to_le_bytes());
+            }
+        }
+        ContentHash::from_bytes(hasher.finalize().into())
+    }
+}
+
+impl Dag {
+    // Same visiting order as `dispatch_with_id`, recording each step.
+    pub fn dispatch_traced<F>(&mut self, mut callback: F) -> DispatchTrace where F: FnMut(NodeStrongRef, DispatchId) {
+        let predecessors = self.topology().predecessors();
+        let mut steps = vec![];
+        let id = self.dispatch_with_id(|node, id| {
+            let (key, payload) = {
+                let borrowed = node.borrow();
+                (borrowed.key.clone(), format!("{:?}", borrowed.data))
+            };
+            let mut inputs = predecessors.get(&key).cloned().unwrap_or_default();
+            inputs.sort();
+            steps.push(TraceStep { key, payload, inputs });
+            callback(node, id);
+        });
+        DispatchTrace { id, steps }
+    }
+
+    // Visits the traced nodes again in exactly the recorded order. Each node
+    // is checked against its step first, and the replay stops at the first
+    // one whose payload or inputs no longer match.
+    pub fn replay_dispatch<F>(&self, trace: &DispatchTrace, mut callback: F) -> Result<(), DagError>
+        where F: FnMut(NodeStrongRef, &TraceStep) {
+        let predecessors = self.topology().predecessors();
+        let mut visited: HashSet<&str> = HashSet::new();
+        for step in trace.steps.iter() {
+            let node = self.get(&step.key).ok_or_else(|| DagError::NodeNotFound(step.key.clone()))?;
+            let payload = format!("{:?}", node.borrow().data);
+            let mut inputs = predecessors.get(&step.key).cloned().unwrap_or_default();
+            inputs.sort();
+            if payload != step.payload || inputs != step.inputs || !visited.insert(&step.key) {
+                return Err(DagError::ReplayDiverged(step.key.clone()));
+            }
+            callback(node, step);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("extract", 1);
+        dag.add("clean", 2);
+        dag.add("report", 3);
+        dag.add_edge_directed("extract", "clean");
+        dag.add_edge_directed("clean", "report");
+        dag.add_edge_directed("extract", "report");
+        dag.update("extract", 1);
+        dag
+    }
+
+    #[test]
+    fn replay_follows_the_recorded_order() {
+        let mut dag = pipeline();
+        let mut original = vec![];
+        let trace = dag.dispatch_traced(|node, _| original.push(node.borrow().key.clone()));
+        assert_eq!(trace.steps[2].inputs, vec![("clean".to_string(), 1), ("extract".to_string(), 1)]);
+
+        let mut replayed = vec![];
+        dag.replay_dispatch(&trace, |node, _| replayed.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(replayed, original);
+        assert_eq!(pipeline().dispatch_traced(|_, _| ()).digest(), trace.digest());
+    }
+
+    #[test]
+    fn replay_stops_where_inputs_changed() {
+        let mut dag = pipeline();
+        let trace = dag.dispatch_traced(|_, _| ());
+        dag.update("clean", 20);
+        let mut replayed = vec![];
+        let result = dag.replay_dispatch(&trace, |node, _| replayed.push(node.borrow().key.clone()));
+        assert_eq!(result, Err(DagError::ReplayDiverged("clean".to_string())));
+        assert_eq!(replayed, vec!["extract".to_string()]);
+    }
+}
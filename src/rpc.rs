@@ -0,0 +1,361 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::{Dag, DagError};
+
+// The operations a graph offers to other processes. `Dag` implements it
+// directly, and `RemoteDag` forwards every call to a `GraphServer`, so code
+// written against the trait works with either.
+pub trait GraphService {
+    fn add(&mut self, key: &str, payload: Value) -> Result<(), DagError>;
+    fn update(&mut self, key: &str, payload: Value) -> Result<(), DagError>;
+    fn remove(&mut self, key: &str) -> Result<bool, DagError>;
+    fn add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError>;
+    fn payload(&mut self, key: &str) -> Result<Option<String>, DagError>;
+    fn keys(&mut self) -> Result<Vec<String>, DagError>;
+    fn successors(&mut self, key: &str) -> Result<Vec<String>, DagError>;
+    fn dirty_order(&mut self) -> Result<Vec<String>, DagError>;
+    fn dispatch(&mut self) -> Result<Vec<String>, DagError>;
+}
+
+// Payloads sent over the wire. Renders as the JSON text it was sent as.
+#[derive(Clone, PartialEq)]
+pub struct JsonPayload(pub Value);
+
+impl fmt::Debug for JsonPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl GraphService for Dag {
+    fn add(&mut self, key: &str, payload: Value) -> Result<(), DagError> {
+        self.try_add(key, JsonPayload(payload))
+    }
+
+    fn update(&mut self, key: &str, payload: Value) -> Result<(), DagError> {
+        if self.get(key).is_none() {
+            return Err(DagError::NodeNotFound(key.to_string()));
+        }
+        Dag::update(self, key, JsonPayload(payload));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, DagError> {
+        Ok(Dag::remove(self, key))
+    }
+
+    fn add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        self.try_add_edge(from_key, to_key)
+    }
+
+    fn payload(&mut self, key: &str) -> Result<Option<String>, DagError> {
+        Ok(self.get(key).map(|node| format!("{:?}", node.borrow().data)))
+    }
+
+    fn keys(&mut self) -> Result<Vec<String>, DagError> {
+        Ok(self.index.iter().cloned().collect())
+    }
+
+    fn successors(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        if self.get(key).is_none() {
+            return Err(DagError::NodeNotFound(key.to_string()));
+        }
+        Ok(Dag::successors(self, key))
+    }
+
+    fn dirty_order(&mut self) -> Result<Vec<String>, DagError> {
+        Dag::dirty_order(self)
+    }
+
+    fn dispatch(&mut self) -> Result<Vec<String>, DagError> {
+        let mut visited = vec![];
+        self.dispatch_with_id(|node, _| visited.push(node.borrow().key.clone()));
+        Ok(visited)
+    }
+}
+
+fn error_object(err: &DagError) -> Value {
+    let data = match err.root() {
+        DagError::NodeNotFound(key) => json!({ "kind": "node_not_found", "key": key }),
+        DagError::DuplicateNode(key) => json!({ "kind": "duplicate_node", "key": key }),
+        DagError::WouldCreateCycle { from, to, path } => {
+            json!({ "kind": "would_create_cycle", "from": from, "to": to, "path": path })
+        },
+        _ => Value::Null,
+    };
+    json!({ "code": -32000, "message": err.to_string(), "data": data })
+}
+
+fn parse_error(error: &Value) -> DagError {
+    let text = |field: &str| error["data"][field].as_str().unwrap_or_default().to_string();
+    match error["data"]["kind"].as_str() {
+        Some("node_not_found") => DagError::NodeNotFound(text("key")),
+        Some("duplicate_node") => DagError::DuplicateNode(text("key")),
+        Some("would_create_cycle") => DagError::WouldCreateCycle {
+            from: text("from"),
+            to: text("to"),
+            path: serde_json::from_value(error["data"]["path"].clone()).unwrap_or_default(),
+        },
+        _ => DagError::Remote(error["message"].as_str().unwrap_or("malformed error").to_string()),
+    }
+}
+
+fn remote_error(err: impl fmt::Display) -> DagError {
+    DagError::Remote(err.to_string())
+}
+
+// Owns the authoritative graph and answers newline-delimited JSON-RPC 2.0
+// requests. `Dag` is single-threaded, so connections are multiplexed on the
+// calling thread rather than handed to workers.
+pub struct GraphServer {
+    dag: Dag,
+}
+
+struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl GraphServer {
+    pub fn new(dag: Dag) -> GraphServer {
+        GraphServer { dag }
+    }
+
+    pub fn dag(&self) -> &Dag {
+        &self.dag
+    }
+
+    pub fn into_dag(self) -> Dag {
+        self.dag
+    }
+
+    pub fn serve(&mut self, listener: TcpListener) -> Result<(), DagError> {
+        listener.set_nonblocking(true).map_err(remote_error)?;
+        let mut connections: Vec<Connection> = vec![];
+        loop {
+            let mut idle = true;
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true).map_err(remote_error)?;
+                    connections.push(Connection { stream, buffer: vec![] });
+                    idle = false;
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => (),
+                Err(err) => return Err(remote_error(err)),
+            }
+            connections.retain_mut(|connection| {
+                let (open, progressed) = self.poll(connection);
+                idle &= !progressed;
+                open
+            });
+            if idle {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    // Returns whether the connection is still open and whether it did any
+    // work.
+    fn poll(&mut self, connection: &mut Connection) -> (bool, bool) {
+        let mut chunk = [0u8; 4096];
+        let mut progressed = false;
+        loop {
+            match connection.stream.read(&mut chunk) {
+                Ok(0) => return (false, progressed),
+                Ok(read) => {
+                    connection.buffer.extend_from_slice(&chunk[..read]);
+                    progressed = true;
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return (false, progressed),
+            }
+        }
+        while let Some(end) = connection.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = connection.buffer.drain(..=end).collect();
+            let mut response = self.handle(&String::from_utf8_lossy(&line));
+            response.push('\n');
+            connection.stream.set_nonblocking(false).ok();
+            let written = connection.stream.write_all(response.as_bytes());
+            connection.stream.set_nonblocking(true).ok();
+            if written.is_err() {
+                return (false, progressed);
+            }
+        }
+        (true, progressed)
+    }
+
+    pub fn handle(&mut self, request: &str) -> String {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(err) => {
+                return json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": err.to_string() } })
+                    .to_string();
+            },
+        };
+        let id = request["id"].clone();
+        let method = request["method"].as_str().unwrap_or_default();
+        match self.call(method, &request["params"]) {
+            Ok(Ok(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Ok(Err(err)) => json!({ "jsonrpc": "2.0", "id": id, "error": error_object(&err) }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        }.to_string()
+    }
+
+    // The outer error is a protocol error object: an unknown method or
+    // malformed params. The inner one is the graph's own.
+    fn call(&mut self, method: &str, params: &Value) -> Result<Result<Value, DagError>, Value> {
+        // The first of `names` present must be a string; later ones are older
+        // spellings still accepted.
+        let text = |names: &[&str]| names.iter().find_map(|name| params.get(*name)).and_then(Value::as_str)
+            .ok_or_else(|| json!({ "code": -32602, "message": format!("invalid params: {} must be a string", names[0]) }));
+        Ok(match method {
+            "add" => GraphService::add(&mut self.dag, text(&["key"])?, params["payload"].clone()).map(|_| Value::Null),
+            "update" => GraphService::update(&mut self.dag, text(&["key"])?, params["payload"].clone()).map(|_| Value::Null),
+            "remove" => GraphService::remove(&mut self.dag, text(&["key"])?).map(Value::from),
+            "add_edge" => {
+                // `to_node_key`/`from_node_key` are the old, backwards names:
+                // `to_node_key` was the source.
+                let (from_key, to_key) = (text(&["from_key", "to_node_key"])?, text(&["to_key", "from_node_key"])?);
+                GraphService::add_edge(&mut self.dag, from_key, to_key).map(|_| Value::Null)
+            },
+            "payload" => GraphService::payload(&mut self.dag, text(&["key"])?).map(Value::from),
+            "keys" => GraphService::keys(&mut self.dag).map(Value::from),
+            "successors" => GraphService::successors(&mut self.dag, text(&["key"])?).map(Value::from),
+            "dirty_order" => GraphService::dirty_order(&mut self.dag).map(Value::from),
+            "dispatch" => GraphService::dispatch(&mut self.dag).map(Value::from),
+            method => return Err(json!({ "code": -32601, "message": format!("unknown method {}", method) })),
+        })
+    }
+}
+
+pub struct RemoteDag {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl RemoteDag {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> Result<RemoteDag, DagError> {
+        let writer = TcpStream::connect(address).map_err(remote_error)?;
+        let reader = BufReader::new(writer.try_clone().map_err(remote_error)?);
+        Ok(RemoteDag { reader, writer, next_id: 0 })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, DagError> {
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params });
+        writeln!(self.writer, "{}", request).map_err(remote_error)?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(remote_error)? == 0 {
+            return Err(DagError::Remote("server closed the connection".to_string()));
+        }
+        let mut response: Value = serde_json::from_str(&line).map_err(remote_error)?;
+        if response["id"] != json!(self.next_id) {
+            return Err(DagError::Remote("response does not match request".to_string()));
+        }
+        match response.get("error") {
+            Some(error) => Err(parse_error(error)),
+            None => Ok(response["result"].take()),
+        }
+    }
+
+    fn call_keys(&mut self, method: &str, params: Value) -> Result<Vec<String>, DagError> {
+        serde_json::from_value(self.call(method, params)?).map_err(remote_error)
+    }
+}
+
+impl GraphService for RemoteDag {
+    fn add(&mut self, key: &str, payload: Value) -> Result<(), DagError> {
+        self.call("add", json!({ "key": key, "payload": payload })).map(|_| ())
+    }
+
+    fn update(&mut self, key: &str, payload: Value) -> Result<(), DagError> {
+        self.call("update", json!({ "key": key, "payload": payload })).map(|_| ())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, DagError> {
+        Ok(self.call("remove", json!({ "key": key }))?.as_bool().unwrap_or(false))
+    }
+
+    fn add_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        self.call("add_edge", json!({ "from_key": from_key, "to_key": to_key })).map(|_| ())
+    }
+
+    fn payload(&mut self, key: &str) -> Result<Option<String>, DagError> {
+        Ok(self.call("payload", json!({ "key": key }))?.as_str().map(|text| text.to_string()))
+    }
+
+    fn keys(&mut self) -> Result<Vec<String>, DagError> {
+        self.call_keys("keys", json!({}))
+    }
+
+    fn successors(&mut self, key: &str) -> Result<Vec<String>, DagError> {
+        self.call_keys("successors", json!({ "key": key }))
+    }
+
+    fn dirty_order(&mut self) -> Result<Vec<String>, DagError> {
+        self.call_keys("dirty_order", json!({}))
+    }
+
+    fn dispatch(&mut self) -> Result<Vec<String>, DagError> {
+        self.call_keys("dispatch", json!({}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(graph: &mut dyn GraphService) -> Result<(), DagError> {
+        graph.add("extract", json!({ "rows": 10 }))?;
+        graph.add("load", json!("warehouse"))?;
+        graph.add_edge("extract", "load")?;
+        graph.update("extract", json!({ "rows": 12 }))
+    }
+
+    #[test]
+    fn remote_clients_share_one_graph() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || GraphServer::new(Dag::new()).serve(listener));
+
+        let mut writer = RemoteDag::connect(address).unwrap();
+        let mut reader = RemoteDag::connect(address).unwrap();
+        build(&mut writer).unwrap();
+
+        assert_eq!(reader.keys().unwrap(), vec!["extract".to_string(), "load".to_string()]);
+        assert_eq!(reader.payload("extract").unwrap().as_deref(), Some(r#"{"rows":12}"#));
+        assert_eq!(reader.successors("extract").unwrap(), vec!["load".to_string()]);
+        assert_eq!(reader.successors("missing"), Err(DagError::NodeNotFound("missing".to_string())));
+        assert_eq!(reader.dispatch().unwrap(), vec!["extract".to_string(), "load".to_string()]);
+        assert!(writer.dirty_order().unwrap().is_empty());
+    }
+
+    #[test]
+    fn local_graph_implements_the_same_interface() {
+        let mut dag = Dag::new();
+        build(&mut dag).unwrap();
+        assert_eq!(GraphService::dirty_order(&mut dag).unwrap(), vec!["extract".to_string(), "load".to_string()]);
+
+        let mut server = GraphServer::new(dag);
+        let response: Value = serde_json::from_str(&server.handle(r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#)).unwrap();
+        assert_eq!(response["id"], json!(7));
+        assert_eq!(response["error"]["code"], json!(-32601));
+
+        server.handle(r#"{"jsonrpc":"2.0","id":8,"method":"add","params":{"key":"audit","payload":null}}"#);
+        server.handle(r#"{"jsonrpc":"2.0","id":9,"method":"add_edge","params":{"to_node_key":"load","from_node_key":"audit"}}"#);
+        assert_eq!(server.dag().successors("load"), vec!["audit".to_string()]);
+
+        for request in [r#"{"jsonrpc":"2.0","id":10,"method":"remove"}"#, r#"{"jsonrpc":"2.0","id":11,"method":"add","params":{"key":3}}"#] {
+            let response: Value = serde_json::from_str(&server.handle(request)).unwrap();
+            assert_eq!(response["error"]["code"], json!(-32602));
+        }
+        assert!(server.dag().get("").is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use crate::topology::Topology;
+use crate::{Dag, DagError, Node};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledNode {
+    pub worker: usize,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedSchedule {
+    pub nodes: BTreeMap<String, ScheduledNode>,
+    pub makespan: u64,
+}
+
+impl Dag {
+    pub fn layer_assignment(&self, max_width: Option<usize>) -> Result<Vec<Vec<String>>, DagError> {
+        let topology = self.topology();
+        let heights = heights(&topology)?;
+        let width = max_width.unwrap_or(usize::MAX).max(1);
+
+        let mut degrees = topology.in_degrees();
+        let mut ready: Vec<String> = degrees.iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut layers = vec![];
+        while !ready.is_empty() {
+            // Nodes heading the longest remaining chains go first so capped
+            // waves don't push the critical chain further out.
+            ready.sort_by(|a, b| (Reverse(heights[a]), a).cmp(&(Reverse(heights[b]), b)));
+            let deferred = ready.split_off(width.min(ready.len()));
+            let layer = std::mem::replace(&mut ready, deferred);
+            for key in layer.iter() {
+                for (to_key, _) in topology.successors(key) {
+                    let degree = degrees.get_mut(to_key).expect("Topology edge to unknown node");
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push(to_key.clone());
+                    }
+                }
+            }
+            layers.push(layer);
+        }
+        Ok(layers)
+    }
+
+    pub fn simulate_schedule<F>(&self, workers: usize, mut duration: F) -> Result<SimulatedSchedule, DagError>
+        where F: FnMut(&Node) -> u64 {
+        assert!(workers > 0, "A schedule simulation needs at least one worker");
+        let topology = self.topology();
+        let order = topology.topological_order()?;
+        let durations: BTreeMap<String, u64> = order.iter()
+            .map(|key| {
+                let node = self.get(key).expect("Topology node missing from graph");
+                let borrowed_node = node.borrow();
+                (key.clone(), duration(&borrowed_node))
+            })
+            .collect();
+
+        // List scheduling keyed on bottom level: a node's own duration plus
+        // the longest chain of work waiting behind it.
+        let mut bottom_levels: BTreeMap<String, u64> = BTreeMap::new();
+        for key in order.iter().rev() {
+            let tail = topology.successors(key).iter()
+                .map(|(to_key, _)| bottom_levels[to_key])
+                .max()
+                .unwrap_or(0);
+            bottom_levels.insert(key.clone(), durations[key] + tail);
+        }
+
+        let mut degrees = topology.in_degrees();
+        let mut ready: BinaryHeap<(u64, Reverse<String>)> = degrees.iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(key, _)| (bottom_levels[key], Reverse(key.clone())))
+            .collect();
+        let mut idle: BinaryHeap<Reverse<usize>> = (0..workers).map(Reverse).collect();
+        let mut running: BinaryHeap<Reverse<(u64, usize, String)>> = BinaryHeap::new();
+        let mut nodes = BTreeMap::new();
+        let mut now = 0;
+
+        loop {
+            while !ready.is_empty() && !idle.is_empty() {
+                let (_, Reverse(key)) = ready.pop().expect("Ready queue emptied");
+                let Reverse(worker) = idle.pop().expect("Worker pool emptied");
+                let end = now + durations[&key];
+                nodes.insert(key.clone(), ScheduledNode { worker, start: now, end });
+                running.push(Reverse((end, worker, key)));
+            }
+            let Some(Reverse((end, worker, key))) = running.pop() else {
+                break;
+            };
+            now = end;
+            idle.push(Reverse(worker));
+            for (to_key, _) in topology.successors(&key) {
+                let degree = degrees.get_mut(to_key).expect("Topology edge to unknown node");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push((bottom_levels[to_key], Reverse(to_key.clone())));
+                }
+            }
+        }
+
+        Ok(SimulatedSchedule { nodes, makespan: now })
+    }
+}
+
+// Number of nodes on the longest chain starting at each node.
+fn heights(topology: &Topology) -> Result<BTreeMap<String, usize>, DagError> {
+    let mut heights = BTreeMap::new();
+    for key in topology.topological_order()?.into_iter().rev() {
+        let height = topology.successors(&key).iter()
+            .map(|(to_key, _)| heights[to_key])
+            .max()
+            .unwrap_or(0) + 1;
+        heights.insert(key, height);
+    }
+    Ok(heights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C", "D"] {
+            dag.add(key, key);
+        }
+        dag.link("A", "B", 1);
+        dag.link("A", "C", 1);
+        dag.link("B", "D", 1);
+        dag.link("C", "D", 1);
+        dag
+    }
+
+    #[test]
+    fn layers_follow_dependencies() {
+        let layers = diamond().layer_assignment(None).unwrap();
+        assert_eq!(layers, vec![vec!["A"], vec!["B", "C"], vec!["D"]]);
+    }
+
+    #[test]
+    fn layers_respect_width_cap() {
+        let mut dag = diamond();
+        dag.add("E", "e");
+        dag.link("E", "D", 1);
+        let layers = dag.layer_assignment(Some(1)).unwrap();
+        assert_eq!(layers, vec![vec!["A"], vec!["B"], vec!["C"], vec!["E"], vec!["D"]]);
+    }
+
+    #[test]
+    fn simulation_reports_makespan() {
+        let dag = diamond();
+        let parallel = dag.simulate_schedule(2, |_| 10).unwrap();
+        assert_eq!(parallel.makespan, 30);
+        assert_eq!(parallel.nodes["D"].start, 20);
+
+        let serial = dag.simulate_schedule(1, |_| 10).unwrap();
+        assert_eq!(serial.makespan, 40);
+    }
+
+    #[test]
+    fn simulation_prefers_longest_chains() {
+        let mut dag = diamond();
+        dag.add("slow", "slow");
+        let schedule = dag.simulate_schedule(1, |node| if node.key == "slow" { 100 } else { 1 }).unwrap();
+        assert_eq!(schedule.nodes["slow"].start, 0);
+        assert_eq!(schedule.makespan, 104);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one worker")]
+    fn simulation_needs_a_worker() {
+        let _ = diamond().simulate_schedule(0, |_| 10);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::Dag;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    Star,
+    GlobStar,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+// Path-style globs over "/"-separated keys: `*` and `?` stay within one
+// segment, `**` crosses segments, `[a-z]` / `[!a-z]` match classes and `\`
+// escapes the next character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    tokens: Vec<Token>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    tokens.push(Token::GlobStar);
+                    i += 1;
+                },
+                '*' => tokens.push(Token::Star),
+                '?' => tokens.push(Token::AnyChar),
+                '\\' if i + 1 < chars.len() => {
+                    tokens.push(Token::Literal(chars[i + 1]));
+                    i += 1;
+                },
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, consumed)) => {
+                        tokens.push(token);
+                        i += consumed;
+                    },
+                    // An unterminated class is taken literally.
+                    None => tokens.push(Token::Literal('[')),
+                },
+                other => tokens.push(Token::Literal(other)),
+            }
+            i += 1;
+        }
+        Glob { tokens }
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        let chars: Vec<char> = key.chars().collect();
+        // matched[j]: the tokens consumed so far can match chars[..j].
+        let mut matched = vec![false; chars.len() + 1];
+        matched[0] = true;
+        for token in self.tokens.iter() {
+            let mut next = vec![false; chars.len() + 1];
+            for j in 0..=chars.len() {
+                match token {
+                    Token::Star | Token::GlobStar => {
+                        next[j] = matched[j] || (j > 0 && next[j - 1] && (*token == Token::GlobStar || chars[j - 1] != '/'));
+                    },
+                    _ => {
+                        next[j] = j > 0 && matched[j - 1] && single_matches(token, chars[j - 1]);
+                    },
+                }
+            }
+            matched = next;
+        }
+        matched[chars.len()]
+    }
+
+    fn literal_prefix(&self) -> String {
+        self.tokens.iter()
+            .map_while(|token| match token {
+                Token::Literal(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn single_matches(token: &Token, c: char) -> bool {
+    match token {
+        Token::Literal(literal) => *literal == c,
+        Token::AnyChar => c != '/',
+        Token::Class { negated, ranges } => {
+            let inside = ranges.iter().any(|(low, high)| *low <= c && c <= *high);
+            c != '/' && inside != *negated
+        },
+        Token::Star | Token::GlobStar => unreachable!("Stars match runs, not single characters"),
+    }
+}
+
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = vec![];
+    // A leading `]` is a member rather than the terminator.
+    let start = i;
+    while i < chars.len() && (chars[i] != ']' || i == start) {
+        if i + 2 < chars.len() && chars[i + 1] == '-' && chars[i + 2] != ']' {
+            ranges.push((chars[i], chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((chars[i], chars[i]));
+            i += 1;
+        }
+    }
+    if i >= chars.len() {
+        return None;
+    }
+    Some((Token::Class { negated, ranges }, i + 1))
+}
+
+impl Dag {
+    pub fn find_keys(&self, pattern: &str) -> impl Iterator<Item = &String> + '_ {
+        let glob = Glob::new(pattern);
+        let prefix = glob.literal_prefix();
+        let start = self.index.range(prefix.clone()..);
+        start
+            .take_while(move |key| key.starts_with(&prefix))
+            .filter(move |key| glob.matches(key))
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn find_keys_regex<'a>(&'a self, pattern: &'a regex::Regex) -> impl Iterator<Item = &'a String> + 'a {
+        self.index.iter().filter(move |key| pattern.is_match(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_syntax() {
+        assert!(Glob::new("team/*/build").matches("team/api/build"));
+        assert!(!Glob::new("team/*").matches("team/api/build"));
+        assert!(Glob::new("team/**").matches("team/api/build"));
+        assert!(Glob::new("task-?[0-9]").matches("task-a7"));
+        assert!(!Glob::new("task-[!0-9]").matches("task-7"));
+        assert!(Glob::new("literal\\*").matches("literal*"));
+        assert!(Glob::new("open[").matches("open["));
+    }
+
+    #[test]
+    fn keys_found_by_glob() {
+        let mut dag = Dag::new();
+        for key in ["team/api/build", "team/api/test", "team/web/build", "other/build"] {
+            dag.add(key, key);
+        }
+        let found: Vec<&String> = dag.find_keys("team/*/build").collect();
+        assert_eq!(found, vec!["team/api/build", "team/web/build"]);
+        assert_eq!(dag.find_keys("**/build").count(), 3);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn keys_found_by_regex() {
+        let mut dag = Dag::new();
+        dag.add("job-1", 1);
+        dag.add("job-x", 2);
+        let pattern = regex::Regex::new(r"^job-\d+$").unwrap();
+        assert_eq!(dag.find_keys_regex(&pattern).collect::<Vec<_>>(), vec!["job-1"]);
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Dag, DagError, TieBreak};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Query {
+    Reachability,
+    ShortestPath,
+    TopologicalSort,
+}
+
+// Every algorithm answers a query the same way; they only differ in cost.
+// `Layered` and `Indexed` need the index from `build_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    DepthFirst,
+    BreadthFirst,
+    // A breadth-first search that skips nodes in a layer at or below the
+    // target's, which can't lead to it.
+    Layered,
+    Indexed,
+    Kahn,
+}
+
+impl Query {
+    pub fn supports(self, algorithm: Algorithm) -> bool {
+        use Algorithm::*;
+        match self {
+            Query::Reachability => matches!(algorithm, DepthFirst | BreadthFirst | Layered | Indexed),
+            Query::ShortestPath => matches!(algorithm, BreadthFirst | Layered),
+            Query::TopologicalSort => matches!(algorithm, Kahn | Indexed),
+        }
+    }
+}
+
+// `density` is the average number of edges leaving a node. The depth in
+// layers is only known while an index is built.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub density: f64,
+    pub depth: Option<usize>,
+    pub indexed: bool,
+}
+
+// Descendant sets, layers and the topological order, all as of the last
+// `build_index`. Any structural mutation drops it.
+pub(crate) struct ReachIndex {
+    order: Vec<String>,
+    layers: HashMap<String, usize>,
+    descendants: HashMap<String, HashSet<String>>,
+}
+
+impl Dag {
+    // Below this many nodes every algorithm is cheap and a plain walk wins.
+    const SMALL_GRAPH: usize = 32;
+    const DENSE: f64 = 2.0;
+
+    // The counts take a pass over the node table, so they are kept until
+    // the structure next changes.
+    pub fn graph_stats(&self) -> GraphStats {
+        let (nodes, edges) = self.shape.get().unwrap_or_else(|| (self.node_count(), self.edge_count()));
+        self.shape.set(Some((nodes, edges)));
+        GraphStats {
+            nodes,
+            edges,
+            density: if nodes == 0 { 0.0 } else { edges as f64 / nodes as f64 },
+            depth: self.reach_index.as_ref().map(|index| index.layers.values().max().map_or(0, |layer| layer + 1)),
+            indexed: self.reach_index.is_some(),
+        }
+    }
+
+    // Worth it for graphs that are queried far more often than they change.
+    pub fn build_index(&mut self) -> Result<(), DagError> {
+        let topology = self.try_topology("build_index")?;
+        let order = topology.topological_order().map_err(|err| err.during("build_index"))?;
+        let mut layers: HashMap<String, usize> = HashMap::new();
+        for key in order.iter() {
+            let layer = layers.get(key).copied().unwrap_or(0);
+            layers.insert(key.clone(), layer);
+            for (next, _) in topology.successors(key) {
+                let entry = layers.entry(next.clone()).or_default();
+                *entry = (*entry).max(layer + 1);
+            }
+        }
+        let mut descendants: HashMap<String, HashSet<String>> = HashMap::new();
+        for key in order.iter().rev() {
+            let mut reached = HashSet::new();
+            for (next, _) in topology.successors(key) {
+                reached.insert(next.clone());
+                reached.extend(descendants[next].iter().cloned());
+            }
+            descendants.insert(key.clone(), reached);
+        }
+        self.reach_index = Some(ReachIndex { order, layers, descendants });
+        Ok(())
+    }
+
+    pub fn drop_index(&mut self) {
+        self.reach_index = None;
+    }
+
+    // `None` goes back to automatic selection. An override that needs the
+    // index is ignored while there is none.
+    pub fn set_algorithm(&mut self, query: Query, algorithm: Option<Algorithm>) {
+        match algorithm {
+            Some(algorithm) => {
+                assert!(query.supports(algorithm), "{:?} can't answer {:?}", algorithm, query);
+                self.algorithm_overrides.insert(query, algorithm);
+            },
+            None => {
+                self.algorithm_overrides.remove(&query);
+            },
+        }
+    }
+
+    // What the next query of this kind will run.
+    pub fn algorithm_for(&self, query: Query) -> Algorithm {
+        let indexed = self.reach_index.is_some();
+        if let Some(algorithm) = self.algorithm_overrides.get(&query) {
+            if indexed || !matches!(algorithm, Algorithm::Layered | Algorithm::Indexed) {
+                return *algorithm;
+            }
+        }
+        match query {
+            Query::Reachability if indexed => Algorithm::Indexed,
+            Query::Reachability => {
+                // Wide graphs tend to put the target a few hops out, where
+                // a depth-first walk can wander off down the wrong branch.
+                let stats = self.graph_stats();
+                if stats.nodes >= Self::SMALL_GRAPH && stats.density >= Self::DENSE {
+                    Algorithm::BreadthFirst
+                } else {
+                    Algorithm::DepthFirst
+                }
+            },
+            Query::ShortestPath if indexed => Algorithm::Layered,
+            Query::ShortestPath => Algorithm::BreadthFirst,
+            Query::TopologicalSort if indexed => Algorithm::Indexed,
+            Query::TopologicalSort => Algorithm::Kahn,
+        }
+    }
+
+    // Smallest key first among nodes that are ready at the same time.
+    pub fn topological_order(&self) -> Result<Vec<String>, DagError> {
+        if let Some(order) = self.cached_order() {
+            return Ok(order);
+        }
+        let order = match (self.algorithm_for(Query::TopologicalSort), &self.reach_index) {
+            (Algorithm::Indexed, Some(index)) if self.tie_break == TieBreak::Key => index.order.clone(),
+            _ => self.ordered(&self.try_topology("topological_order")?)?,
+        };
+        self.store_order(&order);
+        Ok(order)
+    }
+
+    // Fewest edges, both ends included. Ties go to the path found first
+    // following each node's edges in the order they were added.
+    pub fn shortest_path(&self, from_key: &str, to_key: &str) -> Option<Vec<String>> {
+        let (from_key, to_key) = (self.resolve_key(from_key).into_owned(), self.resolve_key(to_key).into_owned());
+        if self.get(&from_key).is_none() || self.get(&to_key).is_none() {
+            return None;
+        }
+        if let Some(path) = self.cached_path(&from_key, &to_key) {
+            return path;
+        }
+        let layered = self.algorithm_for(Query::ShortestPath) == Algorithm::Layered;
+        let path = self.breadth_first(&from_key, &to_key, layered);
+        self.store_path(&from_key, &to_key, &path);
+        path
+    }
+
+    pub(crate) fn select_reaches(&self, from_key: &str, to_key: &str) -> bool {
+        let (from_key, to_key) = (self.resolve_key(from_key).into_owned(), self.resolve_key(to_key).into_owned());
+        match (self.algorithm_for(Query::Reachability), &self.reach_index) {
+            (Algorithm::Indexed, Some(index)) => from_key == to_key
+                || index.descendants.get(&from_key).is_some_and(|reached| reached.contains(&to_key)),
+            (Algorithm::BreadthFirst, _) => self.breadth_first(&from_key, &to_key, false).is_some(),
+            (Algorithm::Layered, _) => self.breadth_first(&from_key, &to_key, true).is_some(),
+            _ => self.reaches(&from_key, &to_key),
+        }
+    }
+
+    fn breadth_first(&self, from_key: &str, to_key: &str, layered: bool) -> Option<Vec<String>> {
+        let target_layer = self.reach_index.as_ref().filter(|_| layered).map(|index| (index, index.layers[to_key]));
+        let mut parents: HashMap<String, String> = HashMap::new();
+        let mut seen: HashSet<String> = HashSet::from([from_key.to_string()]);
+        let mut queue = VecDeque::from([from_key.to_string()]);
+        while let Some(key) = queue.pop_front() {
+            if key == to_key {
+                let mut path = vec![key];
+                while let Some(parent) = parents.get(path.last().expect("Path starts non-empty")) {
+                    path.push(parent.clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.successors(&key) {
+                let pruned = target_layer.is_some_and(|(index, layer)| next != to_key && index.layers[&next] >= layer);
+                if !pruned && seen.insert(next.clone()) {
+                    parents.insert(next.clone(), key.clone());
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A wide, dense fan of `width` nodes per layer, each linked to every
+    // node of the next layer.
+    fn mesh(width: usize, depth: usize) -> Dag {
+        let mut dag = Dag::new();
+        for layer in 0..depth {
+            for i in 0..width {
+                dag.add(&format!("{}-{}", layer, i), ());
+                if layer > 0 {
+                    for j in 0..width {
+                        dag.add_edge_directed(&format!("{}-{}", layer - 1, j), &format!("{}-{}", layer, i));
+                    }
+                }
+            }
+        }
+        dag
+    }
+
+    #[test]
+    fn selection_follows_shape_and_index() {
+        let mut chain = Dag::new();
+        chain.add("a", ());
+        chain.add("b", ()).depends_on("a");
+        assert_eq!(chain.algorithm_for(Query::Reachability), Algorithm::DepthFirst);
+
+        let mut dag = mesh(8, 5);
+        assert_eq!(dag.algorithm_for(Query::Reachability), Algorithm::BreadthFirst);
+        let (order, path) = (dag.topological_order().unwrap(), dag.shortest_path("0-3", "4-1").unwrap());
+        assert!(dag.is_reachable("0-0", "4-7") && !dag.is_reachable("4-7", "0-0"));
+
+        dag.build_index().unwrap();
+        assert_eq!(dag.graph_stats().depth, Some(5));
+        assert_eq!(dag.algorithm_for(Query::Reachability), Algorithm::Indexed);
+        assert_eq!(dag.algorithm_for(Query::ShortestPath), Algorithm::Layered);
+        assert_eq!(dag.topological_order().unwrap(), order);
+        assert_eq!(dag.shortest_path("0-3", "4-1").unwrap(), path);
+        assert!(dag.is_reachable("0-0", "4-7") && !dag.is_reachable("4-7", "0-0"));
+
+        dag.add("late", ()).depends_on("4-7");
+        assert!(!dag.graph_stats().indexed);
+        assert!(dag.is_reachable("0-0", "late"));
+    }
+
+    #[test]
+    fn overrides_win_until_cleared() {
+        let mut dag = mesh(8, 4);
+        dag.set_algorithm(Query::Reachability, Some(Algorithm::Indexed));
+        assert_eq!(dag.algorithm_for(Query::Reachability), Algorithm::BreadthFirst);
+        dag.set_algorithm(Query::Reachability, Some(Algorithm::DepthFirst));
+        dag.build_index().unwrap();
+        assert_eq!(dag.algorithm_for(Query::Reachability), Algorithm::DepthFirst);
+        dag.set_algorithm(Query::Reachability, None);
+        assert_eq!(dag.algorithm_for(Query::Reachability), Algorithm::Indexed);
+        let unsupported = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            dag.set_algorithm(Query::ShortestPath, Some(Algorithm::Kahn));
+        }));
+        assert!(unsupported.is_err());
+    }
+}
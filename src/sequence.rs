@@ -0,0 +1,119 @@
+use crate::topology::Topology;
+use crate::{borrow, Dag, DagError};
+
+// How `topological_order` and the sequential dispatches pick among nodes
+// that are ready at the same time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    #[default]
+    Key,
+    // Smallest sequence number first, then smallest key.
+    Oldest,
+}
+
+// Every node and edge, hydrated ones included, gets a sequence number from
+// one counter as it is added. Numbers set by hand (timestamps, say) push the
+// counter past them, so whatever is added afterwards still counts as newer.
+impl Dag {
+    pub fn node_sequence(&self, key: &str) -> Option<u64> {
+        self.sequences.borrow().get(self.resolve_key(key).as_ref()).copied()
+    }
+
+    // Returns false if `key` isn't in the graph.
+    pub fn set_node_sequence(&mut self, key: &str, sequence: u64) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        self.sequences.get_mut().insert(key, sequence);
+        self.last_sequence.set(self.last_sequence.get().max(sequence));
+        self.forget_cached_order();
+        true
+    }
+
+    pub fn edge_sequence(&self, from_key: &str, to_key: &str) -> Option<u64> {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let node = self.get(from_key)?;
+        let borrowed_node = borrow::or_panic(borrow::read(&node, from_key, "edge_sequence"));
+        borrowed_node.edges.iter()
+            .find(|edge| edge.to_key == to_key && self.live_target(edge).is_some())
+            .map(|edge| edge.sequence)
+    }
+
+    // Returns false if there is no edge from `from_key` to `to_key`.
+    pub fn set_edge_sequence(&mut self, from_key: &str, to_key: &str, sequence: u64) -> bool {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let Some(node) = self.get(from_key) else { return false };
+        let mut borrowed_node = borrow::or_panic(borrow::write(&node, from_key, "set_edge_sequence"));
+        let mut found = false;
+        for edge in borrowed_node.edges.iter_mut().filter(|edge| edge.to_key == to_key) {
+            edge.sequence = sequence;
+            found = true;
+        }
+        if found {
+            self.last_sequence.set(self.last_sequence.get().max(sequence));
+        }
+        found
+    }
+
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+        self.forget_cached_order();
+    }
+
+    pub(crate) fn next_sequence(&self) -> u64 {
+        self.last_sequence.set(self.last_sequence.get() + 1);
+        self.last_sequence.get()
+    }
+
+    pub(crate) fn ordered(&self, region: &Topology) -> Result<Vec<String>, DagError> {
+        match self.tie_break {
+            TieBreak::Key => region.topological_order(),
+            TieBreak::Oldest => {
+                let sequences = self.sequences.borrow();
+                region.topological_order_by(|key| sequences.get(key).copied().unwrap_or(0))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_dependencies_run_first() {
+        let mut dag = Dag::new();
+        for key in ["source", "zeta", "alpha", "sink"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("source", "zeta");
+        dag.add_edge_directed("source", "alpha");
+        dag.add_edge_directed("zeta", "sink");
+        dag.add_edge_directed("alpha", "sink");
+        assert!(dag.node_sequence("zeta") < dag.node_sequence("alpha"));
+        assert!(dag.edge_sequence("alpha", "sink") > dag.node_sequence("sink"));
+
+        let visit = |dag: &mut Dag| {
+            dag.update("source", ());
+            let mut seen = vec![];
+            dag.dispatch_with_ctx(|ctx| seen.push(ctx.key().to_string())).unwrap();
+            seen
+        };
+        assert_eq!(visit(&mut dag), vec!["source", "alpha", "zeta", "sink"]);
+        dag.set_tie_break(TieBreak::Oldest);
+        assert_eq!(visit(&mut dag), vec!["source", "zeta", "alpha", "sink"]);
+        assert_eq!(dag.topological_order().unwrap(), vec!["source", "zeta", "alpha", "sink"]);
+
+        assert!(dag.set_node_sequence("alpha", 1));
+        assert!(!dag.set_node_sequence("missing", 1));
+        assert_eq!(dag.topological_order().unwrap(), vec!["source", "alpha", "zeta", "sink"]);
+        assert!(dag.set_edge_sequence("source", "zeta", 1_000));
+        dag.add("late", ());
+        assert_eq!(dag.node_sequence("late"), Some(1_001));
+    }
+}
@@ -0,0 +1,65 @@
+use std::mem;
+
+use crate::{Dag, Node};
+
+pub(crate) type PayloadSizer = Box<dyn Fn(&Node) -> usize>;
+
+impl Dag {
+    // Without a sizer a payload counts as its own inline size, which misses
+    // anything it owns on the heap: a `String` is 24 bytes however long.
+    // Payloads are type-erased, so a sizer that knows better has to work
+    // from the node, e.g. by its key prefix or its `Debug` output.
+    pub fn set_payload_sizer<F>(&mut self, sizer: F) where F: Fn(&Node) -> usize + 'static {
+        self.payload_sizer = Some(Box::new(sizer));
+    }
+
+    pub fn clear_payload_sizer(&mut self) {
+        self.payload_sizer = None;
+    }
+
+    pub fn payload_size(&self, key: &str) -> Option<usize> {
+        self.get(key).map(|node| self.size_of(&node.borrow()))
+    }
+
+    pub fn total_payload_size(&self) -> usize {
+        self.nodes.borrow().values().map(|node| self.size_of(&node.borrow())).sum()
+    }
+
+    // The `top_n` biggest payloads, biggest first; equal sizes in key order.
+    pub fn largest_nodes(&self, top_n: usize) -> Vec<(String, usize)> {
+        let mut sizes: Vec<(String, usize)> = self.index.iter()
+            .filter_map(|key| self.payload_size(key).map(|size| (key.clone(), size)))
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes.truncate(top_n);
+        sizes
+    }
+
+    fn size_of(&self, node: &Node) -> usize {
+        match self.payload_sizer.as_ref() {
+            Some(sizer) => sizer(node),
+            None => mem::size_of_val(&*node.data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_payloads_first() {
+        let mut dag = Dag::new();
+        dag.add("flag", true);
+        dag.add("buffer", [0u8; 4096]);
+        dag.add("name", "a much longer name than the others".to_string());
+        dag.add("count", 7u64);
+        assert_eq!(dag.largest_nodes(2), vec![("buffer".to_string(), 4096), ("name".to_string(), 24)]);
+        assert_eq!(dag.payload_size("flag"), Some(1));
+        assert_eq!(dag.payload_size("missing"), None);
+
+        dag.set_payload_sizer(|node| format!("{:?}", node.data).len());
+        assert_eq!(dag.largest_nodes(2)[1], ("name".to_string(), 36));
+        assert_eq!(dag.total_payload_size(), format!("{:?}", [0u8; 4096]).len() + 36 + 4 + 1);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{borrow, Dag, DagError};
+
+// A soft edge only orders execution: its target still runs after its source
+// when both are dispatched together, but invalidating or failing the source
+// leaves the target alone.
+impl Dag {
+    pub fn add_soft_edge(&mut self, from_key: &str, to_key: &str) {
+        self.add_edge_directed(from_key, to_key);
+        self.set_edge_soft(from_key, to_key, true);
+    }
+
+    // Checked like `try_add_edge`: quotas and cycles.
+    pub fn try_add_soft_edge(&mut self, from_key: &str, to_key: &str) -> Result<(), DagError> {
+        self.try_add_edge(from_key, to_key)?;
+        self.set_edge_soft(from_key, to_key, true);
+        Ok(())
+    }
+
+    // Returns false if there is no edge from `from_key` to `to_key`.
+    pub fn set_edge_soft(&mut self, from_key: &str, to_key: &str, soft: bool) -> bool {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        let Some(node) = self.get(from_key) else { return false };
+        let found = {
+            let mut borrowed_node = borrow::or_panic(borrow::write(&node, from_key, "set_edge_soft"));
+            let mut found = false;
+            for edge in borrowed_node.edges.iter_mut().filter(|edge| edge.to_key == to_key) {
+                edge.soft = soft;
+                found = true;
+            }
+            found
+        };
+        if found {
+            self.mark_stored_dirty(&[from_key]);
+        }
+        found
+    }
+
+    pub fn is_soft_edge(&self, from_key: &str, to_key: &str) -> bool {
+        let (from_key, to_key): (&str, &str) = (&self.resolve_key(from_key), &self.resolve_key(to_key));
+        self.get(from_key).is_some_and(|node| {
+            borrow::or_panic(borrow::read(&node, from_key, "is_soft_edge")).edges.iter()
+                .any(|edge| edge.soft && edge.to_key == to_key && self.live_target(edge).is_some())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `load` feeds `report`; `vacuum` should only run after `load`.
+    fn pipeline() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["load", "report", "vacuum"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("load", "report");
+        dag.add_soft_edge("load", "vacuum");
+        dag.dispatch(|_| ());
+        dag
+    }
+
+    #[test]
+    fn soft_edges_order_without_invalidating() {
+        let mut dag = pipeline();
+        assert!(dag.is_soft_edge("load", "vacuum"));
+        dag.update("load", ());
+        let mut seen = vec![];
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.key().to_string())).unwrap();
+        assert_eq!(seen, vec!["load", "report"]);
+
+        dag.update("vacuum", ());
+        dag.update("load", ());
+        seen.clear();
+        dag.dispatch_with_ctx(|ctx| seen.push(ctx.key().to_string())).unwrap();
+        assert_eq!(seen, vec!["load", "report", "vacuum"]);
+    }
+
+    #[test]
+    fn failure_does_not_cross_soft_edges() {
+        let mut dag = pipeline();
+        dag.update("load", ());
+        dag.update("vacuum", ());
+        let outcome = dag.try_dispatch(|ctx| if ctx.key() == "load" { Err(()) } else { Ok(()) }).unwrap();
+        assert_eq!(outcome.blocked, vec!["report".to_string()]);
+        assert_eq!(dag.last_dispatched("vacuum"), Some(outcome.id));
+    }
+
+    #[test]
+    fn checked_soft_edges_reject_cycles() {
+        let mut dag = pipeline();
+        assert!(matches!(dag.try_add_soft_edge("vacuum", "load"), Err(DagError::WouldCreateCycle { .. })));
+        assert!(!dag.is_soft_edge("vacuum", "load"));
+        assert!(dag.find_cycle().is_none());
+    }
+}
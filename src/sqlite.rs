@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{DagError, GraphStore, StoreOp, StoredGraph};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS nodes (
+        key TEXT PRIMARY KEY,
+        payload TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS edges (
+        from_key TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        to_key TEXT NOT NULL,
+        weight INTEGER NOT NULL,
+        PRIMARY KEY (from_key, position)
+    );
+    CREATE INDEX IF NOT EXISTS edges_to_key ON edges (to_key);
+    CREATE TABLE IF NOT EXISTS soft_edges (
+        from_key TEXT NOT NULL,
+        to_key TEXT NOT NULL,
+        PRIMARY KEY (from_key, to_key)
+    );
+    CREATE TABLE IF NOT EXISTS invalidated (
+        key TEXT PRIMARY KEY
+    );
+";
+
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+fn store_error(err: rusqlite::Error) -> DagError {
+    DagError::Store(err.to_string())
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore, DagError> {
+        SqliteStore::with_connection(Connection::open(path).map_err(store_error)?)
+    }
+
+    pub fn in_memory() -> Result<SqliteStore, DagError> {
+        SqliteStore::with_connection(Connection::open_in_memory().map_err(store_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<SqliteStore, DagError> {
+        connection.execute_batch(SCHEMA).map_err(store_error)?;
+        Ok(SqliteStore { connection })
+    }
+}
+
+impl GraphStore for SqliteStore {
+    fn apply(&mut self, ops: &[StoreOp]) -> Result<(), DagError> {
+        // Dropping the transaction on an early return rolls it back.
+        let transaction = self.connection.transaction().map_err(store_error)?;
+        for op in ops {
+            match op {
+                StoreOp::Clear => {
+                    transaction.execute_batch(
+                        "DELETE FROM nodes; DELETE FROM edges; DELETE FROM soft_edges; DELETE FROM invalidated;")
+                },
+                StoreOp::PutNode { key, payload } => transaction.execute(
+                    "INSERT INTO nodes (key, payload) VALUES (?1, ?2)
+                     ON CONFLICT (key) DO UPDATE SET payload = excluded.payload",
+                    params![key, payload],
+                ).map(|_| ()),
+                StoreOp::RemoveNode { key } => transaction.execute("DELETE FROM nodes WHERE key = ?1", params![key])
+                    .and_then(|_| transaction.execute(
+                        "DELETE FROM edges WHERE from_key = ?1 OR to_key = ?1", params![key]))
+                    .and_then(|_| transaction.execute(
+                        "DELETE FROM soft_edges WHERE from_key = ?1 OR to_key = ?1", params![key]))
+                    .map(|_| ()),
+                StoreOp::ReplaceEdges { from, edges, soft } => {
+                    transaction.execute("DELETE FROM edges WHERE from_key = ?1", params![from]).map(|_| ())
+                        .and_then(|_| edges.iter().enumerate().try_for_each(|(position, (to, weight))| {
+                            transaction.execute(
+                                "INSERT INTO edges (from_key, position, to_key, weight) VALUES (?1, ?2, ?3, ?4)",
+                                params![from, position as i64, to, weight],
+                            ).map(|_| ())
+                        }))
+                        .and_then(|_| transaction.execute("DELETE FROM soft_edges WHERE from_key = ?1", params![from]))
+                        .and_then(|_| soft.iter().try_for_each(|to| {
+                            transaction.execute(
+                                "INSERT OR IGNORE INTO soft_edges (from_key, to_key) VALUES (?1, ?2)", params![from, to],
+                            ).map(|_| ())
+                        }))
+                },
+                StoreOp::ReplaceInvalidated(keys) => {
+                    transaction.execute("DELETE FROM invalidated", []).map(|_| ())
+                        .and_then(|_| keys.iter().try_for_each(|key| {
+                            transaction.execute("INSERT INTO invalidated (key) VALUES (?1)", params![key]).map(|_| ())
+                        }))
+                },
+            }.map_err(store_error)?;
+        }
+        transaction.commit().map_err(store_error)
+    }
+
+    fn load(&self) -> Result<StoredGraph, DagError> {
+        let mut stored = StoredGraph::default();
+        let mut nodes = self.connection.prepare("SELECT key, payload FROM nodes ORDER BY key").map_err(store_error)?;
+        for row in nodes.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(store_error)? {
+            stored.nodes.push(row.map_err(store_error)?);
+        }
+        let mut edges = self.connection.prepare(
+            "SELECT from_key, to_key, weight FROM edges ORDER BY from_key, position"
+        ).map_err(store_error)?;
+        for row in edges.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).map_err(store_error)? {
+            stored.edges.push(row.map_err(store_error)?);
+        }
+        let mut soft = self.connection.prepare(
+            "SELECT from_key, to_key FROM soft_edges ORDER BY from_key, to_key"
+        ).map_err(store_error)?;
+        for row in soft.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(store_error)? {
+            stored.soft_edges.push(row.map_err(store_error)?);
+        }
+        let mut invalidated = self.connection.prepare("SELECT key FROM invalidated ORDER BY key").map_err(store_error)?;
+        for row in invalidated.query_map([], |row| row.get(0)).map_err(store_error)? {
+            stored.invalidated.push(row.map_err(store_error)?);
+        }
+        Ok(stored)
+    }
+
+    fn load_payload(&self, key: &str) -> Result<Option<String>, DagError> {
+        self.connection.query_row("SELECT payload FROM nodes WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(store_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dag;
+
+    #[test]
+    fn graph_survives_reopening_the_database() {
+        let path = std::env::temp_dir().join(format!("dag-sqlite-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut dag = Dag::new();
+        dag.attach_store(Box::new(SqliteStore::open(&path).unwrap()));
+        dag.add("extract", 1);
+        dag.add("load", 2);
+        dag.add("report", 3);
+        dag.add_edge_directed("extract", "load");
+        dag.add_edge_directed("load", "report");
+        dag.flush().unwrap();
+        dag.remove("report");
+        dag.update("load", 20);
+        dag.flush().unwrap();
+        drop(dag);
+
+        let reopened = Dag::open_store(Box::new(SqliteStore::open(&path).unwrap())).unwrap();
+        assert!(reopened.get("report").is_none());
+        assert_eq!(format!("{:?}", reopened.get("load").unwrap().borrow().data), "20");
+        assert_eq!(reopened.successors("extract"), vec!["load".to_string()]);
+        assert!(reopened.invalidated.contains("load"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_batch_leaves_store_untouched() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        store.apply(&[StoreOp::PutNode { key: "A".to_string(), payload: "1".to_string() }]).unwrap();
+        store.connection.execute_batch("CREATE TRIGGER reject BEFORE INSERT ON invalidated BEGIN
+            SELECT RAISE(ABORT, 'rejected'); END;").unwrap();
+
+        let result = store.apply(&[
+            StoreOp::PutNode { key: "B".to_string(), payload: "2".to_string() },
+            StoreOp::ReplaceInvalidated(vec!["B".to_string()]),
+        ]);
+        assert!(matches!(result, Err(DagError::Store(_))));
+        assert_eq!(store.load().unwrap().nodes, vec![("A".to_string(), "1".to_string())]);
+    }
+}
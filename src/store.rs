@@ -0,0 +1,336 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::cache::PayloadCache;
+use crate::{Dag, DagError, NodeData};
+
+// Backends only ever see payloads as text. Debug output is what gets
+// written, and a decoder chosen when the store is opened turns it back into
+// data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreOp {
+    Clear,
+    PutNode { key: String, payload: String },
+    RemoveNode { key: String },
+    // `soft` lists the targets of the soft edges among `edges`.
+    ReplaceEdges { from: String, edges: Vec<(String, i32)>, soft: Vec<String> },
+    ReplaceInvalidated(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredGraph {
+    pub nodes: Vec<(String, String)>,
+    pub edges: Vec<(String, String, i32)>,
+    pub soft_edges: Vec<(String, String)>,
+    pub invalidated: Vec<String>,
+}
+
+// `apply` must be all-or-nothing: either every op in the batch lands or the
+// store is left as it was.
+pub trait GraphStore {
+    fn apply(&mut self, ops: &[StoreOp]) -> Result<(), DagError>;
+    fn load(&self) -> Result<StoredGraph, DagError>;
+
+    fn load_payload(&self, key: &str) -> Result<Option<String>, DagError> {
+        Ok(self.load()?.nodes.into_iter().find(|(stored, _)| stored == key).map(|(_, payload)| payload))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    nodes: BTreeMap<String, String>,
+    edges: BTreeMap<String, Vec<(String, i32)>>,
+    soft: BTreeMap<String, Vec<String>>,
+    invalidated: BTreeSet<String>,
+}
+
+impl GraphStore for MemoryStore {
+    fn apply(&mut self, ops: &[StoreOp]) -> Result<(), DagError> {
+        for op in ops {
+            match op {
+                StoreOp::Clear => *self = MemoryStore::default(),
+                StoreOp::PutNode { key, payload } => {
+                    self.nodes.insert(key.clone(), payload.clone());
+                },
+                StoreOp::RemoveNode { key } => {
+                    self.nodes.remove(key);
+                    self.edges.remove(key);
+                    self.soft.remove(key);
+                    for edges in self.edges.values_mut() {
+                        edges.retain(|(to_key, _)| to_key != key);
+                    }
+                    for soft in self.soft.values_mut() {
+                        soft.retain(|to_key| to_key != key);
+                    }
+                },
+                StoreOp::ReplaceEdges { from, edges, soft } => {
+                    self.edges.insert(from.clone(), edges.clone());
+                    self.soft.insert(from.clone(), soft.clone());
+                },
+                StoreOp::ReplaceInvalidated(keys) => {
+                    self.invalidated = keys.iter().cloned().collect();
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self) -> Result<StoredGraph, DagError> {
+        Ok(StoredGraph {
+            nodes: self.nodes.iter().map(|(key, payload)| (key.clone(), payload.clone())).collect(),
+            edges: self.edges.iter()
+                .flat_map(|(from, edges)| edges.iter().map(move |(to, weight)| (from.clone(), to.clone(), *weight)))
+                .collect(),
+            soft_edges: self.soft.iter()
+                .flat_map(|(from, soft)| soft.iter().map(move |to| (from.clone(), to.clone())))
+                .collect(),
+            invalidated: self.invalidated.iter().cloned().collect(),
+        })
+    }
+
+    fn load_payload(&self, key: &str) -> Result<Option<String>, DagError> {
+        Ok(self.nodes.get(key).cloned())
+    }
+}
+
+// Payload text loaded without a decoder. Its Debug output is the stored text
+// itself, so flushing it again writes back exactly what was read.
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoredPayload(pub String);
+
+impl fmt::Debug for StoredPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub(crate) fn stored_payload(text: &str) -> Box<NodeData> {
+    Box::new(StoredPayload(text.to_string()))
+}
+
+pub(crate) struct Attached {
+    pub(crate) backend: Box<dyn GraphStore>,
+    pub(crate) decode: fn(&str) -> Box<NodeData>,
+    pub(crate) cache: Option<RefCell<PayloadCache>>,
+    dirty: BTreeSet<String>,
+    invalidated: BTreeSet<String>,
+    rewrite: bool,
+}
+
+impl Attached {
+    fn new(backend: Box<dyn GraphStore>, decode: fn(&str) -> Box<NodeData>, invalidated: BTreeSet<String>, rewrite: bool) -> Attached {
+        Attached { backend, decode, cache: None, dirty: BTreeSet::new(), invalidated, rewrite }
+    }
+}
+
+impl Dag {
+    pub fn open_store(store: Box<dyn GraphStore>) -> Result<Dag, DagError> {
+        Dag::open_store_with(store, stored_payload)
+    }
+
+    pub fn open_store_with(store: Box<dyn GraphStore>, decode: fn(&str) -> Box<NodeData>) -> Result<Dag, DagError> {
+        let stored = store.load().map_err(|err| err.during("open_store"))?;
+        let mut dag = Dag::from_stored_graph(stored, decode);
+        let invalidated: BTreeSet<String> = dag.invalidated.iter().cloned().collect();
+        dag.store = Some(Attached::new(store, decode, invalidated, false));
+        Ok(dag)
+    }
+
+    // Edges and invalidation marks naming keys the graph doesn't hold are
+    // dropped.
+    pub fn from_stored_graph(stored: StoredGraph, decode: fn(&str) -> Box<NodeData>) -> Dag {
+        let mut dag = Dag::new();
+        for (key, payload) in stored.nodes.iter() {
+            dag.insert_boxed(key, decode(payload));
+        }
+        for (from, to, weight) in stored.edges.iter() {
+            if dag.get(from).is_some() && dag.get(to).is_some() {
+                dag.link(from, to, *weight);
+            }
+        }
+        for (from, to) in stored.soft_edges.iter() {
+            dag.set_edge_soft(from, to, true);
+        }
+        dag.invalidated = stored.invalidated.into_iter()
+            .filter(|key| dag.nodes.borrow().contains_key(key))
+            .collect();
+        dag
+    }
+
+    // Everything a store would hold for this graph: nodes by key with their
+    // payloads rendered, and each node's edges in the order they were added.
+    pub fn to_stored_graph(&self) -> StoredGraph {
+        let mut stored = StoredGraph::default();
+        for key in self.index.iter() {
+            let node = self.get(key).expect("Indexed node missing");
+            let borrowed = node.borrow();
+            stored.nodes.push((key.clone(), format!("{:?}", borrowed.data)));
+            for edge in borrowed.edges.iter() {
+                if self.live_target(edge).is_some() {
+                    stored.edges.push((key.clone(), edge.to_key.clone(), edge.weight));
+                    if edge.soft {
+                        stored.soft_edges.push((key.clone(), edge.to_key.clone()));
+                    }
+                }
+            }
+        }
+        let mut invalidated: Vec<String> = self.invalidated.iter().cloned().collect();
+        invalidated.sort();
+        stored.invalidated = invalidated;
+        stored
+    }
+
+    pub fn attach_store(&mut self, store: Box<dyn GraphStore>) {
+        self.attach_store_with(store, stored_payload);
+    }
+
+    // The store is overwritten with this graph on the next flush. `decode`
+    // is used for payloads read back after being evicted from a cache.
+    pub fn attach_store_with(&mut self, store: Box<dyn GraphStore>, decode: fn(&str) -> Box<NodeData>) {
+        self.page_in_all();
+        self.store = Some(Attached::new(store, decode, BTreeSet::new(), true));
+    }
+
+    pub fn detach_store(&mut self) -> Option<Box<dyn GraphStore>> {
+        self.page_in_all();
+        self.store.take().map(|attached| attached.backend)
+    }
+
+    pub fn has_unflushed_changes(&self) -> bool {
+        self.store.as_ref().is_some_and(|attached| {
+            attached.rewrite || !attached.dirty.is_empty() || !self.invalidated_matches(&attached.invalidated)
+        })
+    }
+
+    // Writes every change since the last flush as one batch, then trims any
+    // payload cache back to capacity. On failure the changes stay pending and
+    // the next flush retries them.
+    pub fn flush(&mut self) -> Result<(), DagError> {
+        let (rewrite, keys) = match self.store.as_ref() {
+            Some(attached) if attached.rewrite => (true, self.index.iter().cloned().collect::<Vec<_>>()),
+            Some(attached) => (false, attached.dirty.iter().cloned().collect()),
+            None => return Ok(()),
+        };
+        let mut ops = vec![];
+        if rewrite {
+            ops.push(StoreOp::Clear);
+        }
+        for key in keys.iter() {
+            ops.extend(self.store_ops(key));
+        }
+        let invalidated: BTreeSet<String> = self.invalidated.iter().cloned().collect();
+        let stored_invalidated = &self.store.as_ref().expect("Store detached during flush").invalidated;
+        if rewrite || invalidated != *stored_invalidated {
+            ops.push(StoreOp::ReplaceInvalidated(invalidated.iter().cloned().collect()));
+        }
+        if !ops.is_empty() {
+            let attached = self.store.as_mut().expect("Store detached during flush");
+            attached.backend.apply(&ops).map_err(|err| err.during("flush"))?;
+            attached.dirty.clear();
+            attached.invalidated = invalidated;
+            attached.rewrite = false;
+        }
+        self.evict_to_capacity();
+        Ok(())
+    }
+
+    // A failed write-through stays pending like any other unflushed change.
+    pub(crate) fn mark_stored_dirty(&mut self, keys: &[&str]) {
+        if let Some(attached) = self.store.as_mut() {
+            attached.dirty.extend(keys.iter().map(|key| key.to_string()));
+            self.touch_cached(keys);
+            if self.writes_through() {
+                let _ = self.flush();
+            }
+        }
+    }
+
+    fn invalidated_matches(&self, stored: &BTreeSet<String>) -> bool {
+        self.invalidated.len() == stored.len() && stored.iter().all(|key| self.invalidated.contains(key))
+    }
+
+    // A cold node's payload is already in the store, so only its edges can
+    // have changed.
+    fn store_ops(&self, key: &str) -> Vec<StoreOp> {
+        match self.nodes.borrow().get(key) {
+            Some(node) => {
+                let borrowed = node.borrow();
+                let edges = borrowed.edges.iter()
+                    .filter_map(|edge| self.live_target(edge).map(|_| (edge.to_key.clone(), edge.weight)))
+                    .collect();
+                let soft = borrowed.edges.iter()
+                    .filter(|edge| edge.soft && self.live_target(edge).is_some())
+                    .map(|edge| edge.to_key.clone())
+                    .collect();
+                let mut ops = vec![];
+                if !self.is_cold(key) {
+                    ops.push(StoreOp::PutNode { key: key.to_string(), payload: format!("{:?}", borrowed.data) });
+                }
+                ops.push(StoreOp::ReplaceEdges { from: key.to_string(), edges, soft });
+                ops
+            },
+            None => vec![StoreOp::RemoveNode { key: key.to_string() }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shares one MemoryStore between the graph and the test so restarts can
+    // be simulated by reopening it.
+    #[derive(Clone, Default)]
+    struct Shared(std::rc::Rc<std::cell::RefCell<MemoryStore>>);
+
+    impl GraphStore for Shared {
+        fn apply(&mut self, ops: &[StoreOp]) -> Result<(), DagError> {
+            self.0.borrow_mut().apply(ops)
+        }
+
+        fn load(&self) -> Result<StoredGraph, DagError> {
+            self.0.borrow().load()
+        }
+    }
+
+    #[test]
+    fn flushed_graph_survives_reopen() {
+        let shared = Shared::default();
+        let mut dag = Dag::new();
+        dag.add("A", 1);
+        dag.add("B", "two");
+        dag.add("C", 3);
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.attach_store(Box::new(shared.clone()));
+        dag.flush().unwrap();
+
+        dag.remove("C");
+        dag.update("A", 10);
+        assert!(dag.has_unflushed_changes());
+        dag.flush().unwrap();
+
+        let reopened = Dag::open_store(Box::new(shared.clone())).unwrap();
+        assert!(reopened.get("C").is_none());
+        assert_eq!(format!("{:?}", reopened.get("A").unwrap().borrow().data), "10");
+        assert_eq!(format!("{:?}", reopened.get("B").unwrap().borrow().data), "\"two\"");
+        assert_eq!(reopened.successors("A"), vec!["B".to_string()]);
+        assert!(reopened.invalidated.contains("A"));
+        assert!(!reopened.has_unflushed_changes());
+    }
+
+    #[test]
+    fn unflushed_changes_are_not_persisted() {
+        let shared = Shared::default();
+        let mut dag = Dag::new();
+        dag.attach_store(Box::new(shared.clone()));
+        dag.add("A", 1);
+        dag.flush().unwrap();
+        dag.add("B", 2);
+
+        let reopened = Dag::open_store_with(Box::new(shared), |text| Box::new(text.parse::<i32>().unwrap())).unwrap();
+        assert!(reopened.get("A").is_some());
+        assert!(reopened.get("B").is_none());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeSet;
+
+use crate::{Dag, DagError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportPolicy {
+    // Nodes the source doesn't mention are left alone unless this is set.
+    pub remove_absent_nodes: bool,
+    // Edges leaving a node the source describes, but missing from the source.
+    pub remove_absent_edges: bool,
+}
+
+impl Default for ImportPolicy {
+    // The source is taken as authoritative for the nodes it describes, but
+    // never deletes nodes it simply doesn't know about.
+    fn default() -> Self {
+        ImportPolicy {
+            remove_absent_nodes: false,
+            remove_absent_edges: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added_nodes: Vec<String>,
+    pub updated_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<(String, String)>,
+    pub reweighted_edges: Vec<(String, String)>,
+    pub removed_edges: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.updated_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.reweighted_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl Dag {
+    // Merges `source` into this graph, taking over its payloads. A node whose
+    // payload renders the same as before is left untouched, so a repeated
+    // sync of an unchanged source invalidates nothing and reports nothing.
+    pub fn import(&mut self, source: Dag, policy: ImportPolicy) -> Result<ImportReport, DagError> {
+        let topology = source.topology();
+        let keys: Vec<String> = topology.keys().map(|key| self.resolve_key(key).into_owned()).collect();
+        let new_keys: Vec<&str> = keys.iter().map(|key| key.as_str()).filter(|key| self.get(key).is_none()).collect();
+        self.check_node_quota(&new_keys).map_err(|err| err.during("import"))?;
+        // Reweighting relinks in place, so only missing edges count.
+        let mut edge_sources: Vec<&str> = vec![];
+        for (source_key, from) in topology.keys().zip(keys.iter()) {
+            for (source_to, _) in topology.successors(source_key) {
+                if self.edge_weight(from, source_to).is_none() {
+                    edge_sources.push(from);
+                }
+            }
+        }
+        self.check_edge_quota(&edge_sources).map_err(|err| err.during("import"))?;
+
+        let mut report = ImportReport::default();
+        for (source_key, key) in topology.keys().zip(keys.iter()) {
+            let node = source.get(source_key).expect("Topology node missing from source");
+            let data = std::mem::replace(&mut node.borrow_mut().data, Box::new(()));
+            match self.get(key) {
+                None => {
+                    self.insert_boxed(key, data);
+                    report.added_nodes.push(key.clone());
+                },
+                Some(existing) => {
+                    let changed = format!("{:?}", existing.borrow().data) != format!("{:?}", data);
+                    if changed {
+                        self.update_boxed(key, data);
+                        report.updated_nodes.push(key.clone());
+                    }
+                },
+            }
+        }
+
+        // Soft edges count too, though `successors` skips them.
+        let existing_edges = self.topology();
+        for (source_key, from) in topology.keys().zip(keys.iter()) {
+            let mut wanted: BTreeSet<String> = BTreeSet::new();
+            for (source_to, weight) in topology.successors(source_key) {
+                let to = self.resolve_key(source_to).into_owned();
+                match self.edge_weight(from, &to) {
+                    None => {
+                        self.link(from, &to, *weight);
+                        report.added_edges.push((from.clone(), to.clone()));
+                    },
+                    Some(existing) if existing != *weight => {
+                        self.unlink(from, &to);
+                        self.link(from, &to, *weight);
+                        report.reweighted_edges.push((from.clone(), to.clone()));
+                    },
+                    Some(_) => {},
+                }
+                let soft = topology.is_soft(source_key, source_to);
+                if self.is_soft_edge(from, &to) != soft {
+                    self.set_edge_soft(from, &to, soft);
+                }
+                wanted.insert(to);
+            }
+            if policy.remove_absent_edges {
+                for (to, _) in existing_edges.successors(from) {
+                    if !wanted.contains(to) && self.unlink(from, to) {
+                        report.removed_edges.push((from.clone(), to.clone()));
+                    }
+                }
+            }
+        }
+
+        if policy.remove_absent_nodes {
+            let present: BTreeSet<&String> = keys.iter().collect();
+            let absent: Vec<String> = self.index.iter().filter(|key| !present.contains(key)).cloned().collect();
+            for key in absent {
+                self.invalidated.remove(&key);
+                self.remove(&key);
+                report.removed_nodes.push(key);
+            }
+        }
+        report.added_nodes.sort();
+        report.updated_nodes.sort();
+        report.added_edges.sort();
+        report.reweighted_edges.sort();
+        report.removed_edges.sort();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, Limits};
+
+    fn catalog(tables: &[(&str, i32)], edges: &[(&str, &str, i32)]) -> Dag {
+        let mut dag = Dag::new();
+        for (key, version) in tables {
+            dag.add(key, *version);
+        }
+        for (from, to, weight) in edges {
+            dag.link(from, to, *weight);
+        }
+        dag
+    }
+
+    #[test]
+    fn repeated_syncs_report_only_changes() {
+        let mut dag = Dag::new();
+        let current = || catalog(&[("orders", 1), ("users", 1)], &[("users", "orders", 1)]);
+        let first = dag.import(current(), ImportPolicy::default()).unwrap();
+        assert_eq!(first.added_nodes, vec!["orders", "users"]);
+        assert_eq!(first.added_edges, vec![("users".to_string(), "orders".to_string())]);
+        let again = dag.import(current(), ImportPolicy::default()).unwrap();
+        assert!(again.is_empty());
+
+        dag.add("local", 0);
+        dag.link("orders", "local", 1);
+        let next = catalog(&[("orders", 2), ("users", 1), ("items", 1)], &[("users", "orders", 3), ("items", "orders", 1)]);
+        let report = dag.import(next, ImportPolicy::default()).unwrap();
+        assert_eq!(report.added_nodes, vec!["items"]);
+        assert_eq!(report.updated_nodes, vec!["orders"]);
+        assert_eq!(report.reweighted_edges, vec![("users".to_string(), "orders".to_string())]);
+        assert_eq!(report.removed_edges, vec![("orders".to_string(), "local".to_string())]);
+        assert!(dag.invalidated.contains("orders") && !dag.invalidated.contains("users"));
+        assert_eq!(dag.edge_weight("users", "orders"), Some(3));
+    }
+
+    #[test]
+    fn absent_nodes_go_only_when_asked() {
+        let mut dag = catalog(&[("orders", 1), ("stale", 1)], &[("orders", "stale", 1)]);
+        let keep_edges = ImportPolicy { remove_absent_edges: false, ..ImportPolicy::default() };
+        let report = dag.import(catalog(&[("orders", 1)], &[]), keep_edges).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(dag.successors("orders"), vec!["stale".to_string()]);
+
+        let remove_nodes = ImportPolicy { remove_absent_nodes: true, ..keep_edges };
+        let report = dag.import(catalog(&[("orders", 1)], &[]), remove_nodes).unwrap();
+        assert_eq!(report.removed_nodes, vec!["stale"]);
+        assert!(dag.get("stale").is_none());
+    }
+
+    #[test]
+    fn imports_respect_the_edge_quota() {
+        let mut dag = catalog(&[("orders", 1), ("users", 1)], &[]);
+        dag.set_limits(Limits { max_edges: Some(1), ..Limits::default() });
+        let source = catalog(&[("orders", 2), ("users", 1)], &[("users", "orders", 1), ("orders", "users", 1)]);
+        let err = dag.import(source, ImportPolicy::default()).unwrap_err();
+        assert!(matches!(err.root(), DagError::QuotaExceeded { limit: Limit::Edges, .. }));
+        assert_eq!(err.operation(), Some("import"));
+        assert_eq!(dag.edge_count(), 0);
+        assert!(!dag.invalidated.contains("orders"));
+    }
+
+    #[test]
+    fn soft_edges_keep_their_flag() {
+        let mut source = catalog(&[("orders", 1), ("users", 1), ("audit", 1)], &[("users", "orders", 1), ("orders", "audit", 1)]);
+        source.set_edge_soft("users", "orders", true);
+        let mut dag = catalog(&[("audit", 1)], &[]);
+        dag.import(source, ImportPolicy::default()).unwrap();
+        assert!(dag.is_soft_edge("users", "orders"));
+
+        dag.set_edge_soft("orders", "audit", true);
+        let report = dag.import(catalog(&[("orders", 1), ("audit", 1)], &[]), ImportPolicy::default()).unwrap();
+        assert_eq!(report.removed_edges, vec![("orders".to_string(), "audit".to_string())]);
+    }
+}
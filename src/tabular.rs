@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::Read;
+
+use csv::StringRecord;
+
+use crate::{Dag, DagError, Limits};
+
+// Which columns hold what. `payload` lists the node columns kept on each
+// node; leaving it empty keeps every column except the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvMapping {
+    pub key: String,
+    pub payload: Vec<String>,
+    pub from: String,
+    pub to: String,
+    // Edges weigh 1 without a weight column.
+    pub weight: Option<String>,
+    pub delimiter: u8,
+    // Set on the imported graph and enforced while it is built.
+    pub limits: Limits,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            key: "key".to_string(),
+            payload: vec![],
+            from: "from".to_string(),
+            to: "to".to_string(),
+            weight: None,
+            delimiter: b',',
+            limits: Limits::default(),
+        }
+    }
+}
+
+// Payload of a node imported without a decoder: the mapped columns by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvRow(pub BTreeMap<String, String>);
+
+impl CsvRow {
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.0.get(column).map(|value| value.as_str())
+    }
+}
+
+fn csv_error(source: &str, line: u64, detail: impl std::fmt::Display) -> DagError {
+    DagError::Import(format!("{} line {}: {}", source, line, detail))
+}
+
+fn column(headers: &StringRecord, source: &str, name: &str) -> Result<usize, DagError> {
+    headers.iter().position(|header| header == name)
+        .ok_or_else(|| DagError::Import(format!("{} has no {} column", source, name)))
+}
+
+fn rows<R: Read>(reader: R, source: &'static str, mapping: &CsvMapping)
+    -> Result<(StringRecord, Vec<(u64, StringRecord)>), DagError> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(mapping.delimiter).trim(csv::Trim::All).from_reader(reader);
+    let headers = reader.headers().map_err(|err| DagError::Import(format!("{}: {}", source, err)))?.clone();
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|err| DagError::Import(format!("{}: {}", source, err)))?;
+        rows.push((record.position().map(|position| position.line()).unwrap_or_default(), record));
+    }
+    Ok((headers, rows))
+}
+
+impl Dag {
+    pub fn from_csv<N, E>(nodes: N, edges: E, mapping: &CsvMapping) -> Result<Dag, DagError> where N: Read, E: Read {
+        Dag::from_csv_with(nodes, edges, mapping, Ok)
+    }
+
+    // As `from_csv`, with `decode` turning each node's mapped columns into its
+    // payload. Nothing is returned unless every row imports.
+    pub fn from_csv_with<N, E, T, F>(nodes: N, edges: E, mapping: &CsvMapping, decode: F) -> Result<Dag, DagError>
+        where N: Read, E: Read, T: Debug + 'static, F: FnMut(CsvRow) -> Result<T, DagError> {
+        import(nodes, edges, mapping, decode).map_err(|err| err.during("from_csv"))
+    }
+}
+
+fn import<N, E, T, F>(nodes: N, edges: E, mapping: &CsvMapping, mut decode: F) -> Result<Dag, DagError>
+    where N: Read, E: Read, T: Debug + 'static, F: FnMut(CsvRow) -> Result<T, DagError> {
+    let mut dag = Dag::with_limits(mapping.limits);
+    let (headers, node_rows) = rows(nodes, "nodes", mapping)?;
+    let key = column(&headers, "nodes", &mapping.key)?;
+    let payload: Vec<(String, usize)> = if mapping.payload.is_empty() {
+        headers.iter().enumerate()
+            .filter(|(index, _)| *index != key)
+            .map(|(index, name)| (name.to_string(), index))
+            .collect()
+    } else {
+        mapping.payload.iter()
+            .map(|name| column(&headers, "nodes", name).map(|index| (name.clone(), index)))
+            .collect::<Result<_, _>>()?
+    };
+    for (line, record) in node_rows {
+        let node_key = &record[key];
+        if dag.get(node_key).is_some() {
+            return Err(DagError::DuplicateNode(node_key.to_string()));
+        }
+        let row = CsvRow(payload.iter()
+            .map(|(name, index)| (name.clone(), record.get(*index).unwrap_or_default().to_string()))
+            .collect());
+        let data = decode(row).map_err(|err| csv_error("nodes", line, err))?;
+        dag.check_node_quota(&[node_key])?;
+        dag.add(node_key, data);
+    }
+
+    let (headers, edge_rows) = rows(edges, "edges", mapping)?;
+    let (from, to) = (column(&headers, "edges", &mapping.from)?, column(&headers, "edges", &mapping.to)?);
+    let weight = mapping.weight.as_ref().map(|name| column(&headers, "edges", name)).transpose()?;
+    for (line, record) in edge_rows {
+        for endpoint in [&record[from], &record[to]] {
+            if dag.get(endpoint).is_none() {
+                return Err(DagError::NodeNotFound(endpoint.to_string()));
+            }
+        }
+        let weight = match weight {
+            Some(index) => record[index].parse()
+                .map_err(|_| csv_error("edges", line, format!("invalid weight {:?}", &record[index])))?,
+            None => 1,
+        };
+        dag.check_edge_quota(&[&record[from]])?;
+        dag.link(&record[from], &record[to], weight);
+    }
+    Ok(dag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Limit;
+
+    const NODES: &str = "id,name,cost\nextract,Extract,3\nclean, Clean ,1\nreport,Report,2\n";
+    const EDGES: &str = "src,dst,w\nextract,clean,5\nclean,report,1\n";
+
+    fn mapping() -> CsvMapping {
+        CsvMapping {
+            key: "id".to_string(),
+            payload: vec!["cost".to_string()],
+            from: "src".to_string(),
+            to: "dst".to_string(),
+            weight: Some("w".to_string()),
+            ..CsvMapping::default()
+        }
+    }
+
+    #[test]
+    fn two_tables_become_one_graph() {
+        let dag = Dag::from_csv(NODES.as_bytes(), EDGES.as_bytes(), &mapping()).unwrap();
+        assert_eq!(dag.node_count(), 3);
+        assert_eq!(dag.edge_weight("extract", "clean"), Some(5));
+        assert_eq!(dag.successors("clean"), vec!["report".to_string()]);
+
+        let typed = Dag::from_csv_with(NODES.as_bytes(), EDGES.as_bytes(), &mapping(), |row| {
+            row.get("cost").unwrap_or_default().parse::<u32>().map_err(|err| DagError::Import(err.to_string()))
+        }).unwrap();
+        assert_eq!(format!("{:?}", typed.get("extract").unwrap().borrow().data), "3");
+    }
+
+    #[test]
+    fn bad_rows_name_their_line() {
+        let edges = "src,dst,w\nextract,clean,5\nclean,report,heavy\n";
+        let err = Dag::from_csv(NODES.as_bytes(), edges.as_bytes(), &mapping()).err().unwrap();
+        assert_eq!(err.operation(), Some("from_csv"));
+        assert_eq!(err.root(), &DagError::Import("edges line 3: invalid weight \"heavy\"".to_string()));
+
+        let edges = "src,dst,w\nextract,load,1\n";
+        let err = Dag::from_csv(NODES.as_bytes(), edges.as_bytes(), &mapping()).err().unwrap();
+        assert_eq!(err.keys(), vec!["load"]);
+    }
+
+    #[test]
+    fn imports_respect_the_mapped_limits() {
+        let limited = CsvMapping { limits: Limits { max_edges: Some(1), ..Limits::default() }, ..mapping() };
+        let err = Dag::from_csv(NODES.as_bytes(), EDGES.as_bytes(), &limited).err().unwrap();
+        assert_eq!(err.root(), &DagError::QuotaExceeded { limit: Limit::Edges, maximum: 1, key: "clean".to_string() });
+        assert_eq!(err.operation(), Some("from_csv"));
+    }
+}
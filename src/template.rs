@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Debug;
+
+use crate::{Dag, DagError, NodeData};
+
+pub type TemplateParams = BTreeMap<String, String>;
+
+type PayloadFactory = Box<dyn Fn(&TemplateParams) -> Box<NodeData>>;
+
+// (from, to) keys, in the order `Dag::add_edge_directed` takes them.
+type RenderedEdge = (String, String);
+
+// Keys are patterns with `{name}` parameters, e.g. "{customer}/ingest".
+pub struct DagTemplate {
+    nodes: Vec<(String, PayloadFactory)>,
+    edges: Vec<(String, String)>,
+}
+
+enum Segment<'a> {
+    Literal(&'a str),
+    Parameter(&'a str),
+}
+
+fn segments(pattern: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        match rest[open..].find('}') {
+            Some(close) => {
+                segments.push(Segment::Literal(&rest[..open]));
+                segments.push(Segment::Parameter(&rest[open + 1..open + close]));
+                rest = &rest[open + close + 1..];
+            },
+            None => break,
+        }
+    }
+    segments.push(Segment::Literal(rest));
+    segments
+}
+
+fn render(pattern: &str, params: &TemplateParams) -> Result<String, DagError> {
+    let mut key = String::new();
+    for segment in segments(pattern) {
+        match segment {
+            Segment::Literal(text) => key.push_str(text),
+            Segment::Parameter(name) => match params.get(name) {
+                Some(value) => key.push_str(value),
+                None => return Err(DagError::MissingParameter {
+                    pattern: pattern.to_string(),
+                    parameter: name.to_string(),
+                }),
+            },
+        }
+    }
+    Ok(key)
+}
+
+impl DagTemplate {
+    pub fn new() -> DagTemplate {
+        DagTemplate { nodes: vec![], edges: vec![] }
+    }
+
+    pub fn node<T, F>(&mut self, key_pattern: &str, factory: F) -> &mut DagTemplate
+        where T: Debug + 'static, F: Fn(&TemplateParams) -> T + 'static {
+        self.nodes.push((key_pattern.to_string(), Box::new(move |params| Box::new(factory(params)))));
+        self
+    }
+
+    // Same argument order as Dag::add_edge_directed.
+    pub fn edge(&mut self, from_pattern: &str, to_pattern: &str) -> &mut DagTemplate {
+        self.edges.push((from_pattern.to_string(), to_pattern.to_string()));
+        self
+    }
+
+    pub fn parameters(&self) -> BTreeSet<String> {
+        let patterns = self.nodes.iter().map(|(pattern, _)| pattern)
+            .chain(self.edges.iter().flat_map(|(from, to)| [from, to]));
+        patterns
+            .flat_map(|pattern| segments(pattern).into_iter().filter_map(|segment| match segment {
+                Segment::Parameter(name) => Some(name.to_string()),
+                Segment::Literal(_) => None,
+            }))
+            .collect()
+    }
+
+    pub fn instantiate(&self, params: &TemplateParams) -> Result<Dag, DagError> {
+        let mut dag = Dag::new();
+        self.instantiate_into(&mut dag, params)?;
+        Ok(dag)
+    }
+
+    // Stamps one instance into an existing graph. Edge patterns may also name
+    // nodes already in `dag`, which lets instances hang off shared nodes.
+    // Nothing is added unless the whole instance is valid.
+    pub fn instantiate_into(&self, dag: &mut Dag, params: &TemplateParams) -> Result<Vec<String>, DagError> {
+        let (keys, edges) = self.plan(dag, params).map_err(|err| err.during("instantiate_into"))?;
+        for ((_, factory), key) in self.nodes.iter().zip(keys.iter()) {
+            dag.insert_boxed(key, factory(params));
+        }
+        for (from_key, to_key) in edges.iter() {
+            dag.add_edge_directed(from_key, to_key);
+        }
+        Ok(keys)
+    }
+
+    // Rendered node keys and edges of one instance, once it is known to fit.
+    fn plan(&self, dag: &Dag, params: &TemplateParams) -> Result<(Vec<String>, Vec<RenderedEdge>), DagError> {
+        let keys = self.nodes.iter()
+            .map(|(pattern, _)| render(pattern, params))
+            .collect::<Result<Vec<String>, DagError>>()?;
+        let mut fresh: HashSet<&str> = HashSet::new();
+        for key in keys.iter() {
+            if dag.get(key).is_some() || !fresh.insert(key) {
+                return Err(DagError::DuplicateNode(key.clone()));
+            }
+        }
+        let mut edges = vec![];
+        for (from, to) in self.edges.iter() {
+            let (from_key, to_key) = (render(from, params)?, render(to, params)?);
+            for key in [&from_key, &to_key] {
+                if !fresh.contains(key.as_str()) && dag.get(key).is_none() {
+                    return Err(DagError::NodeNotFound(key.clone()));
+                }
+            }
+            edges.push((from_key, to_key));
+        }
+        let key_refs: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+        dag.check_node_quota(&key_refs)?;
+        let edge_sources: Vec<&str> = edges.iter().map(|(from_key, _)| from_key.as_str()).collect();
+        dag.check_edge_quota(&edge_sources)?;
+        Ok((keys, edges))
+    }
+}
+
+impl Default for DagTemplate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> TemplateParams {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn pipeline() -> DagTemplate {
+        let mut template = DagTemplate::new();
+        template
+            .node("{customer}/extract", |params| format!("extract {}", params["date"]))
+            .node("{customer}/load", |_| "load")
+            .edge("{customer}/extract", "{customer}/load");
+        template
+    }
+
+    #[test]
+    fn instances_are_stamped_from_parameters() {
+        let template = pipeline();
+        assert_eq!(template.parameters(), ["customer".to_string()].into_iter().collect());
+
+        let dag = template.instantiate(&params(&[("customer", "acme"), ("date", "2024-01-01")])).unwrap();
+        assert_eq!(dag.successors("acme/extract"), vec!["acme/load".to_string()]);
+        assert_eq!(format!("{:?}", dag.get("acme/extract").unwrap().borrow().data), "\"extract 2024-01-01\"");
+    }
+
+    #[test]
+    fn instantiation_is_all_or_nothing() {
+        let template = pipeline();
+        let mut dag = Dag::new();
+        template.instantiate_into(&mut dag, &params(&[("customer", "a"), ("date", "d")])).unwrap();
+        assert!(matches!(
+            template.instantiate_into(&mut dag, &params(&[("customer", "a"), ("date", "d")])),
+            Err(err) if matches!(err.root(), DagError::DuplicateNode(key) if key == "a/extract")
+        ));
+        assert_eq!(
+            template.instantiate_into(&mut dag, &params(&[("date", "d")])),
+            Err(DagError::MissingParameter { pattern: "{customer}/extract".to_string(), parameter: "customer".to_string() }
+                .during("instantiate_into"))
+        );
+        assert_eq!(dag.nodes_with_prefix(""), vec!["a/extract", "a/load"]);
+    }
+}
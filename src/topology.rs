@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::{borrow, Dag, DagError};
+
+// Key-level snapshot of the graph structure. Algorithms that only care about
+// shape run against this instead of walking the RefCell nodes directly.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Topology {
+    successors: BTreeMap<String, Vec<(String, i32)>>,
+    soft: BTreeSet<(String, String)>,
+}
+
+impl Topology {
+    pub(crate) fn insert_node(&mut self, key: &str) {
+        self.successors.entry(key.to_string()).or_default();
+    }
+
+    pub(crate) fn insert_edge(&mut self, from_key: &str, to_key: &str, weight: i32) {
+        self.insert_node(to_key);
+        self.successors.entry(from_key.to_string()).or_default().push((to_key.to_string(), weight));
+    }
+
+    pub(crate) fn remove_node(&mut self, key: &str) -> bool {
+        let removed = self.successors.remove(key).is_some();
+        if removed {
+            for edges in self.successors.values_mut() {
+                edges.retain(|(to_key, _)| to_key != key);
+            }
+            self.soft.retain(|(from_key, to_key)| from_key != key && to_key != key);
+        }
+        removed
+    }
+
+    pub(crate) fn mark_soft(&mut self, from_key: &str, to_key: &str) {
+        self.soft.insert((from_key.to_string(), to_key.to_string()));
+    }
+
+    pub(crate) fn is_soft(&self, from_key: &str, to_key: &str) -> bool {
+        self.soft.contains(&(from_key.to_string(), to_key.to_string()))
+    }
+
+    pub(crate) fn hard_successors<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.successors(key).iter()
+            .filter(move |(to_key, _)| !self.is_soft(key, to_key))
+            .map(|(to_key, _)| to_key.as_str())
+    }
+
+    pub(crate) fn remove_edge(&mut self, from_key: &str, to_key: &str) -> bool {
+        match self.successors.get_mut(from_key) {
+            Some(edges) => {
+                let before = edges.len();
+                edges.retain(|(key, _)| key != to_key);
+                self.soft.remove(&(from_key.to_string(), to_key.to_string()));
+                edges.len() != before
+            },
+            None => false,
+        }
+    }
+
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.successors.contains_key(key)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &String> {
+        self.successors.keys()
+    }
+
+    pub(crate) fn successors(&self, key: &str) -> &[(String, i32)] {
+        self.successors.get(key).map(|edges| edges.as_slice()).unwrap_or(&[])
+    }
+
+    pub(crate) fn predecessors(&self) -> BTreeMap<String, Vec<(String, i32)>> {
+        let mut predecessors: BTreeMap<String, Vec<(String, i32)>> =
+            self.keys().map(|key| (key.clone(), vec![])).collect();
+        for (from_key, edges) in self.successors.iter() {
+            for (to_key, weight) in edges {
+                predecessors.entry(to_key.clone()).or_default().push((from_key.clone(), *weight));
+            }
+        }
+        predecessors
+    }
+
+    pub(crate) fn in_degrees(&self) -> BTreeMap<String, usize> {
+        let mut degrees: BTreeMap<String, usize> = self.keys().map(|key| (key.clone(), 0)).collect();
+        for edges in self.successors.values() {
+            for (to_key, _) in edges {
+                *degrees.entry(to_key.clone()).or_default() += 1;
+            }
+        }
+        degrees
+    }
+
+    // Kahn's algorithm, always releasing the smallest ready key first so the
+    // order is stable across runs.
+    pub(crate) fn topological_order(&self) -> Result<Vec<String>, DagError> {
+        self.topological_order_by(|_| ())
+    }
+
+    // As `topological_order`, releasing the ready node with the smallest
+    // rank first and falling back to the smallest key.
+    pub(crate) fn topological_order_by<R: Ord>(&self, rank: impl Fn(&str) -> R) -> Result<Vec<String>, DagError> {
+        let mut degrees = self.in_degrees();
+        let mut ready: BTreeSet<(R, String)> = degrees.iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(key, _)| (rank(key), key.clone()))
+            .collect();
+        let mut order = Vec::with_capacity(degrees.len());
+        while let Some((_, key)) = ready.pop_first() {
+            for (to_key, _) in self.successors(&key) {
+                let degree = degrees.get_mut(to_key).expect("Topology edge to unknown node");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.insert((rank(to_key), to_key.clone()));
+                }
+            }
+            order.push(key);
+        }
+        if order.len() < degrees.len() {
+            let path = self.find_cycle().expect("Kahn's algorithm stalled without a cycle");
+            return Err(DagError::CycleDetected { path });
+        }
+        Ok(order)
+    }
+
+    // One cycle as the nodes along it in edge order; the last node links
+    // back to the first. The search visits keys in order, so the same graph
+    // always reports the same cycle.
+    pub(crate) fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut finished: HashSet<&str> = HashSet::new();
+        for root in self.keys() {
+            if finished.contains(root.as_str()) {
+                continue;
+            }
+            let mut stack: Vec<(&str, usize)> = vec![(root, 0)];
+            let mut on_stack: HashSet<&str> = HashSet::from([root.as_str()]);
+            while let Some(&(key, next)) = stack.last() {
+                match self.successors(key).get(next) {
+                    Some((to_key, _)) => {
+                        stack.last_mut().expect("Stack checked above").1 += 1;
+                        if on_stack.contains(to_key.as_str()) {
+                            let start = stack.iter().position(|(open, _)| *open == to_key).expect("Open node off the stack");
+                            return Some(stack[start..].iter().map(|(open, _)| open.to_string()).collect());
+                        }
+                        if !finished.contains(to_key.as_str()) {
+                            on_stack.insert(to_key);
+                            stack.push((to_key, 0));
+                        }
+                    },
+                    None => {
+                        on_stack.remove(key);
+                        finished.insert(key);
+                        stack.pop();
+                    },
+                }
+            }
+        }
+        None
+    }
+
+    // Shortest path by edge count, both ends included.
+    pub(crate) fn path(&self, from_key: &str, to_key: &str) -> Option<Vec<String>> {
+        let mut parents: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from_key]);
+        let mut seen: HashSet<&str> = HashSet::from([from_key]);
+        while let Some(key) = queue.pop_front() {
+            if key == to_key {
+                let mut path = vec![key.to_string()];
+                let mut cursor = key;
+                while let Some(parent) = parents.get(cursor) {
+                    path.push(parent.to_string());
+                    cursor = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, _) in self.successors(key) {
+                if seen.insert(next) {
+                    parents.insert(next, key);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    pub(crate) fn reaches(&self, from_key: &str, to_key: &str) -> bool {
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut stack = vec![from_key];
+        while let Some(key) = stack.pop() {
+            if key == to_key {
+                return true;
+            }
+            if visited.insert(key) {
+                stack.extend(self.successors(key).iter().map(|(next, _)| next.as_str()));
+            }
+        }
+        false
+    }
+
+    // Heaviest path by summed edge weight.
+    pub(crate) fn critical_path(&self) -> Result<Vec<String>, DagError> {
+        let mut best: BTreeMap<String, (i64, Option<String>)> = BTreeMap::new();
+        for key in self.topological_order()? {
+            let (distance, _) = *best.entry(key.clone()).or_insert((0, None));
+            for (to_key, weight) in self.successors(&key) {
+                let candidate = distance + *weight as i64;
+                let entry = best.entry(to_key.clone()).or_insert((i64::MIN, None));
+                if candidate > entry.0 {
+                    *entry = (candidate, Some(key.clone()));
+                }
+            }
+        }
+        let mut cursor = best.iter()
+            .max_by(|(a_key, (a, _)), (b_key, (b, _))| a.cmp(b).then(b_key.cmp(a_key)))
+            .map(|(key, _)| key.clone());
+        let mut path = vec![];
+        while let Some(key) = cursor {
+            cursor = best[&key].1.clone();
+            path.push(key);
+        }
+        path.reverse();
+        Ok(path)
+    }
+}
+
+impl Dag {
+    pub fn is_reachable(&self, from_key: &str, to_key: &str) -> bool {
+        self.get(from_key).is_some() && self.get(to_key).is_some() && self.select_reaches(from_key, to_key)
+    }
+
+    pub fn critical_path(&self) -> Result<Vec<String>, DagError> {
+        self.try_topology("critical_path")?.critical_path()
+    }
+
+    // Edges added without a cycle check can leave the graph cyclic; this
+    // names the nodes of one such cycle, in edge order.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        self.topology().find_cycle()
+    }
+
+    pub(crate) fn topology(&self) -> Topology {
+        borrow::or_panic(self.try_topology("topology"))
+    }
+
+    pub(crate) fn try_topology(&self, operation: &'static str) -> Result<Topology, DagError> {
+        let mut topology = Topology::default();
+        for key in self.nodes.borrow().keys() {
+            topology.insert_node(key);
+        }
+        for (key, node) in self.nodes.borrow().iter() {
+            for edge in borrow::read(node, key, operation)?.edges.iter() {
+                if self.live_target(edge).is_some() {
+                    topology.insert_edge(key, &edge.to_key, edge.weight);
+                    if edge.soft {
+                        topology.mark_soft(key, &edge.to_key);
+                    }
+                }
+            }
+        }
+        Ok(topology)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topological_order_is_stable() {
+        let mut topology = Topology::default();
+        topology.insert_edge("b", "c", 1);
+        topology.insert_edge("a", "c", 1);
+        assert_eq!(topology.topological_order().unwrap(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn topological_order_reports_cycles() {
+        let mut topology = Topology::default();
+        topology.insert_edge("a", "b", 1);
+        topology.insert_edge("b", "a", 1);
+        topology.insert_edge("b", "c", 1);
+        topology.insert_edge("c", "b", 1);
+        assert_eq!(
+            topology.topological_order(),
+            Err(DagError::CycleDetected { path: vec!["a".to_string(), "b".to_string()] })
+        );
+        assert_eq!(topology.path("a", "c").unwrap(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn find_cycle_names_the_nodes_involved() {
+        let mut dag = Dag::new();
+        for key in ["A", "B", "C", "D"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("A", "B");
+        dag.add_edge_directed("B", "C");
+        dag.add_edge_directed("C", "D");
+        assert_eq!(dag.find_cycle(), None);
+        dag.add_edge_directed("D", "B");
+        assert_eq!(dag.find_cycle(), Some(vec!["B".to_string(), "C".to_string(), "D".to_string()]));
+    }
+
+    #[test]
+    fn critical_path_follows_heaviest_edges() {
+        let mut topology = Topology::default();
+        topology.insert_edge("a", "b", 1);
+        topology.insert_edge("a", "c", 5);
+        topology.insert_edge("b", "d", 1);
+        topology.insert_edge("c", "d", 1);
+        assert_eq!(topology.critical_path().unwrap(), vec!["a", "c", "d"]);
+    }
+}
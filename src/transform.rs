@@ -0,0 +1,352 @@
+use std::fmt::{Debug, Display};
+
+use crate::{Dag, DagError, NodeData};
+
+// Payload of a node produced by `collapse_chains`: the merged keys in chain
+// order with their original payloads and the weights of the edges between.
+#[derive(Debug)]
+pub struct CollapsedChain {
+    pub keys: Vec<String>,
+    pub payloads: Vec<Box<NodeData>>,
+    pub weights: Vec<i32>,
+}
+
+impl Dag {
+    pub fn add_between<T>(
+        &mut self,
+        new_key: &str,
+        data: T,
+        predecessors: &[&str],
+        successors: &[&str],
+        remove_direct: bool,
+    ) -> Result<(), DagError> where T: Debug + 'static {
+        self.check_between(new_key, predecessors, successors).map_err(|err| err.during("add_between"))?;
+        self.add(new_key, data);
+        for predecessor in predecessors {
+            if remove_direct {
+                for successor in successors {
+                    self.unlink(predecessor, successor);
+                }
+            }
+            self.link(predecessor, new_key, 1);
+        }
+        for successor in successors {
+            self.link(new_key, successor, 1);
+        }
+        Ok(())
+    }
+
+    // Replaces `key` with one copy per parameter, keyed "key[param]". Each
+    // copy inherits the original's incoming and outgoing edges.
+    pub fn expand<P, T, F>(&mut self, key: &str, params: &[P], factory: F) -> Result<Vec<String>, DagError>
+        where P: Display, T: Debug + 'static, F: FnMut(&P) -> T {
+        self.expand_boxed(key, params, factory, None).map_err(|err| err.during("expand"))
+    }
+
+    // As `expand`, but the copies feed a single gather node which takes over
+    // the original's outgoing edges.
+    pub fn expand_gathered<P, T, F, G>(&mut self, key: &str, params: &[P], factory: F, gather_key: &str, gather_data: G)
+        -> Result<Vec<String>, DagError>
+        where P: Display, T: Debug + 'static, F: FnMut(&P) -> T, G: Debug + 'static {
+        self.expand_boxed(key, params, factory, Some((gather_key, Box::new(gather_data))))
+            .map_err(|err| err.during("expand_gathered"))
+    }
+
+    // Contracts every edge u -> v where u has no other outgoing edge and v has
+    // no other incoming one. Each chain is merged into its head node, whose
+    // payload becomes a CollapsedChain and which is invalidated, as by
+    // `update`. Returns the merged chains.
+    pub fn collapse_chains(&mut self) -> Result<Vec<Vec<String>>, DagError> {
+        let topology = self.topology();
+        let in_degrees = topology.in_degrees();
+        let predecessors = topology.predecessors();
+        let contractible = |key: &str| -> Option<(String, i32)> {
+            match topology.successors(key) {
+                [(next, weight)] if in_degrees[next] == 1 => Some((next.clone(), *weight)),
+                _ => None,
+            }
+        };
+        let mut chains = vec![];
+        for key in topology.topological_order().map_err(|err| err.during("collapse_chains"))? {
+            let is_link_target = match predecessors[&key].as_slice() {
+                [(predecessor, _)] => contractible(predecessor).is_some(),
+                _ => false,
+            };
+            if is_link_target || contractible(&key).is_none() {
+                continue;
+            }
+            let mut chain = vec![(key.clone(), 0)];
+            while let Some(next) = contractible(&chain.last().expect("Chain emptied").0) {
+                chain.push(next);
+            }
+            chains.push(chain);
+        }
+
+        for chain in chains.iter() {
+            let head = chain[0].0.clone();
+            let tail = chain.last().expect("Chain emptied").0.clone();
+            let mut payloads = vec![];
+            for (key, _) in chain.iter() {
+                let node = self.get(key).expect("Chain node missing from graph");
+                let payload = std::mem::replace(&mut node.borrow_mut().data, Box::new(()));
+                payloads.push(payload);
+            }
+            let outgoing = topology.successors(&tail).to_vec();
+            self.unlink(&head, &chain[1].0);
+            for (key, _) in chain.iter().skip(1) {
+                self.invalidated.remove(key);
+                self.remove(key);
+            }
+            for (successor, weight) in outgoing {
+                self.link(&head, &successor, weight);
+                if topology.is_soft(&tail, &successor) {
+                    self.set_edge_soft(&head, &successor, true);
+                }
+            }
+            let merged = CollapsedChain {
+                keys: chain.iter().map(|(key, _)| key.clone()).collect(),
+                payloads,
+                weights: chain.iter().skip(1).map(|(_, weight)| *weight).collect(),
+            };
+            // A new payload, so the head is invalidated like any update.
+            self.update_boxed(&head, Box::new(merged));
+        }
+        Ok(chains.into_iter().map(|chain| chain.into_iter().map(|(key, _)| key).collect()).collect())
+    }
+
+    fn check_between(&self, new_key: &str, predecessors: &[&str], successors: &[&str]) -> Result<(), DagError> {
+        if self.get(new_key).is_some() {
+            return Err(DagError::DuplicateNode(new_key.to_string()));
+        }
+        if let Some(missing) = predecessors.iter().chain(successors).find(|key| self.get(key).is_none()) {
+            return Err(DagError::NodeNotFound(missing.to_string()));
+        }
+        // pred -> new -> succ implies a path pred -> succ, so any path back
+        // from a successor to a predecessor would close a cycle.
+        let topology = self.topology();
+        for successor in successors {
+            for predecessor in predecessors {
+                if let Some(path) = topology.path(successor, predecessor) {
+                    return Err(DagError::WouldCreateCycle {
+                        from: predecessor.to_string(),
+                        to: successor.to_string(),
+                        path,
+                    });
+                }
+            }
+        }
+
+        self.check_node_quota(&[new_key])?;
+        let mut edge_sources: Vec<&str> = predecessors.to_vec();
+        edge_sources.extend(successors.iter().map(|_| new_key));
+        // Checked before direct edges are dropped, so this is conservative
+        // when `remove_direct` is set.
+        self.check_edge_quota(&edge_sources)?;
+        Ok(())
+    }
+
+    fn expand_boxed<P, T, F>(&mut self, key: &str, params: &[P], mut factory: F, gather: Option<(&str, Box<NodeData>)>)
+        -> Result<Vec<String>, DagError>
+        where P: Display, T: Debug + 'static, F: FnMut(&P) -> T {
+        if self.get(key).is_none() {
+            return Err(DagError::NodeNotFound(key.to_string()));
+        }
+        let copies: Vec<String> = params.iter().map(|param| format!("{}[{}]", key, param)).collect();
+        let mut new_keys: Vec<&str> = copies.iter().map(|copy| copy.as_str()).collect();
+        if let Some((gather_key, _)) = gather.as_ref() {
+            new_keys.push(gather_key);
+        }
+        for (index, new_key) in new_keys.iter().enumerate() {
+            if self.get(new_key).is_some() || new_keys[..index].contains(new_key) {
+                return Err(DagError::DuplicateNode(new_key.to_string()));
+            }
+        }
+        self.check_node_quota(&new_keys)?;
+
+        let topology = self.topology();
+        let incoming: Vec<(String, i32)> = topology.predecessors().remove(key).unwrap_or_default();
+        let outgoing: Vec<(String, i32)> = topology.successors(key).to_vec();
+        let mut edge_sources: Vec<&str> = vec![];
+        for copy in copies.iter() {
+            edge_sources.extend(incoming.iter().map(|(predecessor, _)| predecessor.as_str()));
+            match gather.as_ref() {
+                Some(_) => edge_sources.push(copy),
+                None => edge_sources.extend(outgoing.iter().map(|_| copy.as_str())),
+            }
+        }
+        if let Some((gather_key, _)) = gather.as_ref() {
+            edge_sources.extend(outgoing.iter().map(|_| *gather_key));
+        }
+        // Checked before the original's edges are dropped, so this is
+        // conservative, as in `add_between`.
+        self.check_edge_quota(&edge_sources)?;
+
+        let was_invalidated = self.invalidated.remove(key);
+        for (predecessor, _) in incoming.iter() {
+            self.unlink(predecessor, key);
+        }
+        self.remove(key);
+
+        for (param, copy) in params.iter().zip(copies.iter()) {
+            self.insert_boxed(copy, Box::new(factory(param)));
+            for (predecessor, weight) in incoming.iter() {
+                self.link(predecessor, copy, *weight);
+                if topology.is_soft(predecessor, key) {
+                    self.set_edge_soft(predecessor, copy, true);
+                }
+            }
+        }
+        let mut touched = copies.clone();
+        match gather {
+            Some((gather_key, gather_data)) => {
+                self.insert_boxed(gather_key, gather_data);
+                for copy in copies.iter() {
+                    self.link(copy, gather_key, 1);
+                }
+                for (successor, weight) in outgoing.iter() {
+                    self.link(gather_key, successor, *weight);
+                    if topology.is_soft(key, successor) {
+                        self.set_edge_soft(gather_key, successor, true);
+                    }
+                }
+                touched.push(gather_key.to_string());
+            },
+            None => {
+                for copy in copies.iter() {
+                    for (successor, weight) in outgoing.iter() {
+                        self.link(copy, successor, *weight);
+                        if topology.is_soft(key, successor) {
+                            self.set_edge_soft(copy, successor, true);
+                        }
+                    }
+                }
+            },
+        }
+        if was_invalidated {
+            self.invalidated.extend(touched);
+        }
+        Ok(copies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, Limits, Mutation, Provenance};
+
+    #[test]
+    fn node_spliced_between_sets() {
+        let mut dag = Dag::new();
+        dag.add("A", "a");
+        dag.add("B", "b");
+        dag.link("A", "B", 1);
+        dag.add_between("M", "m", &["A"], &["B"], true).unwrap();
+        assert_eq!(dag.successors("A"), vec!["M".to_string()]);
+        assert_eq!(dag.successors("M"), vec!["B".to_string()]);
+    }
+
+    #[test]
+    fn splice_rejects_cycles() {
+        let mut dag = Dag::new();
+        dag.add("A", "a");
+        dag.add("B", "b");
+        dag.link("A", "B", 1);
+        let result = dag.add_between("M", "m", &["B"], &["A"], false);
+        let err = result.unwrap_err();
+        assert_eq!(err.operation(), Some("add_between"));
+        assert_eq!(err.root(), &DagError::WouldCreateCycle {
+            from: "B".to_string(),
+            to: "A".to_string(),
+            path: vec!["A".to_string(), "B".to_string()],
+        });
+        assert_eq!(err.keys(), vec!["B", "A"]);
+        assert!(dag.get("M").is_none());
+    }
+
+    fn etl() -> Dag {
+        let mut dag = Dag::new();
+        for key in ["extract", "transform", "load"] {
+            dag.add(key, key);
+        }
+        dag.link("extract", "transform", 1);
+        dag.link("transform", "load", 1);
+        dag
+    }
+
+    #[test]
+    fn expansion_fans_edges_out_and_in() {
+        let mut dag = etl();
+        let copies = dag.expand("transform", &[1, 2], |partition| format!("part {}", partition)).unwrap();
+        assert_eq!(copies, vec!["transform[1]", "transform[2]"]);
+        assert!(dag.get("transform").is_none());
+        let mut successors = dag.successors("extract");
+        successors.sort();
+        assert_eq!(successors, copies);
+        assert_eq!(dag.successors("transform[2]"), vec!["load".to_string()]);
+    }
+
+    #[test]
+    fn expansion_can_gather() {
+        let mut dag = etl();
+        dag.expand_gathered("transform", &["a", "b"], |_| "part", "gather", "gather").unwrap();
+        assert_eq!(dag.successors("transform[a]"), vec!["gather".to_string()]);
+        assert_eq!(dag.successors("gather"), vec!["load".to_string()]);
+        assert_eq!(dag.layer_assignment(None).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn expansion_keeps_soft_edges_and_quotas() {
+        let mut dag = etl();
+        dag.set_edge_soft("extract", "transform", true);
+        dag.expand("transform", &[1, 2], |_| "part").unwrap();
+        assert!(dag.is_soft_edge("extract", "transform[1]"));
+        assert!(!dag.is_soft_edge("transform[1]", "load"));
+
+        let mut dag = etl();
+        dag.set_limits(Limits { max_out_degree: Some(2), ..Limits::default() });
+        let err = dag.expand("transform", &[1, 2, 3], |_| "part").unwrap_err();
+        assert!(matches!(err.root(), DagError::QuotaExceeded { limit: Limit::OutDegree, key, .. } if key == "extract"));
+        assert!(dag.get("transform").is_some());
+    }
+
+    #[test]
+    fn linear_chains_collapse_into_their_head() {
+        let mut dag = etl();
+        dag.add("report", "report");
+        dag.add("audit", "audit");
+        dag.link("load", "report", 1);
+        dag.link("load", "audit", 2);
+        let chains = dag.collapse_chains().unwrap();
+        assert_eq!(chains, vec![vec!["extract", "transform", "load"]]);
+        assert!(dag.get("transform").is_none());
+        let mut successors = dag.successors("extract");
+        successors.sort();
+        assert_eq!(successors, vec!["audit", "report"]);
+        assert_eq!(dag.edge_weight("extract", "audit"), Some(2));
+        let rendered = format!("{:?}", dag.get("extract").unwrap().borrow().data);
+        assert!(rendered.contains("payloads: [\"extract\", \"transform\", \"load\"]"));
+    }
+
+    #[test]
+    fn collapsed_heads_are_updated() {
+        let mut dag = etl();
+        dag.define_view("all", |_| true);
+        dag.set_provenance(Some(Provenance::new("compact", "run-1")));
+        dag.collapse_chains().unwrap();
+        assert_eq!(dag.dirty_order().unwrap(), vec!["extract"]);
+        assert_eq!(dag.view_dirty("all").unwrap(), vec!["extract"]);
+        assert!(dag.provenance("extract").iter().any(|record| record.mutation == Mutation::UpdateNode));
+    }
+
+    #[test]
+    fn collapsed_heads_keep_soft_edges() {
+        let mut dag = etl();
+        dag.add("report", "report");
+        dag.add("audit", "audit");
+        dag.link("load", "report", 1);
+        dag.add_soft_edge("load", "audit");
+        dag.collapse_chains().unwrap();
+        assert!(dag.is_soft_edge("extract", "audit"));
+        assert!(!dag.is_soft_edge("extract", "report"));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{Dag, Node, NodeStrongRef};
+
+// Walks that keep their visited bitset (one bit per node id) and stack
+// between calls, so once the buffers have grown to fit the graph a walk
+// allocates nothing. Marks persist until `reset`: walking from several
+// roots in a row visits each node once overall.
+pub struct Traverser {
+    visited: Vec<u64>,
+    stack: Vec<NodeStrongRef>,
+}
+
+impl Traverser {
+    pub fn new(dag: &Dag) -> Traverser {
+        Traverser {
+            visited: vec![0; dag.id_bound().div_ceil(64)],
+            stack: Vec::with_capacity(dag.node_count()),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.visited.fill(0);
+    }
+
+    pub fn is_visited(&self, dag: &Dag, key: &str) -> bool {
+        dag.ids.slot(&dag.resolve_key(key)).is_some_and(|slot| self.test(slot))
+    }
+
+    // Depth-first from `start` along every live edge, soft ones included,
+    // like `is_reachable`. Nodes already marked are skipped, along with
+    // everything only reachable through them. Returns how many nodes were
+    // visited, or `None` if `start` isn't in the graph.
+    pub fn walk<F>(&mut self, dag: &Dag, start: &str, mut visit: F) -> Option<usize> where F: FnMut(&Node) {
+        let start = dag.get(start)?;
+        self.fit(dag);
+        let slot = dag.ids.slot(&start.borrow().key).expect("Node without an id");
+        if !self.mark(slot) {
+            return Some(0);
+        }
+        self.stack.push(start);
+        let mut visited = 0;
+        while let Some(node) = self.stack.pop() {
+            dag.page_in(&node);
+            dag.resolve_payload(&node);
+            dag.discover_edges(&node);
+            let borrowed_node = node.borrow();
+            dag.cover_node(&borrowed_node.key);
+            visit(&borrowed_node);
+            visited += 1;
+            for edge in borrowed_node.edges.iter().rev() {
+                let Some(target) = dag.live_target(edge) else { continue };
+                dag.cover_edge(&borrowed_node.key, &edge.to_key);
+                let slot = dag.ids.slot(&edge.to_key).expect("Node without an id");
+                if self.mark(slot) {
+                    self.stack.push(target);
+                }
+            }
+        }
+        Some(visited)
+    }
+
+    // Starts from a clean slate, and leaves the marks from this search.
+    pub fn reaches(&mut self, dag: &Dag, from_key: &str, to_key: &str) -> bool {
+        self.reset();
+        if dag.get(to_key).is_none() {
+            return false;
+        }
+        self.walk(dag, from_key, |_| ());
+        self.is_visited(dag, to_key)
+    }
+
+    // Graphs can grow between walks; this is the only place that allocates.
+    fn fit(&mut self, dag: &Dag) {
+        let words = dag.id_bound().div_ceil(64);
+        if self.visited.len() < words {
+            self.visited.resize(words, 0);
+        }
+    }
+
+    fn test(&self, slot: usize) -> bool {
+        self.visited.get(slot / 64).is_some_and(|word| word & (1 << (slot % 64)) != 0)
+    }
+
+    // Returns false if the slot was already marked.
+    fn mark(&mut self, slot: usize) -> bool {
+        let word = &mut self.visited[slot / 64];
+        let bit = 1 << (slot % 64);
+        let fresh = *word & bit == 0;
+        *word |= bit;
+        fresh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_carry_over_until_reset() {
+        let mut dag = Dag::new();
+        for key in ["a", "b", "c", "d"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("a", "b");
+        dag.add_edge_directed("a", "c");
+        dag.add_soft_edge("c", "d");
+
+        let mut traverser = Traverser::new(&dag);
+        let mut seen = vec![];
+        assert_eq!(traverser.walk(&dag, "c", |node| seen.push(node.key.clone())), Some(2));
+        assert_eq!(traverser.walk(&dag, "a", |node| seen.push(node.key.clone())), Some(2));
+        assert_eq!(seen, vec!["c", "d", "a", "b"]);
+        assert_eq!(traverser.walk(&dag, "missing", |_| ()), None);
+
+        for i in 0..100 {
+            dag.add(&format!("e{}", i), ()).depends_on("d");
+        }
+        assert!(traverser.reaches(&dag, "a", "e99"));
+        assert!(traverser.is_visited(&dag, "b"));
+        assert!(!traverser.reaches(&dag, "b", "a"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use crate::{Dag, NodeStrongRef};
+
+// Walks edges backwards from a start node, nearest ancestors first, each
+// one once. The incoming edges are snapshotted when the walk starts.
+pub struct Ancestors<'a> {
+    dag: &'a Dag,
+    predecessors: BTreeMap<String, Vec<(String, i32)>>,
+    pending: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl Ancestors<'_> {
+    fn discover(&mut self, key: &str) {
+        let mut inputs: Vec<String> = self.predecessors.get(key).into_iter().flatten()
+            .map(|(input, _)| input.clone())
+            .collect();
+        inputs.sort();
+        for input in inputs {
+            self.dag.cover_edge(&input, key);
+            if self.seen.insert(input.clone()) {
+                self.pending.push_back(input);
+            }
+        }
+    }
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = NodeStrongRef;
+
+    fn next(&mut self) -> Option<NodeStrongRef> {
+        while let Some(key) = self.pending.pop_front() {
+            self.discover(&key);
+            if let Some(node) = self.dag.get(&key) {
+                self.dag.cover_node(&key);
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+impl Dag {
+    // Everything `start` depends on, directly or not; empty if `start`
+    // isn't in the graph. Answers "what feeds X?".
+    pub fn ancestors_iter(&self, start: &str) -> Ancestors<'_> {
+        let start = self.resolve_key(start).into_owned();
+        let predecessors = match self.get(&start) {
+            Some(_) => self.topology().predecessors(),
+            None => BTreeMap::new(),
+        };
+        let seen = HashSet::from([start.clone()]);
+        let mut ancestors = Ancestors { dag: self, predecessors, pending: VecDeque::new(), seen };
+        ancestors.discover(&start);
+        ancestors
+    }
+
+    // Calls `callback` on `start` and then on each of its ancestors, in
+    // `ancestors_iter` order.
+    pub fn traverse_up<F>(&self, start: &str, mut callback: F) where F: FnMut(NodeStrongRef) {
+        if let Some(node) = self.get(start) {
+            callback(node);
+            self.ancestors_iter(start).for_each(callback);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_against_edge_direction() {
+        let mut dag = Dag::new();
+        for key in ["raw", "lookup", "clean", "report", "unrelated"] {
+            dag.add(key, ());
+        }
+        dag.add_edge_directed("raw", "clean");
+        dag.add_edge_directed("lookup", "clean");
+        dag.add_edge_directed("raw", "lookup");
+        dag.add_edge_directed("clean", "report");
+
+        let key = |node: NodeStrongRef| node.borrow().key.clone();
+        assert_eq!(dag.ancestors_iter("report").map(key).collect::<Vec<_>>(), vec!["clean", "lookup", "raw"]);
+        let mut walked = vec![];
+        dag.traverse_up("lookup", |node| walked.push(key(node)));
+        assert_eq!(walked, vec!["lookup", "raw"]);
+        assert_eq!(dag.ancestors_iter("missing").count(), 0);
+    }
+}
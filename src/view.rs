@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Dag, DagError, Node, NodeStrongRef};
+
+struct View {
+    select: Box<dyn Fn(&Node) -> bool>,
+    invalidated: HashSet<String>,
+}
+
+// Named subsets of the graph, each with an invalidation set of its own.
+// Every `update` invalidates the node in every view; dispatching a view
+// clears only that view's set and leaves the graph's own set, its dispatch
+// ids and the other views alone.
+#[derive(Default)]
+pub(crate) struct Views {
+    views: BTreeMap<String, View>,
+}
+
+impl Views {
+    pub(crate) fn invalidate(&mut self, key: &str) {
+        for view in self.views.values_mut() {
+            view.invalidated.insert(key.to_string());
+        }
+    }
+
+    pub(crate) fn forget(&mut self, key: &str) {
+        for view in self.views.values_mut() {
+            view.invalidated.remove(key);
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<&View, DagError> {
+        self.views.get(name).ok_or_else(|| DagError::ViewNotFound(name.to_string()))
+    }
+}
+
+impl Dag {
+    // `select` is asked afresh on every dispatch, so membership follows the
+    // payloads. Redefining a view keeps its invalidation set.
+    pub fn define_view<F>(&mut self, name: &str, select: F) where F: Fn(&Node) -> bool + 'static {
+        let invalidated = self.views.views.remove(name).map(|view| view.invalidated).unwrap_or_default();
+        self.views.views.insert(name.to_string(), View { select: Box::new(select), invalidated });
+    }
+
+    pub fn remove_view(&mut self, name: &str) -> bool {
+        self.views.views.remove(name).is_some()
+    }
+
+    pub fn view_names(&self) -> Vec<String> {
+        self.views.views.keys().cloned().collect()
+    }
+
+    // Invalidates `key` for this view only. Returns false if there is no
+    // such node or view.
+    pub fn invalidate_in_view(&mut self, name: &str, key: &str) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        let Some(view) = self.views.views.get_mut(name) else { return false };
+        view.invalidated.insert(key);
+        true
+    }
+
+    // Members of the view that its next dispatch would run, in key order.
+    pub fn view_dirty(&self, name: &str) -> Result<Vec<String>, DagError> {
+        let view = self.views.get(name).map_err(|err| err.during("view_dirty"))?;
+        let mut dirty: Vec<String> = self.region_below(view.invalidated.iter())?.keys()
+            .filter(|key| self.is_view_member(view, key))
+            .cloned()
+            .collect();
+        dirty.sort();
+        Ok(dirty)
+    }
+
+    // Runs the view's members downstream of what it saw invalidated, in
+    // dependency order. Invalidation spreads through non-members as usual;
+    // they just aren't handed to `callback`. Returns the keys run.
+    pub fn dispatch_view<F>(&mut self, name: &str, mut callback: F) -> Result<Vec<String>, DagError> where F: FnMut(NodeStrongRef) {
+        let view = self.views.get(name).map_err(|err| err.during("dispatch_view"))?;
+        let region = self.region_below(view.invalidated.iter()).map_err(|err| err.during("dispatch_view"))?;
+        let order = region.topological_order().map_err(|err| err.during("dispatch_view"))?;
+        let members: Vec<String> = order.into_iter().filter(|key| self.is_view_member(view, key)).collect();
+        for key in members.iter() {
+            callback(self.get(key).expect("Region node missing"));
+        }
+        self.views.views.get_mut(name).expect("View checked above").invalidated.clear();
+        Ok(members)
+    }
+
+    fn is_view_member(&self, view: &View, key: &str) -> bool {
+        self.get(key).is_some_and(|node| (view.select)(&node.borrow()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Meshes feed a scene that both the renderer and the saver care about;
+    // only the saver cares about the raw assets.
+    fn scene() -> Dag {
+        let mut dag = Dag::new();
+        dag.add("asset:mesh", ());
+        dag.add("render:mesh", ()).depends_on("asset:mesh");
+        dag.add("render:scene", ()).depends_on("render:mesh");
+        dag.add("save:scene", ()).depends_on("render:scene");
+        dag.define_view("render", |node| node.key.starts_with("render:"));
+        dag.define_view("persist", |node| !node.key.starts_with("render:"));
+        dag
+    }
+
+    #[test]
+    fn views_dispatch_independently() {
+        let mut dag = scene();
+        dag.update("asset:mesh", ());
+        assert_eq!(dag.view_dirty("render").unwrap(), vec!["render:mesh", "render:scene"]);
+
+        let mut seen = vec![];
+        let ran = dag.dispatch_view("render", |node| seen.push(node.borrow().key.clone())).unwrap();
+        assert_eq!(ran, seen);
+        assert_eq!(ran, vec!["render:mesh", "render:scene"]);
+        assert!(dag.view_dirty("render").unwrap().is_empty());
+        assert_eq!(dag.view_dirty("persist").unwrap(), vec!["asset:mesh", "save:scene"]);
+        assert!(dag.invalidated.contains("asset:mesh"));
+
+        assert!(dag.invalidate_in_view("render", "render:scene"));
+        assert_eq!(dag.view_dirty("render").unwrap(), vec!["render:scene"]);
+        assert_eq!(dag.dispatch_view("persist", |_| ()).unwrap(), vec!["asset:mesh", "save:scene"]);
+        assert_eq!(dag.view_dirty("render").unwrap(), vec!["render:scene"]);
+    }
+
+    #[test]
+    fn unknown_views_are_errors() {
+        let mut dag = scene();
+        assert!(!dag.invalidate_in_view("audit", "render:scene"));
+        assert_eq!(dag.view_dirty("audit").unwrap_err().root(), &DagError::ViewNotFound("audit".to_string()));
+        let err = dag.dispatch_view("audit", |_| ()).unwrap_err();
+        assert_eq!(err, DagError::ViewNotFound("audit".to_string()).during("dispatch_view"));
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+
+use crate::{Dag, DagError};
+
+// When a node may run, on top of waiting for its dependencies. Either end
+// may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub not_before: Option<SystemTime>,
+    pub not_after: Option<SystemTime>,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, now: SystemTime) -> bool {
+        self.not_before.is_none_or(|start| start <= now) && self.not_after.is_none_or(|end| now <= end)
+    }
+}
+
+impl Dag {
+    // Returns false if `key` isn't in the graph. An empty window clears it.
+    pub fn set_window(&mut self, key: &str, window: TimeWindow) -> bool {
+        let key = self.resolve_key(key).into_owned();
+        if self.get(&key).is_none() {
+            return false;
+        }
+        if window == TimeWindow::default() {
+            self.windows.remove(&key);
+        } else {
+            self.windows.insert(key, window);
+        }
+        true
+    }
+
+    pub fn window(&self, key: &str) -> TimeWindow {
+        self.windows.get(self.resolve_key(key).as_ref()).copied().unwrap_or_default()
+    }
+
+    // The ready nodes of the current run whose window is open at `now`, most
+    // urgent first. Nodes whose window has not opened yet stay ready and show
+    // up here once it does.
+    pub fn ready_at(&mut self, now: SystemTime) -> Result<Vec<String>, DagError> {
+        let ready = self.ready_nodes()?;
+        Ok(ready.into_iter().filter(|key| self.window(key).contains(now)).collect())
+    }
+
+    // Ready nodes whose window closed before `now`; they will never be
+    // returned by `ready_at` again unless their window is moved.
+    pub fn missed_windows(&mut self, now: SystemTime) -> Result<Vec<String>, DagError> {
+        let ready = self.ready_nodes()?;
+        Ok(ready.into_iter().filter(|key| self.window(key).not_after.is_some_and(|end| end < now)).collect())
+    }
+
+    // When the next ready node's window opens, for executors deciding how
+    // long to sleep.
+    pub fn next_window_opening(&mut self, now: SystemTime) -> Result<Option<SystemTime>, DagError> {
+        let ready = self.ready_nodes()?;
+        Ok(ready.iter().filter_map(|key| self.window(key).not_before).filter(|start| *start > now).min())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn clock_and_dependencies_both_gate_readiness() {
+        let midnight = SystemTime::UNIX_EPOCH + Duration::from_secs(86_400);
+        let mut dag = Dag::new();
+        for key in ["extract", "nightly", "report"] {
+            dag.add(key, ());
+        }
+        dag.link("extract", "report", 1);
+        dag.dispatch(|_| ());
+        dag.update("extract", ());
+        dag.update("nightly", ());
+        let first_hour = TimeWindow { not_before: Some(midnight), not_after: Some(midnight + Duration::from_secs(3_600)) };
+        dag.set_window("nightly", first_hour);
+        dag.set_window("report", TimeWindow { not_before: Some(midnight), not_after: None });
+
+        let evening = midnight - Duration::from_secs(600);
+        assert_eq!(dag.ready_at(evening).unwrap(), vec!["extract".to_string()]);
+        assert_eq!(dag.next_window_opening(evening).unwrap(), Some(midnight));
+        dag.mark_complete("extract").unwrap();
+        assert!(dag.ready_at(evening).unwrap().is_empty());
+
+        assert_eq!(dag.ready_at(midnight).unwrap(), vec!["nightly".to_string(), "report".to_string()]);
+        let late = midnight + Duration::from_secs(7_200);
+        assert_eq!(dag.ready_at(late).unwrap(), vec!["report".to_string()]);
+        assert_eq!(dag.missed_windows(late).unwrap(), vec!["nightly".to_string()]);
+    }
+}
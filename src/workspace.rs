@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::topology::Topology;
+use crate::{Dag, DagError, DispatchId, NodeStrongRef};
+
+// A node in one of a workspace's graphs. Errors and cycle paths spell it
+// `graph:key`, which is why graph names may not contain a colon.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeRef {
+    pub graph: String,
+    pub key: String,
+}
+
+impl NodeRef {
+    pub fn new(graph: &str, key: &str) -> NodeRef {
+        NodeRef { graph: graph.to_string(), key: key.to_string() }
+    }
+
+    fn qualified(&self) -> String {
+        self.to_string()
+    }
+
+    fn parse(qualified: &str) -> NodeRef {
+        let (graph, key) = qualified.split_once(':').expect("Qualified key without a graph");
+        NodeRef::new(graph, key)
+    }
+}
+
+impl fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.graph, self.key)
+    }
+}
+
+// A cross-graph edge with an end that no longer exists. Edges refer to
+// nodes by name, so one comes back to life if its missing end is added
+// again; until then it is skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    pub from: NodeRef,
+    pub to: NodeRef,
+    pub missing: Vec<NodeRef>,
+}
+
+// Named graphs plus edges between them. Cross-graph edges are hard edges:
+// invalidation and ordering both follow them.
+#[derive(Default)]
+pub struct Workspace {
+    graphs: BTreeMap<String, Dag>,
+    cross_edges: Vec<(NodeRef, NodeRef, i32)>,
+}
+
+impl Workspace {
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    // Returns the graph previously stored under `name`.
+    pub fn insert_graph(&mut self, name: &str, dag: Dag) -> Option<Dag> {
+        assert!(!name.contains(':'), "Graph names may not contain ':'");
+        self.graphs.insert(name.to_string(), dag)
+    }
+
+    // Cross-graph edges touching the graph go with it.
+    pub fn remove_graph(&mut self, name: &str) -> Option<Dag> {
+        self.cross_edges.retain(|(from, to, _)| from.graph != name && to.graph != name);
+        self.graphs.remove(name)
+    }
+
+    pub fn graph(&self, name: &str) -> Option<&Dag> {
+        self.graphs.get(name)
+    }
+
+    pub fn graph_mut(&mut self, name: &str) -> Option<&mut Dag> {
+        self.graphs.get_mut(name)
+    }
+
+    pub fn graph_names(&self) -> Vec<String> {
+        self.graphs.keys().cloned().collect()
+    }
+
+    pub fn cross_edges(&self) -> Vec<(NodeRef, NodeRef)> {
+        self.cross_edges.iter().map(|(from, to, _)| (from.clone(), to.clone())).collect()
+    }
+
+    // Removes the node and reports the cross-graph edges this broke. Nodes
+    // removed straight from a graph break their edges the same way; see
+    // `broken_links`.
+    pub fn remove_node(&mut self, node_ref: &NodeRef) -> Vec<BrokenLink> {
+        let removed = self.graphs.get_mut(&node_ref.graph).is_some_and(|dag| dag.remove(&node_ref.key));
+        if !removed {
+            return vec![];
+        }
+        self.broken_links().into_iter()
+            .filter(|link| link.missing.contains(node_ref))
+            .collect()
+    }
+
+    pub fn broken_links(&self) -> Vec<BrokenLink> {
+        self.cross_edges.iter()
+            .filter_map(|(from, to, _)| {
+                let missing: Vec<NodeRef> = [from, to].into_iter()
+                    .filter(|end| self.resolve(end).is_err())
+                    .cloned()
+                    .collect();
+                (!missing.is_empty()).then(|| BrokenLink { from: from.clone(), to: to.clone(), missing })
+            })
+            .collect()
+    }
+
+    // Forgets the broken edges for good, returning them.
+    pub fn prune_broken_links(&mut self) -> Vec<BrokenLink> {
+        let broken = self.broken_links();
+        self.cross_edges.retain(|(from, to, _)| !broken.iter().any(|link| &link.from == from && &link.to == to));
+        broken
+    }
+
+    // `to` runs after `from`. Rejected if either end is missing or if the
+    // edge would close a cycle through any of the graphs.
+    pub fn add_cross_edge(&mut self, from: NodeRef, to: NodeRef) -> Result<(), DagError> {
+        for end in [&from, &to] {
+            self.resolve(end)?;
+        }
+        let topology = self.topology()?;
+        if let Some(path) = topology.path(&to.qualified(), &from.qualified()) {
+            return Err(DagError::WouldCreateCycle { from: from.qualified(), to: to.qualified(), path });
+        }
+        self.cross_edges.push((from, to, 1));
+        Ok(())
+    }
+
+    // Every node of every graph, each after everything it depends on in any
+    // graph. Ties go to the smaller `graph:key`.
+    pub fn topological_order(&self) -> Result<Vec<NodeRef>, DagError> {
+        let order = self.topology()?.topological_order()?;
+        Ok(order.iter().map(|qualified| NodeRef::parse(qualified)).collect())
+    }
+
+    // Runs the invalidated nodes of every graph and everything downstream
+    // of them, across graphs, in workspace order. Each graph that had work
+    // records the run under an id of its own, returned by graph name.
+    pub fn dispatch<F>(&mut self, mut callback: F) -> Result<BTreeMap<String, DispatchId>, DagError>
+        where F: FnMut(&NodeRef, NodeStrongRef) {
+        let topology = self.topology()?;
+        let roots: Vec<String> = self.graphs.iter()
+            .flat_map(|(name, dag)| dag.invalidated.iter().map(move |key| NodeRef::new(name, key).qualified()))
+            .collect();
+        let mut dirty: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = roots.into_iter().filter(|key| topology.contains(key)).collect();
+        while let Some(key) = stack.pop() {
+            let node_ref = NodeRef::parse(&key);
+            if dirty.insert(key.clone()) && !self.graphs[&node_ref.graph].is_barrier(&node_ref.key) {
+                stack.extend(topology.hard_successors(&key).map(|next| next.to_string()));
+            }
+        }
+        let order: Vec<NodeRef> = topology.topological_order()?.into_iter()
+            .filter(|key| dirty.contains(key))
+            .map(|key| NodeRef::parse(&key))
+            .collect();
+
+        let mut dispatched: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        for node_ref in order {
+            let node = self.resolve(&node_ref)?;
+            callback(&node_ref, node);
+            dispatched.entry(node_ref.graph).or_default().insert(node_ref.key);
+        }
+        let mut ids = BTreeMap::new();
+        for (name, keys) in dispatched {
+            let dag = self.graphs.get_mut(&name).expect("Dispatched graph missing");
+            let id = dag.next_dispatch_id();
+            dag.mark_dispatched(keys, id);
+            dag.clear_invalidated();
+            ids.insert(name, id);
+        }
+        Ok(ids)
+    }
+
+    fn resolve(&self, node_ref: &NodeRef) -> Result<NodeStrongRef, DagError> {
+        self.graphs.get(&node_ref.graph)
+            .and_then(|dag| dag.get(&node_ref.key))
+            .ok_or_else(|| DagError::NodeNotFound(node_ref.qualified()))
+    }
+
+    fn topology(&self) -> Result<Topology, DagError> {
+        let mut combined = Topology::default();
+        for (name, dag) in self.graphs.iter() {
+            let topology = dag.try_topology("workspace")?;
+            for key in topology.keys() {
+                let from = NodeRef::new(name, key).qualified();
+                combined.insert_node(&from);
+                for (next, weight) in topology.successors(key) {
+                    let to = NodeRef::new(name, next).qualified();
+                    combined.insert_edge(&from, &to, *weight);
+                    if topology.is_soft(key, next) {
+                        combined.mark_soft(&from, &to);
+                    }
+                }
+            }
+        }
+        for (from, to, weight) in self.cross_edges.iter() {
+            if self.resolve(from).is_ok() && self.resolve(to).is_ok() {
+                combined.insert_edge(&from.qualified(), &to.qualified(), *weight);
+            }
+        }
+        Ok(combined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `app` links against `lib`, which depends on `core` in another project.
+    fn monorepo() -> Workspace {
+        let mut core = Dag::new();
+        core.add("core", ());
+        let mut lib = Dag::new();
+        lib.add("lib", ());
+        let mut app = Dag::new();
+        app.add("build", ());
+        app.add("app", ()).depends_on("build");
+
+        let mut workspace = Workspace::new();
+        workspace.insert_graph("core", core);
+        workspace.insert_graph("lib", lib);
+        workspace.insert_graph("app", app);
+        workspace.add_cross_edge(NodeRef::new("core", "core"), NodeRef::new("lib", "lib")).unwrap();
+        workspace.add_cross_edge(NodeRef::new("lib", "lib"), NodeRef::new("app", "app")).unwrap();
+        workspace
+    }
+
+    #[test]
+    fn order_and_dispatch_cross_graph_boundaries() {
+        let mut workspace = monorepo();
+        let order: Vec<String> = workspace.topological_order().unwrap().iter().map(|node| node.to_string()).collect();
+        assert_eq!(order, vec!["app:build", "core:core", "lib:lib", "app:app"]);
+
+        workspace.graph_mut("core").unwrap().update("core", ());
+        let mut seen = vec![];
+        let ids = workspace.dispatch(|node_ref, _| seen.push(node_ref.to_string())).unwrap();
+        assert_eq!(seen, vec!["core:core", "lib:lib", "app:app"]);
+        assert_eq!(ids.keys().collect::<Vec<_>>(), vec!["app", "core", "lib"]);
+        assert_eq!(workspace.graph("app").unwrap().last_dispatched("build"), None);
+    }
+
+    #[test]
+    fn removed_nodes_break_links_without_panicking() {
+        let mut workspace = monorepo();
+        let lib = NodeRef::new("lib", "lib");
+        let broken = workspace.remove_node(&lib);
+        assert_eq!(broken, vec![
+            BrokenLink { from: NodeRef::new("core", "core"), to: lib.clone(), missing: vec![lib.clone()] },
+            BrokenLink { from: lib.clone(), to: NodeRef::new("app", "app"), missing: vec![lib.clone()] },
+        ]);
+        workspace.graph_mut("core").unwrap().update("core", ());
+        let mut seen = vec![];
+        workspace.dispatch(|node_ref, _| seen.push(node_ref.to_string())).unwrap();
+        assert_eq!(seen, vec!["core:core"]);
+
+        workspace.graph_mut("lib").unwrap().add("lib", ());
+        assert!(workspace.broken_links().is_empty());
+        workspace.graph_mut("app").unwrap().remove("app");
+        assert_eq!(workspace.prune_broken_links().len(), 1);
+        assert_eq!(workspace.cross_edges(), vec![(NodeRef::new("core", "core"), lib)]);
+    }
+
+    #[test]
+    fn cross_graph_cycles_are_rejected() {
+        let mut workspace = monorepo();
+        let err = workspace.add_cross_edge(NodeRef::new("app", "app"), NodeRef::new("core", "core")).unwrap_err();
+        assert_eq!(err, DagError::WouldCreateCycle {
+            from: "app:app".to_string(),
+            to: "core:core".to_string(),
+            path: vec!["core:core".to_string(), "lib:lib".to_string(), "app:app".to_string()],
+        });
+        assert_eq!(
+            workspace.add_cross_edge(NodeRef::new("app", "missing"), NodeRef::new("core", "core")),
+            Err(DagError::NodeNotFound("app:missing".to_string()))
+        );
+    }
+}